@@ -0,0 +1,323 @@
+use std::io;
+
+use const_oid::{
+    ObjectIdentifier,
+    db::{rfc5911::ID_SIGNED_DATA, rfc5912::ID_AD_CA_ISSUERS},
+};
+use der::{Any, Decode as _, Sequence};
+use eyre::{WrapErr as _, bail};
+use x509_cert::{
+    Certificate,
+    ext::pkix::{AuthorityInfoAccessSyntax, name::GeneralName},
+};
+
+use crate::{
+    cert::{Cert, is_issuer},
+    http::Fetcher,
+    signature::{self, Error},
+};
+
+/// Upper bound on the number of issuers fetched for one chain, guarding against AIA loops.
+const MAX_FETCHED: usize = 8;
+
+/// PKCS#7 content info as defined in [RFC 5652 §3].
+///
+/// [RFC 5652 §3]: https://datatracker.ietf.org/doc/html/rfc5652#section-3
+#[derive(Debug, Sequence)]
+struct ContentInfo {
+    content_type: ObjectIdentifier,
+
+    #[asn1(context_specific = "0", tag_mode = "EXPLICIT")]
+    content: Any,
+}
+
+/// PKCS#7 signed data as defined in [RFC 5652 §5.1], of which only the certificates are used.
+///
+/// ```text
+/// SignedData ::= SEQUENCE {
+///    version CMSVersion,
+///    digestAlgorithms DigestAlgorithmIdentifiers,
+///    encapContentInfo EncapsulatedContentInfo,
+///    certificates [0] IMPLICIT CertificateSet OPTIONAL,
+///    crls [1] IMPLICIT RevocationInfoChoices OPTIONAL,
+///    signerInfos SignerInfos }
+/// ```
+///
+/// [RFC 5652 §5.1]: https://datatracker.ietf.org/doc/html/rfc5652#section-5.1
+#[derive(Debug, Sequence)]
+struct SignedData {
+    version: u8,
+    digest_algorithms: Any,
+    encap_content_info: Any,
+
+    #[asn1(context_specific = "0", tag_mode = "IMPLICIT", optional = "true")]
+    certificates: Option<Vec<Cert>>,
+
+    #[asn1(context_specific = "1", tag_mode = "IMPLICIT", optional = "true")]
+    crls: Option<Vec<Any>>,
+
+    signer_infos: Any,
+}
+
+/// Issuer certificate downloaded from a CA Issuers URL.
+#[derive(Debug)]
+pub(crate) struct Fetched {
+    pub(crate) cert: Cert,
+    pub(crate) url: String,
+}
+
+/// Result of following CA Issuers URLs from the end of a chain.
+#[derive(Debug, Default)]
+pub(crate) struct Completion {
+    /// Fetched issuers, in chain order.
+    pub(crate) fetched: Vec<Fetched>,
+
+    /// Problems which stopped (or were encountered while) completing the chain.
+    pub(crate) errors: Vec<String>,
+}
+
+/// Returns URLs of the given access method from a certificate's Authority Information Access.
+pub(crate) fn access_urls(cert: &Certificate, method: ObjectIdentifier) -> Vec<String> {
+    let Ok(Some((_, aia))) = cert
+        .tbs_certificate()
+        .get_extension::<AuthorityInfoAccessSyntax>()
+    else {
+        return Vec::new();
+    };
+
+    aia.0
+        .iter()
+        .filter(|desc| desc.access_method == method)
+        .filter_map(|desc| match &desc.access_location {
+            GeneralName::UniformResourceIdentifier(uri) => Some(uri.as_str().to_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Follows CA Issuers URLs until the chain ends in a self-issued certificate or an issuer which is
+/// already present.
+pub(crate) fn complete_chain(certs: &[Cert], fetcher: &impl Fetcher) -> Completion {
+    let mut completion = Completion::default();
+
+    while completion.fetched.len() < MAX_FETCHED {
+        let idx = certs.len() + completion.fetched.len() - 1;
+        let last = completion
+            .fetched
+            .last()
+            .map_or(&certs[certs.len() - 1], |fetched| &fetched.cert);
+
+        let issuer_present = certs
+            .iter()
+            .chain(completion.fetched.iter().map(|fetched| &fetched.cert))
+            .any(|cert| is_issuer(cert, last));
+
+        if issuer_present {
+            break;
+        }
+
+        let urls = access_urls(last, ID_AD_CA_ISSUERS);
+
+        if urls.is_empty() {
+            completion
+                .errors
+                .push(format!("certificate #{idx} has no CA Issuers URL"));
+            break;
+        }
+
+        let mut found = None;
+
+        for url in urls {
+            let candidates = match fetcher.get(&url).and_then(|data| parse_certs(&data)) {
+                Ok(candidates) => candidates,
+                Err(err) => {
+                    completion.errors.push(format!("{err:#}"));
+                    continue;
+                }
+            };
+
+            let issuer = candidates.into_iter().find(|candidate| {
+                is_issuer(candidate, last)
+                    && !matches!(
+                        signature::verify_cert(last, candidate),
+                        Err(Error::Invalid(_))
+                    )
+            });
+
+            match issuer {
+                Some(cert) => {
+                    found = Some(Fetched { cert, url });
+                    break;
+                }
+                None => completion.errors.push(format!(
+                    "{url} did not provide the issuer of certificate #{idx}"
+                )),
+            }
+        }
+
+        match found {
+            Some(fetched) => {
+                tracing::info!(url = %fetched.url, "fetched issuer of certificate #{idx}");
+                completion.fetched.push(fetched);
+            }
+            None => break,
+        }
+    }
+
+    completion
+}
+
+/// Writes fetched issuers and any problems encountered in text form.
+pub(crate) fn write_report(
+    first_idx: usize,
+    completion: &Completion,
+    mut wrt: impl io::Write,
+) -> io::Result<()> {
+    writeln!(wrt, "Issuer Fetching (AIA)")?;
+    writeln!(wrt, "=====================")?;
+    writeln!(wrt, "Fetched: {}", completion.fetched.len())?;
+
+    for (idx, fetched) in (first_idx..).zip(&completion.fetched) {
+        writeln!(
+            wrt,
+            "  [{idx}] {} (from {})",
+            fetched.cert.tbs_certificate().subject(),
+            fetched.url
+        )?;
+    }
+
+    for err in &completion.errors {
+        writeln!(wrt, "Error: {err}")?;
+    }
+
+    Ok(())
+}
+
+/// Parses certificates from a CA Issuers response: DER, PEM or a PKCS#7 certs-only bundle.
+fn parse_certs(data: &[u8]) -> eyre::Result<Vec<Cert>> {
+    if data.starts_with(b"-----BEGIN") {
+        return rustls_pemfile::certs(&mut io::Cursor::new(data))
+            .map(|der| Ok(Cert::from_der(&der?)?))
+            .collect();
+    }
+
+    if let Ok(cert) = Cert::from_der(data) {
+        return Ok(vec![cert]);
+    }
+
+    let info = ContentInfo::from_der(data).wrap_err("response is not a certificate or PKCS#7")?;
+
+    if info.content_type != ID_SIGNED_DATA {
+        bail!("PKCS#7 content is not signed data");
+    }
+
+    let signed_data: SignedData = info.content.decode_as()?;
+
+    Ok(signed_data.certificates.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cert::fixtures,
+        http::{CachingFetcher, StubFetcher},
+    };
+
+    const CA_ISSUERS_URL: &str = "http://ca.test/ca.der";
+
+    fn ca_der() -> Vec<u8> {
+        fixtures::certs("pki/ca.pem")[0].der().to_vec()
+    }
+
+    #[test]
+    fn fetches_missing_issuer() {
+        let certs = fixtures::certs("pki/good.pem");
+        let fetcher = StubFetcher::default().with(CA_ISSUERS_URL, ca_der());
+
+        let completion = complete_chain(&certs, &fetcher);
+
+        assert_eq!(completion.errors, Vec::<String>::new());
+        assert_eq!(completion.fetched.len(), 1);
+        assert_eq!(completion.fetched[0].url, CA_ISSUERS_URL);
+        assert_eq!(completion.fetched[0].cert.der(), ca_der());
+
+        // fetching stops at the self-signed root
+        assert_eq!(*fetcher.requests.borrow(), [CA_ISSUERS_URL]);
+    }
+
+    #[test]
+    fn accepts_pem_and_pkcs7() {
+        let certs = fixtures::certs("pki/good.pem");
+
+        for data in [fixtures::read("pki/ca.pem"), fixtures::read("pki/ca.p7c")] {
+            let fetcher = StubFetcher::default().with(CA_ISSUERS_URL, data);
+            let completion = complete_chain(&certs, &fetcher);
+
+            assert_eq!(completion.fetched[0].cert.der(), ca_der());
+        }
+    }
+
+    #[test]
+    fn skips_complete_chain() {
+        let certs = fixtures::chain(&["pki/good.pem", "pki/ca.pem"]);
+        let fetcher = StubFetcher::default();
+
+        let completion = complete_chain(&certs, &fetcher);
+
+        assert!(completion.fetched.is_empty());
+        assert!(completion.errors.is_empty());
+        assert!(fetcher.requests.borrow().is_empty());
+    }
+
+    #[test]
+    fn rejects_wrong_issuer() {
+        let certs = fixtures::certs("pki/good.pem");
+        let other_ca = fixtures::certs("pki/other-ca.pem")[0].der().to_vec();
+        let fetcher = StubFetcher::default().with(CA_ISSUERS_URL, other_ca);
+
+        let completion = complete_chain(&certs, &fetcher);
+
+        assert!(completion.fetched.is_empty());
+        assert_eq!(
+            completion.errors,
+            [format!(
+                "{CA_ISSUERS_URL} did not provide the issuer of certificate #0"
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_fetch_errors() {
+        let certs = fixtures::certs("pki/good.pem");
+
+        let completion = complete_chain(&certs, &StubFetcher::default());
+
+        assert!(completion.fetched.is_empty());
+        assert_eq!(completion.errors.len(), 1);
+        assert!(completion.errors[0].contains("HTTP status 404"));
+    }
+
+    #[test]
+    fn reports_missing_url() {
+        let certs = fixtures::certs("ct/leaf.pem");
+
+        let completion = complete_chain(&certs, &StubFetcher::default());
+
+        assert_eq!(completion.errors, ["certificate #0 has no CA Issuers URL"]);
+    }
+
+    #[test]
+    fn uses_cache_dir() {
+        let certs = fixtures::certs("pki/good.pem");
+
+        // any request reaching the stub would fail
+        let fetcher =
+            CachingFetcher::new(StubFetcher::default()).with_dir(fixtures::path("aia-cache"));
+
+        let completion = complete_chain(&certs, &fetcher);
+
+        assert!(completion.errors.is_empty());
+        assert_eq!(completion.fetched[0].cert.der(), ca_der());
+    }
+}
@@ -0,0 +1,595 @@
+//! Generic DER decoding, used to render structures that have no dedicated decoder.
+
+use std::{
+    fmt, fs,
+    io::{self, Read as _},
+};
+
+use const_oid::ObjectIdentifier;
+use eyre::{WrapErr as _, bail, eyre};
+use itertools::Itertools as _;
+
+use crate::model::Oid;
+
+/// Maximum nesting depth accepted by [`parse`].
+const MAX_DEPTH: usize = 64;
+
+/// Number of bytes shown per line of hex.
+const HEX_WIDTH: usize = 16;
+
+/// Options for the `asn1` subcommand.
+#[derive(Debug, clap::Args)]
+pub(crate) struct Args {
+    /// DER or PEM file to dump; PEM files may hold any number of blocks of any type. Use - for stdin.
+    #[arg(value_name = "FILE")]
+    file: camino::Utf8PathBuf,
+
+    /// Only dump the element starting at this byte offset.
+    #[arg(long, conflicts_with = "path")]
+    offset: Option<usize>,
+
+    /// Only dump the element at this dot-separated path of child indices, starting with the index of
+    /// the top-level element (e.g. 0.0.6 is a certificate's subject public key info).
+    #[arg(long, value_delimiter = '.')]
+    path: Vec<usize>,
+}
+
+/// Class of an ASN.1 tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Class {
+    Universal,
+    Application,
+    ContextSpecific,
+    Private,
+}
+
+/// Identifier of a DER element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Tag {
+    pub(crate) class: Class,
+    pub(crate) constructed: bool,
+    pub(crate) number: u32,
+}
+
+impl Tag {
+    /// Returns the name of a universal tag, if known.
+    fn universal_name(self) -> Option<&'static str> {
+        if self.class != Class::Universal {
+            return None;
+        }
+
+        Some(match self.number {
+            0 => "EOC",
+            1 => "BOOLEAN",
+            2 => "INTEGER",
+            3 => "BIT STRING",
+            4 => "OCTET STRING",
+            5 => "NULL",
+            6 => "OBJECT IDENTIFIER",
+            7 => "ObjectDescriptor",
+            8 => "EXTERNAL",
+            9 => "REAL",
+            10 => "ENUMERATED",
+            11 => "EMBEDDED PDV",
+            12 => "UTF8String",
+            13 => "RELATIVE-OID",
+            16 => "SEQUENCE",
+            17 => "SET",
+            18 => "NumericString",
+            19 => "PrintableString",
+            20 => "TeletexString",
+            21 => "VideotexString",
+            22 => "IA5String",
+            23 => "UTCTime",
+            24 => "GeneralizedTime",
+            25 => "GraphicString",
+            26 => "VisibleString",
+            27 => "GeneralString",
+            28 => "UniversalString",
+            30 => "BMPString",
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.class, self.universal_name()) {
+            (_, Some(name)) => f.write_str(name),
+            (Class::Universal, None) => write!(f, "[UNIVERSAL {}]", self.number),
+            (Class::Application, _) => write!(f, "[APPLICATION {}]", self.number),
+            (Class::ContextSpecific, _) => write!(f, "[{}]", self.number),
+            (Class::Private, _) => write!(f, "[PRIVATE {}]", self.number),
+        }
+    }
+}
+
+/// Decoded DER element.
+#[derive(Debug)]
+pub(crate) struct Node<'a> {
+    /// Offset of the element's first identifier byte in the parsed input.
+    pub(crate) offset: usize,
+
+    /// Length of the identifier and length octets.
+    pub(crate) header_len: usize,
+
+    pub(crate) tag: Tag,
+
+    /// Content octets.
+    pub(crate) content: &'a [u8],
+
+    /// Elements nested in a constructed element, or encapsulated in an OCTET STRING or BIT STRING.
+    pub(crate) children: Vec<Node<'a>>,
+}
+
+impl Node<'_> {
+    /// Returns true if the children were decoded from the contents of a primitive string.
+    pub(crate) fn is_encapsulating(&self) -> bool {
+        !self.tag.constructed && !self.children.is_empty()
+    }
+
+    /// Formats the element header, and the value of primitive elements, for text output.
+    ///
+    /// Values too long for a single line are returned as additional hex lines.
+    fn fmt_text(&self, show_len: bool) -> (String, Vec<String>) {
+        let len = self.content.len();
+        let mut header = self.tag.to_string();
+
+        if show_len {
+            header.push_str(&format!(" ({len} byte{})", if len == 1 { "" } else { "s" }));
+        }
+
+        if self.tag.constructed {
+            return (header, Vec::new());
+        }
+
+        let mut bytes = self.content;
+
+        if self.tag.class == Class::Universal
+            && self.tag.number == 3
+            && let Some((&unused, rest)) = bytes.split_first()
+        {
+            if unused != 0 {
+                header.push_str(&format!(", {unused} unused bits"));
+            }
+            bytes = rest;
+        }
+
+        if self.is_encapsulating() {
+            header.push_str(", encapsulates");
+            return (header, Vec::new());
+        }
+
+        if let Some(value) = self.primitive_value() {
+            return (format!("{header}: {value}"), Vec::new());
+        }
+
+        match bytes.len() {
+            0 => (header, Vec::new()),
+            n if n <= HEX_WIDTH => (format!("{header}: {}", hex(bytes)), Vec::new()),
+            _ => (header, bytes.chunks(HEX_WIDTH).map(hex).collect()),
+        }
+    }
+
+    /// Decodes the value of primitive universal types with a textual form.
+    fn primitive_value(&self) -> Option<String> {
+        if self.tag.class != Class::Universal || self.tag.constructed {
+            return None;
+        }
+
+        let bytes = self.content;
+
+        match self.tag.number {
+            1 => Some(match bytes {
+                [0] => "FALSE".to_owned(),
+                _ => "TRUE".to_owned(),
+            }),
+            2 | 10 if !bytes.is_empty() && bytes.len() <= 16 => {
+                let fill = if bytes[0] & 0x80 != 0 { 0xff } else { 0x00 };
+                let mut buf = [fill; 16];
+                buf[16 - bytes.len()..].copy_from_slice(bytes);
+                Some(i128::from_be_bytes(buf).to_string())
+            }
+            6 => {
+                let oid = Oid::new(&ObjectIdentifier::from_bytes(bytes).ok()?);
+                Some(match oid.name {
+                    Some(name) => format!("{} ({name})", oid.oid),
+                    None => oid.oid,
+                })
+            }
+            12 => Some(format!("{:?}", std::str::from_utf8(bytes).ok()?)),
+            18 | 19 | 22 | 23 | 24 | 26 if bytes.is_ascii() => {
+                Some(format!("{:?}", std::str::from_utf8(bytes).ok()?))
+            }
+            20 => Some(format!(
+                "{:?}",
+                bytes
+                    .iter()
+                    .map(|&byte| char::from(byte))
+                    .collect::<String>()
+            )),
+            28 if bytes.len().is_multiple_of(4) => Some(format!(
+                "{:?}",
+                bytes
+                    .chunks_exact(4)
+                    .map(|quad| char::from_u32(u32::from_be_bytes([
+                        quad[0], quad[1], quad[2], quad[3]
+                    ])))
+                    .collect::<Option<String>>()?
+            )),
+            30 if bytes.len().is_multiple_of(2) => Some(format!(
+                "{:?}",
+                char::decode_utf16(
+                    bytes
+                        .chunks_exact(2)
+                        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                )
+                .collect::<Result<String, _>>()
+                .ok()?
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a sequence of DER elements that must span the whole input.
+pub(crate) fn parse(der: &[u8]) -> eyre::Result<Vec<Node<'_>>> {
+    parse_at(der, 0, 0)
+}
+
+fn parse_at(der: &[u8], base: usize, depth: usize) -> eyre::Result<Vec<Node<'_>>> {
+    if depth > MAX_DEPTH {
+        bail!("nesting deeper than {MAX_DEPTH} levels at offset {base}");
+    }
+
+    let mut nodes = Vec::new();
+    let mut pos = 0;
+
+    while pos < der.len() {
+        let offset = base + pos;
+        let (tag, header_len, len) = parse_header(&der[pos..])
+            .map_err(|err| eyre!("invalid element at offset {offset}: {err}"))?;
+
+        let start = pos + header_len;
+        let end = start
+            .checked_add(len)
+            .filter(|&end| end <= der.len())
+            .ok_or_else(|| eyre!("element at offset {offset} with length {len} overruns input"))?;
+        let content = &der[start..end];
+
+        let children = if tag.constructed {
+            parse_at(content, base + start, depth + 1)?
+        } else {
+            encapsulated(tag, content, base + start, depth)
+        };
+
+        nodes.push(Node {
+            offset,
+            header_len,
+            tag,
+            content,
+            children,
+        });
+
+        pos = end;
+    }
+
+    Ok(nodes)
+}
+
+/// Parses the identifier and length octets, returning the tag, header length and content length.
+fn parse_header(der: &[u8]) -> eyre::Result<(Tag, usize, usize)> {
+    let mut bytes = der.iter().copied();
+    let mut next = || bytes.next().ok_or_else(|| eyre!("truncated header"));
+
+    let ident = next()?;
+    let class = match ident >> 6 {
+        0 => Class::Universal,
+        1 => Class::Application,
+        2 => Class::ContextSpecific,
+        _ => Class::Private,
+    };
+    let constructed = ident & 0x20 != 0;
+
+    let mut header_len = 1;
+    let mut number = u32::from(ident & 0x1f);
+
+    if number == 0x1f {
+        number = 0;
+        loop {
+            let byte = next()?;
+            header_len += 1;
+
+            if number > u32::MAX >> 7 {
+                bail!("tag number too large");
+            }
+            number = (number << 7) | u32::from(byte & 0x7f);
+
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+    }
+
+    let first = next()?;
+    header_len += 1;
+
+    let len = match first {
+        0x80 => bail!("indefinite length is not allowed in DER"),
+        0xff => bail!("reserved length octet"),
+        n if n & 0x80 == 0 => usize::from(n),
+        n => {
+            let n_octets = usize::from(n & 0x7f);
+            if n_octets > size_of::<usize>() {
+                bail!("length of {n_octets} octets is too large");
+            }
+
+            let mut len = 0usize;
+            for _ in 0..n_octets {
+                len = (len << 8) | usize::from(next()?);
+            }
+            header_len += n_octets;
+
+            len
+        }
+    };
+
+    Ok((
+        Tag {
+            class,
+            constructed,
+            number,
+        },
+        header_len,
+        len,
+    ))
+}
+
+/// Decodes DER nested in an OCTET STRING or BIT STRING.
+///
+/// Contents are only treated as nested DER when they parse completely and start with a constructed
+/// element, to avoid misreading arbitrary binary data.
+fn encapsulated(tag: Tag, content: &[u8], base: usize, depth: usize) -> Vec<Node<'_>> {
+    let (content, base) = match (tag.class, tag.number) {
+        (Class::Universal, 4) => (content, base),
+        (Class::Universal, 3) => match content.split_first() {
+            Some((0, rest)) => (rest, base + 1),
+            _ => return Vec::new(),
+        },
+        _ => return Vec::new(),
+    };
+
+    match parse_at(content, base, depth + 1) {
+        Ok(nodes) if nodes.first().is_some_and(|node| node.tag.constructed) => nodes,
+        _ => Vec::new(),
+    }
+}
+
+/// Runs the `asn1` subcommand, dumping each DER blob in the input.
+pub(crate) fn run(args: &Args, mut wrt: impl io::Write) -> eyre::Result<()> {
+    let data = if args.file == "-" {
+        tracing::info!("reading DER or PEM from stdin");
+
+        let mut buf = Vec::new();
+        io::stdin().read_to_end(&mut buf)?;
+        buf
+    } else {
+        tracing::info!(path = %args.file, "reading DER or PEM from file");
+
+        fs::read(&args.file).wrap_err_with(|| format!("could not open file: {}", args.file))?
+    };
+
+    let blobs = read_blobs(&data)?;
+
+    for (idx, (label, der)) in blobs.iter().enumerate() {
+        let title = if blobs.len() > 1 {
+            format!("{label} #{}", idx + 1)
+        } else {
+            label.clone()
+        };
+
+        let nodes = parse(der).wrap_err_with(|| format!("could not parse {title}"))?;
+
+        let selected = if let Some(offset) = args.offset {
+            vec![
+                find_offset(&nodes, offset)
+                    .ok_or_else(|| eyre!("{title} has no element starting at offset {offset}"))?,
+            ]
+        } else if !args.path.is_empty() {
+            vec![find_path(&nodes, &args.path).ok_or_else(|| {
+                eyre!(
+                    "{title} has no element at path {}",
+                    args.path.iter().join(".")
+                )
+            })?]
+        } else {
+            nodes.iter().collect()
+        };
+
+        writeln!(wrt, "{title}")?;
+        writeln!(wrt, "{}", "=".repeat(title.len()))?;
+
+        for node in selected {
+            for line in fmt_dump(std::slice::from_ref(node)) {
+                writeln!(wrt, "{line}")?;
+            }
+        }
+
+        writeln!(wrt)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the label and DER of each PEM block in the input, or the input itself if it is not PEM.
+fn read_blobs(data: &[u8]) -> eyre::Result<Vec<(String, Vec<u8>)>> {
+    const BEGIN: &str = "-----BEGIN ";
+    const END: &str = "-----END ";
+
+    let Ok(text) = std::str::from_utf8(data) else {
+        return Ok(vec![("DER".to_owned(), data.to_vec())]);
+    };
+
+    if !text.contains(BEGIN) {
+        return Ok(vec![("DER".to_owned(), data.to_vec())]);
+    }
+
+    let mut blobs = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find(BEGIN) {
+        let block = &rest[start..];
+
+        // end boundary runs to the end of its line
+        let end = block
+            .find(END)
+            .map(|end| end + block[end..].find('\n').unwrap_or(block.len() - end))
+            .ok_or_else(|| eyre!("PEM block is missing its END line"))?;
+
+        let (label, der) = pem_rfc7468::decode_vec(block[..end].trim_end().as_bytes())
+            .map_err(|err| eyre!("could not decode PEM block: {err}"))?;
+        blobs.push((label.to_owned(), der));
+
+        rest = &block[end..];
+    }
+
+    Ok(blobs)
+}
+
+/// Formats elements as an indented tree, one line per element plus any hex continuation lines.
+pub(crate) fn fmt_tree(nodes: &[Node<'_>]) -> Vec<String> {
+    let mut lines = Vec::new();
+    fmt_tree_into(nodes, 0, None, &mut lines);
+    lines
+}
+
+/// Formats elements as an indented tree prefixed with the offset, depth, header length and content
+/// length of each, in the style of `openssl asn1parse`.
+pub(crate) fn fmt_dump(nodes: &[Node<'_>]) -> Vec<String> {
+    let end = nodes
+        .last()
+        .map_or(0, |node| node.offset + node.header_len + node.content.len());
+    let width = end.to_string().len();
+
+    let prefix = |node: &Node<'_>, depth: usize| {
+        format!(
+            "{:>width$}:d={depth:<2} hl={} l={:>width$}  ",
+            node.offset,
+            node.header_len,
+            node.content.len(),
+        )
+    };
+
+    let mut lines = Vec::new();
+    fmt_tree_into(nodes, 0, Some(&prefix), &mut lines);
+    lines
+}
+
+/// Formats the prefix of an element's line given the element and its depth.
+type LinePrefix<'f> = dyn Fn(&Node<'_>, usize) -> String + 'f;
+
+/// Formats elements at `depth`, prefixing each line using `prefix` if given or else including the
+/// content length after the tag.
+fn fmt_tree_into(
+    nodes: &[Node<'_>],
+    depth: usize,
+    prefix: Option<&LinePrefix<'_>>,
+    lines: &mut Vec<String>,
+) {
+    let indent = "  ".repeat(depth);
+
+    for node in nodes {
+        let line_prefix = prefix.map(|prefix| prefix(node, depth)).unwrap_or_default();
+        let blank = " ".repeat(line_prefix.len());
+        let (header, hex_lines) = node.fmt_text(prefix.is_none());
+
+        lines.push(format!("{line_prefix}{indent}{header}"));
+        lines.extend(
+            hex_lines
+                .into_iter()
+                .map(|line| format!("{blank}{indent}  {line}")),
+        );
+
+        fmt_tree_into(&node.children, depth + 1, prefix, lines);
+    }
+}
+
+/// Finds the element starting at `offset`, searching nested and encapsulated elements.
+pub(crate) fn find_offset<'n, 'a>(nodes: &'n [Node<'a>], offset: usize) -> Option<&'n Node<'a>> {
+    nodes.iter().find_map(|node| {
+        if node.offset == offset {
+            Some(node)
+        } else {
+            find_offset(&node.children, offset)
+        }
+    })
+}
+
+/// Finds the element at `path`, a list of child indices starting with the index of a top-level
+/// element.
+pub(crate) fn find_path<'n, 'a>(nodes: &'n [Node<'a>], path: &[usize]) -> Option<&'n Node<'a>> {
+    let (&first, rest) = path.split_first()?;
+    let node = nodes.get(first)?;
+
+    if rest.is_empty() {
+        Some(node)
+    } else {
+        find_path(&node.children, rest)
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).join(":")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// SEQUENCE { OID 2.5.29.19, BOOLEAN TRUE, OCTET STRING { SEQUENCE { INTEGER -3 } } }
+    const DER: &[u8] = &[
+        0x30, 0x0f, 0x06, 0x03, 0x55, 0x1d, 0x13, 0x01, 0x01, 0xff, 0x04, 0x05, 0x30, 0x03, 0x02,
+        0x01, 0xfd,
+    ];
+
+    #[test]
+    fn renders_nested_tree() {
+        assert_eq!(
+            fmt_tree(&parse(DER).unwrap()),
+            [
+                "SEQUENCE (15 bytes)",
+                "  OBJECT IDENTIFIER (3 bytes): 2.5.29.19 (Basic Constraints)",
+                "  BOOLEAN (1 byte): TRUE",
+                "  OCTET STRING (5 bytes), encapsulates",
+                "    SEQUENCE (3 bytes)",
+                "      INTEGER (1 byte): -3",
+            ],
+        );
+    }
+
+    #[test]
+    fn dumps_selected_element_with_offsets() {
+        let nodes = parse(DER).unwrap();
+
+        let by_path = find_path(&nodes, &[0, 2, 0]).unwrap();
+        let by_offset = find_offset(&nodes, 12).unwrap();
+        assert!(std::ptr::eq(by_path, by_offset));
+
+        assert_eq!(
+            fmt_dump(std::slice::from_ref(by_path)),
+            [
+                "12:d=0  hl=2 l= 3  SEQUENCE",
+                "14:d=1  hl=2 l= 1    INTEGER: -3",
+            ],
+        );
+
+        assert!(find_path(&nodes, &[0, 3]).is_none());
+        assert!(find_offset(&nodes, 13).is_none());
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        assert!(parse(&[0x30, 0x03, 0x02, 0x01]).is_err());
+        assert!(parse(&[0x30, 0x80, 0x00, 0x00]).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use std::ops::Deref;
+
+use der::{Decode, Encode, Length, Reader, Writer};
+use x509_cert::{
+    Certificate,
+    ext::pkix::{AuthorityKeyIdentifier, SubjectKeyIdentifier},
+};
+
+/// Decoded certificate along with the DER encoding it was decoded from.
+///
+/// Re-encoding a [`Certificate`] does not always reproduce the original bytes (e.g., explicitly
+/// encoded default values are dropped) so anything covered by a signature or hash must use
+/// [`Cert::der`] instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Cert {
+    der: Vec<u8>,
+    cert: Certificate,
+}
+
+impl Cert {
+    /// Returns the original DER encoding of the certificate.
+    pub(crate) fn der(&self) -> &[u8] {
+        &self.der
+    }
+}
+
+impl Deref for Cert {
+    type Target = Certificate;
+
+    fn deref(&self) -> &Self::Target {
+        &self.cert
+    }
+}
+
+impl<'a> Decode<'a> for Cert {
+    type Error = der::Error;
+
+    fn decode<R: Reader<'a>>(reader: &mut R) -> der::Result<Self> {
+        let der = reader.tlv_bytes()?;
+
+        Ok(Self {
+            der: der.to_vec(),
+            cert: Certificate::from_der(der)?,
+        })
+    }
+}
+
+impl Encode for Cert {
+    fn encoded_len(&self) -> der::Result<Length> {
+        Length::try_from(self.der.len())
+    }
+
+    fn encode(&self, writer: &mut impl Writer) -> der::Result<()> {
+        writer.write(&self.der)
+    }
+}
+
+/// Returns true if `issuer`'s subject and key identifier match `cert`'s issuer and authority key
+/// identifier. Key identifiers are only compared when both are present.
+pub(crate) fn is_issuer(issuer: &Certificate, cert: &Certificate) -> bool {
+    if issuer.tbs_certificate().subject() != cert.tbs_certificate().issuer() {
+        return false;
+    }
+
+    let aki = cert
+        .tbs_certificate()
+        .get_extension::<AuthorityKeyIdentifier>()
+        .ok()
+        .flatten()
+        .and_then(|(_, aki)| aki.key_identifier);
+
+    let ski = issuer
+        .tbs_certificate()
+        .get_extension::<SubjectKeyIdentifier>()
+        .ok()
+        .flatten()
+        .map(|(_, ski)| ski.0);
+
+    match (aki, ski) {
+        (Some(aki), Some(ski)) => aki == ski,
+        _ => true,
+    }
+}
+
+/// Returns true if the certificate names itself as its issuer.
+pub(crate) fn is_self_issued(cert: &Certificate) -> bool {
+    is_issuer(cert, cert)
+}
+
+/// Returns index of the issuer of the certificate at `idx`, preferring the next certificate.
+pub(crate) fn find_issuer(idx: usize, certs: &[Cert]) -> Option<usize> {
+    (idx + 1..certs.len())
+        .chain(0..=idx)
+        .find(|&candidate| is_issuer(&certs[candidate], &certs[idx]))
+}
+
+/// Helpers for loading files from `tests/fixtures`.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use std::io;
+
+    use der::Decode as _;
+
+    use super::Cert;
+
+    /// Returns path of a fixture file.
+    pub(crate) fn path(name: &str) -> camino::Utf8PathBuf {
+        camino::Utf8Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    /// Reads a fixture file.
+    pub(crate) fn read(name: &str) -> Vec<u8> {
+        let path = path(name);
+
+        std::fs::read(&path).unwrap_or_else(|err| panic!("could not read {path}: {err}"))
+    }
+
+    /// Reads all certificates from a PEM fixture file.
+    pub(crate) fn certs(name: &str) -> Vec<Cert> {
+        rustls_pemfile::certs(&mut io::Cursor::new(read(name)))
+            .map(|der| Cert::from_der(&der.unwrap()).unwrap())
+            .collect()
+    }
+
+    /// Reads certificates from several PEM fixture files, in order.
+    pub(crate) fn chain(names: &[&str]) -> Vec<Cert> {
+        names.iter().flat_map(|name| certs(name)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn issuer_matches_name_and_key_identifier() {
+        let certs = fixtures::chain(&["pki/good.pem", "pki/ca.pem", "pki/other-ca.pem"]);
+        let [leaf, ca, other_ca] = [&certs[0], &certs[1], &certs[2]];
+
+        assert!(is_issuer(ca, leaf));
+        assert!(!is_issuer(leaf, ca));
+
+        // same subject name as the issuer but a different key
+        assert_eq!(
+            other_ca.tbs_certificate().subject(),
+            leaf.tbs_certificate().issuer()
+        );
+        assert!(!is_issuer(other_ca, leaf));
+    }
+
+    #[test]
+    fn self_issued() {
+        assert!(is_self_issued(&fixtures::certs("pki/ca.pem")[0]));
+        assert!(!is_self_issued(&fixtures::certs("pki/good.pem")[0]));
+    }
+
+    #[test]
+    fn finds_issuer_anywhere_in_chain() {
+        let certs = fixtures::chain(&["pki/other-ca.pem", "pki/good.pem", "pki/ca.pem"]);
+
+        assert_eq!(find_issuer(1, &certs), Some(2));
+
+        let certs = fixtures::chain(&["pki/ca.pem", "pki/good.pem"]);
+        assert_eq!(find_issuer(1, &certs), Some(0));
+
+        let certs = fixtures::chain(&["pki/good.pem", "pki/other-ca.pem"]);
+        assert_eq!(find_issuer(0, &certs), None);
+    }
+
+    #[test]
+    fn keeps_original_encoding() {
+        let certs = fixtures::certs("generalized-time.pem");
+
+        assert_ne!(certs[0].der(), certs[0].cert.to_der().unwrap());
+        assert_eq!(certs[0].to_der().unwrap(), certs[0].der());
+    }
+}
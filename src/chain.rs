@@ -0,0 +1,665 @@
+use std::io;
+
+use crate::cert::{Cert, is_issuer, is_self_issued};
+use crossterm::style::Stylize as _;
+use itertools::Itertools as _;
+use serde::Serialize;
+
+/// Problem with the order or contents of a certificate chain, as sent.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum Diagnostic {
+    /// Certificate is identical to an earlier one.
+    Duplicate {
+        #[serde(rename = "index")]
+        idx: usize,
+        #[serde(rename = "first_index")]
+        first_idx: usize,
+    },
+
+    /// End-entity certificate is not the first certificate.
+    LeafNotFirst {
+        #[serde(rename = "index")]
+        idx: usize,
+    },
+
+    /// Certificate is sent before the certificate it issued.
+    OutOfOrder {
+        #[serde(rename = "index")]
+        idx: usize,
+        #[serde(rename = "subject_index")]
+        subject_idx: usize,
+    },
+
+    /// Certificate is not part of the path from the leaf.
+    Unrelated {
+        #[serde(rename = "index")]
+        idx: usize,
+    },
+
+    /// Self-signed root is included, which clients must already have to trust the chain.
+    RootIncluded {
+        #[serde(rename = "index")]
+        idx: usize,
+    },
+
+    /// Issuer of the leaf is not included.
+    MissingIntermediates {
+        #[serde(rename = "index")]
+        idx: usize,
+    },
+
+    /// More than one certificate could have issued this certificate.
+    MultipleIssuers {
+        #[serde(rename = "index")]
+        idx: usize,
+        candidates: Vec<usize>,
+    },
+}
+
+impl Diagnostic {
+    /// Index of the certificate this diagnostic refers to.
+    pub(crate) fn idx(&self) -> usize {
+        match self {
+            Self::Duplicate { idx, .. }
+            | Self::LeafNotFirst { idx }
+            | Self::OutOfOrder { idx, .. }
+            | Self::Unrelated { idx }
+            | Self::RootIncluded { idx }
+            | Self::MissingIntermediates { idx }
+            | Self::MultipleIssuers { idx, .. } => *idx,
+        }
+    }
+
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Self::Duplicate { first_idx, .. } => {
+                format!("duplicate of certificate #{first_idx}")
+            }
+            Self::LeafNotFirst { .. } => "leaf certificate is not sent first".to_owned(),
+            Self::OutOfOrder { subject_idx, .. } => {
+                format!("out of order: issued certificate #{subject_idx} but is sent before it")
+            }
+            Self::Unrelated { .. } => "unrelated: not part of the path from the leaf".to_owned(),
+            Self::RootIncluded { .. } => {
+                "self-signed root sent unnecessarily: clients must already trust it".to_owned()
+            }
+            Self::MissingIntermediates { .. } => "issuer not sent: intermediate certificates are \
+                missing unless the leaf is issued directly by a trust anchor"
+                .to_owned(),
+            Self::MultipleIssuers { candidates, .. } => format!(
+                "multiple possible issuers: {}",
+                candidates.iter().map(|idx| format!("#{idx}")).join(", ")
+            ),
+        }
+    }
+}
+
+/// Chain structure as determined from subject/issuer names and key identifiers.
+#[derive(Debug)]
+pub(crate) struct Structure {
+    /// Indices of certificates on the path from the leaf, in issuing order.
+    pub(crate) path: Vec<usize>,
+
+    pub(crate) diagnostics: Vec<Diagnostic>,
+}
+
+/// Analyzes the order and completeness of a chain.
+pub(crate) fn analyze(certs: &[Cert]) -> Structure {
+    let mut diagnostics = Vec::new();
+
+    // only the first copy of each certificate takes part in the remaining analysis
+    let mut unique = Vec::new();
+
+    for (idx, cert) in certs.iter().enumerate() {
+        match certs[..idx].iter().position(|other| other == cert) {
+            Some(first_idx) => diagnostics.push(Diagnostic::Duplicate { idx, first_idx }),
+            None => unique.push(idx),
+        }
+    }
+
+    let issuers_of = |idx: usize| {
+        unique
+            .iter()
+            .copied()
+            .filter(|&other| other != idx && is_issuer(&certs[other], &certs[idx]))
+            .collect::<Vec<_>>()
+    };
+
+    // the leaf is the first certificate which did not issue any other certificate
+    let leaf_idx = unique
+        .iter()
+        .copied()
+        .find(|&idx| {
+            !unique
+                .iter()
+                .any(|&other| other != idx && is_issuer(&certs[idx], &certs[other]))
+        })
+        .unwrap_or(0);
+
+    if leaf_idx != 0 {
+        diagnostics.push(Diagnostic::LeafNotFirst { idx: leaf_idx });
+    }
+
+    let mut path = vec![leaf_idx];
+
+    // issuers not chosen for the path are reported as alternatives rather than unrelated
+    let mut alternatives = Vec::new();
+
+    loop {
+        let idx = path[path.len() - 1];
+        let cert = &certs[idx];
+
+        if is_self_issued(cert) {
+            if idx != leaf_idx {
+                diagnostics.push(Diagnostic::RootIncluded { idx });
+            }
+            break;
+        }
+
+        let candidates = issuers_of(idx)
+            .into_iter()
+            .filter(|candidate| !path.contains(candidate))
+            .collect::<Vec<_>>();
+
+        let Some(&first) = candidates.first() else {
+            if idx == leaf_idx {
+                diagnostics.push(Diagnostic::MissingIntermediates { idx });
+            }
+            break;
+        };
+
+        // prefer the issuer sent in the expected position
+        let next = if candidates.contains(&(idx + 1)) {
+            idx + 1
+        } else {
+            first
+        };
+
+        if candidates.len() > 1 {
+            alternatives.extend(candidates.iter().copied().filter(|&other| other != next));
+            diagnostics.push(Diagnostic::MultipleIssuers { idx, candidates });
+        }
+
+        if next < idx {
+            diagnostics.push(Diagnostic::OutOfOrder {
+                idx: next,
+                subject_idx: idx,
+            });
+        }
+
+        path.push(next);
+    }
+
+    for &idx in &unique {
+        if !path.contains(&idx) && !alternatives.contains(&idx) {
+            diagnostics.push(Diagnostic::Unrelated { idx });
+        }
+    }
+
+    diagnostics.sort_by_key(Diagnostic::idx);
+
+    Structure { path, diagnostics }
+}
+
+/// Writes chain structure analysis in text form.
+pub(crate) fn write_report(
+    certs: &[Cert],
+    structure: &Structure,
+    mut wrt: impl io::Write,
+) -> io::Result<()> {
+    writeln!(wrt, "Chain Structure")?;
+    writeln!(wrt, "===============")?;
+    writeln!(
+        wrt,
+        "Path: {}",
+        structure
+            .path
+            .iter()
+            .map(|idx| format!("#{idx}"))
+            .join(" → ")
+    )?;
+
+    if let Some(&top) = structure.path.last() {
+        let top = &certs[top];
+        let tbs = top.tbs_certificate();
+
+        if is_self_issued(top) {
+            writeln!(wrt, "Ends With: self-signed {}", tbs.subject())?;
+        } else {
+            writeln!(wrt, "Ends With: {} (issuer not sent)", tbs.issuer())?;
+        }
+    }
+
+    for diagnostic in &structure.diagnostics {
+        writeln!(
+            wrt,
+            "{} [{}] {}",
+            "✗".red(),
+            diagnostic.idx(),
+            diagnostic.describe()
+        )?;
+    }
+
+    if structure.diagnostics.is_empty() {
+        writeln!(wrt, "{} no problems found", "✓".green())?;
+    }
+
+    Ok(())
+}
+
+/// How the self-signed root is treated when repairing a chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum RootPolicy {
+    /// Include the root only if it was in the original chain.
+    Keep,
+
+    /// Never include the root.
+    Drop,
+
+    /// Always include the root, taking it from the intermediates if necessary.
+    Append,
+}
+
+/// Where a certificate in a repaired chain came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Origin {
+    /// Certificate at this index in the original chain.
+    Chain(usize),
+
+    /// Certificate at this index in the supplied intermediates.
+    Extra(usize),
+}
+
+impl Origin {
+    pub(crate) fn cert<'a>(self, certs: &'a [Cert], extra: &'a [Cert]) -> &'a Cert {
+        match self {
+            Self::Chain(idx) => &certs[idx],
+            Self::Extra(idx) => &extra[idx],
+        }
+    }
+}
+
+/// Chain reordered leaf-to-root with duplicates and unrelated certificates removed.
+#[derive(Debug)]
+pub(crate) struct Repair {
+    pub(crate) certs: Vec<Origin>,
+
+    /// Indices of original certificates left out, along with the reason.
+    pub(crate) removed: Vec<(usize, &'static str)>,
+
+    /// Issuer name of the last certificate, if it is not self-signed and was not found.
+    pub(crate) missing_issuer: Option<String>,
+}
+
+/// Repairs a chain using its analyzed structure, filling gaps from `extra` certificates.
+pub(crate) fn repair(
+    certs: &[Cert],
+    structure: &Structure,
+    extra: &[Cert],
+    root: RootPolicy,
+) -> Repair {
+    let mut repaired = structure
+        .path
+        .iter()
+        .map(|&idx| Origin::Chain(idx))
+        .collect::<Vec<_>>();
+
+    let resolve = |origin: Origin| origin.cert(certs, extra);
+
+    // fill gaps from extra certificates; roots are only added when requested
+    loop {
+        let top = resolve(repaired[repaired.len() - 1]);
+
+        if is_self_issued(top) {
+            break;
+        }
+
+        let issuer_idx = extra.iter().enumerate().position(|(idx, candidate)| {
+            is_issuer(candidate, top)
+                && (root == RootPolicy::Append || !is_self_issued(candidate))
+                && !repaired.contains(&Origin::Extra(idx))
+        });
+
+        match issuer_idx {
+            Some(idx) => repaired.push(Origin::Extra(idx)),
+            None => break,
+        }
+    }
+
+    if root == RootPolicy::Drop
+        && repaired.len() > 1
+        && is_self_issued(resolve(repaired[repaired.len() - 1]))
+    {
+        repaired.pop();
+    }
+
+    // without a root, a chain ending in an intermediate is assumed to end at a trust anchor
+    let top = resolve(repaired[repaired.len() - 1]);
+    let missing_issuer = (!is_self_issued(top)
+        && (root == RootPolicy::Append || repaired.len() == 1))
+        .then(|| top.tbs_certificate().issuer().to_string());
+
+    let removed = (0..certs.len())
+        .filter(|idx| !repaired.contains(&Origin::Chain(*idx)))
+        .map(|idx| {
+            let is_duplicate = structure.diagnostics.iter().any(|diagnostic| {
+                matches!(diagnostic, Diagnostic::Duplicate { idx: dup_idx, .. } if *dup_idx == idx)
+            });
+
+            let reason = if is_duplicate {
+                "duplicate"
+            } else if structure.path.contains(&idx) {
+                "self-signed root"
+            } else {
+                "not on the path from the leaf"
+            };
+
+            (idx, reason)
+        })
+        .collect();
+
+    Repair {
+        certs: repaired,
+        removed,
+        missing_issuer,
+    }
+}
+
+/// Writes the outcome of repairing a chain in text form.
+pub(crate) fn write_repair_report(
+    certs: &[Cert],
+    extra: &[Cert],
+    repair: &Repair,
+    path: &camino::Utf8Path,
+    mut wrt: impl io::Write,
+) -> io::Result<()> {
+    writeln!(wrt, "Chain Repair")?;
+    writeln!(wrt, "============")?;
+    writeln!(wrt, "Written To: {path}")?;
+
+    for (idx, origin) in repair.certs.iter().enumerate() {
+        let note = match origin {
+            Origin::Chain(orig_idx) => format!("was #{orig_idx}"),
+            Origin::Extra(_) => "added".to_owned(),
+        };
+
+        writeln!(
+            wrt,
+            "  [{idx}] {} ({note})",
+            origin.cert(certs, extra).tbs_certificate().subject()
+        )?;
+    }
+
+    for (idx, reason) in &repair.removed {
+        writeln!(
+            wrt,
+            "Removed: #{idx} {} ({reason})",
+            certs[*idx].tbs_certificate().subject()
+        )?;
+    }
+
+    match &repair.missing_issuer {
+        Some(issuer) => writeln!(
+            wrt,
+            "{} chain is incomplete: issuer {issuer} not found",
+            "✗".red()
+        )?,
+        None => writeln!(wrt, "{} no missing issuers", "✓".green())?,
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cert::fixtures;
+
+    const LEAF: &str = "chain/leaf.pem";
+    const INTERMEDIATE: &str = "chain/intermediate.pem";
+    const CROSS: &str = "chain/intermediate-cross.pem";
+    const ROOT: &str = "chain/root.pem";
+    const UNRELATED: &str = "pki/good.pem";
+
+    /// Analyzes a chain, returning the path and each diagnostic's index and description.
+    fn analyze_fixture(names: &[&str]) -> (Vec<usize>, Vec<(usize, String)>) {
+        let structure = analyze(&fixtures::chain(names));
+
+        let diagnostics = structure
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.idx(), diagnostic.describe()))
+            .collect();
+
+        (structure.path, diagnostics)
+    }
+
+    #[test]
+    fn well_formed() {
+        assert_eq!(analyze_fixture(&[LEAF, INTERMEDIATE]), (vec![0, 1], vec![]));
+    }
+
+    #[test]
+    fn root_included() {
+        let (path, diagnostics) = analyze_fixture(&[LEAF, INTERMEDIATE, ROOT]);
+
+        assert_eq!(path, [0, 1, 2]);
+        assert_eq!(
+            diagnostics,
+            [(
+                2,
+                "self-signed root sent unnecessarily: clients must already trust it".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn self_signed_leaf() {
+        assert_eq!(analyze_fixture(&[ROOT]), (vec![0], vec![]));
+    }
+
+    #[test]
+    fn missing_intermediates() {
+        let (path, diagnostics) = analyze_fixture(&[LEAF]);
+
+        assert_eq!(path, [0]);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].0, 0);
+        assert!(diagnostics[0].1.starts_with("issuer not sent"));
+    }
+
+    #[test]
+    fn out_of_order() {
+        let (path, diagnostics) = analyze_fixture(&[INTERMEDIATE, LEAF]);
+
+        assert_eq!(path, [1, 0]);
+        assert_eq!(
+            diagnostics,
+            [
+                (
+                    0,
+                    "out of order: issued certificate #1 but is sent before it".to_owned()
+                ),
+                (1, "leaf certificate is not sent first".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate() {
+        let (path, diagnostics) = analyze_fixture(&[LEAF, INTERMEDIATE, INTERMEDIATE]);
+
+        assert_eq!(path, [0, 1]);
+        assert_eq!(diagnostics, [(2, "duplicate of certificate #1".to_owned())]);
+    }
+
+    #[test]
+    fn duplicate_leaf() {
+        let (path, diagnostics) = analyze_fixture(&[LEAF, LEAF, INTERMEDIATE]);
+
+        assert_eq!(path, [0, 2]);
+        assert_eq!(diagnostics, [(1, "duplicate of certificate #0".to_owned())]);
+    }
+
+    #[test]
+    fn unrelated() {
+        let (path, diagnostics) = analyze_fixture(&[LEAF, UNRELATED, INTERMEDIATE]);
+
+        assert_eq!(path, [0, 2]);
+        assert_eq!(
+            diagnostics,
+            [(
+                1,
+                "unrelated: not part of the path from the leaf".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn multiple_issuers() {
+        let (path, diagnostics) = analyze_fixture(&[LEAF, CROSS, INTERMEDIATE]);
+
+        // the cross-signed intermediate is an alternative issuer rather than unrelated
+        assert_eq!(path, [0, 1]);
+        assert_eq!(
+            diagnostics,
+            [(0, "multiple possible issuers: #1, #2".to_owned())]
+        );
+    }
+
+    /// Repairs a chain, returning the repaired chain's subjects along with the removed certificates
+    /// and missing issuer.
+    fn repair_fixture(
+        names: &[&str],
+        extra: &[&str],
+        root: RootPolicy,
+    ) -> (Vec<String>, Vec<(usize, &'static str)>, Option<String>) {
+        let certs = fixtures::chain(names);
+        let extra = fixtures::chain(extra);
+        let repair = repair(&certs, &analyze(&certs), &extra, root);
+
+        let subjects = repair
+            .certs
+            .iter()
+            .map(|origin| {
+                let subject = origin.cert(&certs, &extra).tbs_certificate().subject();
+
+                match origin {
+                    Origin::Chain(idx) => format!("#{idx} {subject}"),
+                    Origin::Extra(idx) => format!("+{idx} {subject}"),
+                }
+            })
+            .collect();
+
+        (subjects, repair.removed, repair.missing_issuer)
+    }
+
+    #[test]
+    fn repair_keep_root() {
+        let (certs, removed, missing_issuer) = repair_fixture(
+            &[INTERMEDIATE, LEAF, UNRELATED, ROOT, LEAF],
+            &[],
+            RootPolicy::Keep,
+        );
+
+        assert_eq!(
+            certs,
+            [
+                "#1 CN=leaf.chain.test",
+                "#0 CN=Chain Intermediate",
+                "#3 CN=Chain Root"
+            ]
+        );
+        assert_eq!(
+            removed,
+            [(2, "not on the path from the leaf"), (4, "duplicate")]
+        );
+        assert_eq!(missing_issuer, None);
+    }
+
+    #[test]
+    fn repair_keep_without_root() {
+        // roots are not added from extra certificates
+        let (certs, removed, missing_issuer) =
+            repair_fixture(&[LEAF], &[ROOT, INTERMEDIATE], RootPolicy::Keep);
+
+        assert_eq!(certs, ["#0 CN=leaf.chain.test", "+1 CN=Chain Intermediate"]);
+        assert_eq!(removed, []);
+        assert_eq!(missing_issuer, None);
+    }
+
+    #[test]
+    fn repair_drop_root() {
+        let (certs, removed, missing_issuer) =
+            repair_fixture(&[LEAF, INTERMEDIATE, ROOT], &[], RootPolicy::Drop);
+
+        assert_eq!(certs, ["#0 CN=leaf.chain.test", "#1 CN=Chain Intermediate"]);
+        assert_eq!(removed, [(2, "self-signed root")]);
+        assert_eq!(missing_issuer, None);
+    }
+
+    #[test]
+    fn repair_drop_keeps_self_signed_leaf() {
+        let (certs, removed, missing_issuer) = repair_fixture(&[ROOT], &[], RootPolicy::Drop);
+
+        assert_eq!(certs, ["#0 CN=Chain Root"]);
+        assert_eq!(removed, []);
+        assert_eq!(missing_issuer, None);
+    }
+
+    #[test]
+    fn repair_append_root() {
+        let (certs, removed, missing_issuer) =
+            repair_fixture(&[LEAF], &[ROOT, INTERMEDIATE], RootPolicy::Append);
+
+        assert_eq!(
+            certs,
+            [
+                "#0 CN=leaf.chain.test",
+                "+1 CN=Chain Intermediate",
+                "+0 CN=Chain Root"
+            ]
+        );
+        assert_eq!(removed, []);
+        assert_eq!(missing_issuer, None);
+    }
+
+    #[test]
+    fn repair_append_without_root_available() {
+        let (certs, _, missing_issuer) =
+            repair_fixture(&[LEAF, INTERMEDIATE], &[UNRELATED], RootPolicy::Append);
+
+        assert_eq!(certs, ["#0 CN=leaf.chain.test", "#1 CN=Chain Intermediate"]);
+        assert_eq!(missing_issuer.as_deref(), Some("CN=Chain Root"));
+    }
+
+    #[test]
+    fn repair_lone_leaf_is_incomplete() {
+        for root in [RootPolicy::Keep, RootPolicy::Drop, RootPolicy::Append] {
+            let (certs, _, missing_issuer) = repair_fixture(&[LEAF], &[], root);
+
+            assert_eq!(certs, ["#0 CN=leaf.chain.test"]);
+            assert_eq!(missing_issuer.as_deref(), Some("CN=Chain Intermediate"));
+        }
+    }
+
+    #[test]
+    fn report() {
+        let certs = fixtures::chain(&[INTERMEDIATE, LEAF, ROOT]);
+        let mut out = Vec::new();
+        write_report(&certs, &analyze(&certs), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.contains("Path: #1 → #0 → #2\n"));
+        assert!(out.contains("Ends With: self-signed CN=Chain Root\n"));
+        assert!(out.contains("[1] leaf certificate is not sent first\n"));
+
+        let certs = fixtures::chain(&[LEAF]);
+        let mut out = Vec::new();
+        write_report(&certs, &analyze(&certs), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.contains("Ends With: CN=Chain Intermediate (issuer not sent)\n"));
+    }
+}
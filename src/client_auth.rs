@@ -0,0 +1,349 @@
+use std::{
+    fs, io,
+    sync::{Arc, Mutex},
+};
+
+use der::Decode as _;
+use eyre::{WrapErr as _, eyre};
+use rustls::{SignatureScheme, client::ResolvesClientCert, sign::CertifiedKey};
+use rustls_pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use serde::Serialize;
+use x509_cert::name::Name;
+
+/// Client certificate chain and private key used for mutual TLS.
+#[derive(Debug)]
+pub(crate) struct ClientIdentity {
+    certs: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+}
+
+impl ClientIdentity {
+    /// Loads a client identity.
+    ///
+    /// When `key_path` is given, both files are read as PEM. Otherwise, `cert_path` is read either
+    /// as a PEM file containing both certificate chain and key or as a PKCS#12 archive.
+    pub(crate) fn load(
+        cert_path: &camino::Utf8Path,
+        key_path: Option<&camino::Utf8Path>,
+        password: Option<&str>,
+    ) -> eyre::Result<Self> {
+        let cert_data =
+            fs::read(cert_path).wrap_err_with(|| format!("could not open file: {cert_path}"))?;
+
+        // PEM files written by `openssl pkcs12 -nodes` start with a "Bag Attributes" preamble
+        let is_pem = cert_data
+            .windows(b"-----BEGIN ".len())
+            .any(|window| window == b"-----BEGIN ");
+
+        if key_path.is_none() && !is_pem {
+            return Self::from_pkcs12(&cert_data, password.unwrap_or_default())
+                .wrap_err_with(|| format!("could not read PKCS#12 archive: {cert_path}"));
+        }
+
+        let certs = rustls_pemfile::certs(&mut io::Cursor::new(&cert_data))
+            .collect::<Result<Vec<_>, _>>()
+            .wrap_err_with(|| format!("could not read certificates from: {cert_path}"))?;
+
+        if certs.is_empty() {
+            return Err(eyre!("no certificates found in: {cert_path}"));
+        }
+
+        let key_path = key_path.unwrap_or(cert_path);
+        let key_data =
+            fs::read(key_path).wrap_err_with(|| format!("could not open file: {key_path}"))?;
+
+        let key = rustls_pemfile::private_key(&mut io::Cursor::new(&key_data))
+            .wrap_err_with(|| format!("could not read private key from: {key_path}"))?
+            .ok_or_else(|| eyre!("no private key found in: {key_path}"))?;
+
+        Ok(Self { certs, key })
+    }
+
+    fn from_pkcs12(data: &[u8], password: &str) -> eyre::Result<Self> {
+        let keystore = p12_keystore::KeyStore::from_pkcs12(data, password, Default::default())
+            .map_err(|err| eyre!("{err}"))?;
+
+        let (alias, chain) = keystore
+            .private_key_chain()
+            .ok_or_else(|| eyre!("archive contains no private key with certificate chain"))?;
+
+        tracing::debug!(%alias, "using client identity from PKCS#12 archive");
+
+        Ok(Self {
+            certs: chain
+                .certs()
+                .iter()
+                .map(|cert| CertificateDer::from(cert.as_der().to_vec()))
+                .collect(),
+            key: PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(chain.key().as_der().to_vec())),
+        })
+    }
+}
+
+/// Reads a password from the first line of a file.
+pub(crate) fn read_password(path: &camino::Utf8Path) -> eyre::Result<String> {
+    let data = fs::read_to_string(path)
+        .wrap_err_with(|| format!("could not read password file: {path}"))?;
+
+    Ok(data.lines().next().unwrap_or_default().to_owned())
+}
+
+/// Contents of the server's CertificateRequest message.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct CertificateRequest {
+    /// Acceptable certificate authority distinguished names.
+    pub(crate) ca_names: Vec<String>,
+
+    /// Signature schemes the server accepts.
+    pub(crate) signature_schemes: Vec<String>,
+
+    /// Whether a client certificate was sent in response.
+    pub(crate) cert_sent: bool,
+}
+
+/// Client certificate resolver which records the server's request.
+#[derive(Debug)]
+pub(crate) struct RecordingResolver {
+    identity: Option<Arc<CertifiedKey>>,
+    request: Mutex<Option<CertificateRequest>>,
+}
+
+impl RecordingResolver {
+    pub(crate) fn new(identity: Option<&ClientIdentity>) -> eyre::Result<Self> {
+        let identity = identity
+            .map(|identity| -> eyre::Result<_> {
+                let key = rustls::crypto::aws_lc_rs::sign::any_supported_type(&identity.key)
+                    .wrap_err("unsupported client private key")?;
+
+                Ok(Arc::new(CertifiedKey::new(identity.certs.clone(), key)))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            identity,
+            request: Mutex::new(None),
+        })
+    }
+
+    /// Returns the server's certificate request, if one was received.
+    pub(crate) fn request(&self) -> Option<CertificateRequest> {
+        self.request.lock().unwrap().clone()
+    }
+}
+
+impl ResolvesClientCert for RecordingResolver {
+    fn resolve(
+        &self,
+        root_hint_subjects: &[&[u8]],
+        sigschemes: &[SignatureScheme],
+    ) -> Option<Arc<CertifiedKey>> {
+        let ca_names = root_hint_subjects
+            .iter()
+            .map(|der| match Name::from_der(der) {
+                Ok(name) => name.to_string(),
+                Err(err) => format!("<malformed name: {err}>"),
+            })
+            .collect();
+
+        let signature_schemes = sigschemes
+            .iter()
+            .map(|scheme| format!("{scheme:?}"))
+            .collect();
+
+        tracing::debug!("server requested client certificate");
+
+        *self.request.lock().unwrap() = Some(CertificateRequest {
+            ca_names,
+            signature_schemes,
+            cert_sent: self.identity.is_some(),
+        });
+
+        self.identity.clone()
+    }
+
+    fn has_certs(&self) -> bool {
+        self.identity.is_some()
+    }
+}
+
+impl CertificateRequest {
+    /// Writes certificate request details in text form.
+    pub(crate) fn write_info(&self, mut wrt: impl io::Write) -> io::Result<()> {
+        writeln!(wrt, "Certificate Request:")?;
+
+        if self.ca_names.is_empty() {
+            writeln!(wrt, "  Acceptable CA Names: <any>")?;
+        } else {
+            writeln!(wrt, "  Acceptable CA Names:")?;
+            for name in &self.ca_names {
+                writeln!(wrt, "    {name}")?;
+            }
+        }
+
+        writeln!(wrt, "  Signature Schemes:")?;
+        for scheme in &self.signature_schemes {
+            writeln!(wrt, "    {scheme}")?;
+        }
+
+        writeln!(wrt, "  Client Certificate Sent: {}", self.cert_sent)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        cert::fixtures,
+        fetch::{self, TlsVersion, test_server},
+    };
+
+    fn load(cert: &str, key: Option<&str>, password: Option<&str>) -> eyre::Result<ClientIdentity> {
+        let key = key.map(fixtures::path);
+        ClientIdentity::load(&fixtures::path(cert), key.as_deref(), password)
+    }
+
+    #[test]
+    fn load_pem_with_separate_key() {
+        let identity = load("tls/client.pem", Some("tls/client.key"), None).unwrap();
+        assert_eq!(identity.certs.len(), 1);
+
+        let err = load("tls/client.pem", None, None).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("no private key found"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn load_pem_with_bag_attributes() {
+        // written by `openssl pkcs12 -nodes`, which prefixes each block with a text preamble
+        let identity = load("tls/client-nodes.pem", None, None).unwrap();
+        assert_eq!(identity.certs.len(), 2);
+    }
+
+    #[test]
+    fn load_pkcs12() {
+        let identity = load("tls/client.p12", None, Some("secret")).unwrap();
+        assert_eq!(identity.certs.len(), 2);
+        assert!(matches!(identity.key, PrivateKeyDer::Pkcs8(_)));
+
+        let err = load("tls/client.p12", None, Some("wrong")).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("could not read PKCS#12 archive"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn read_password_first_line() {
+        let path = camino::Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!(
+                "inspect-cert-chain-password-{}",
+                std::process::id()
+            ));
+
+        fs::write(&path, "secret\nignored\n").unwrap();
+        let password = read_password(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(password.unwrap(), "secret");
+    }
+
+    #[test]
+    fn sends_requested_certificate() {
+        let (addr, server) = test_server::spawn(test_server::config(true));
+
+        let identity = load("tls/client.p12", None, Some("secret")).unwrap();
+        let opts = fetch::Options {
+            client_identity: Some(Arc::new(identity)),
+            ..test_server::options(addr)
+        };
+        let chain = fetch::cert_chain("localhost", 443, &opts).unwrap();
+        server.join().unwrap();
+
+        assert_eq!(chain.certs.len(), 1);
+        assert!(chain.connection.handshake_error.is_none());
+
+        let req = chain.connection.certificate_request.unwrap();
+        assert_eq!(req.ca_names, ["CN=Test TLS CA"]);
+        assert!(
+            req.signature_schemes
+                .contains(&"ECDSA_NISTP256_SHA256".to_owned()),
+            "{:?}",
+            req.signature_schemes
+        );
+        assert!(req.cert_sent);
+    }
+
+    #[test]
+    fn keeps_chain_when_client_auth_aborted() {
+        let (addr, server) = test_server::spawn(test_server::config(true));
+
+        // in TLS 1.2 the server aborts the handshake itself when no certificate is sent
+        let opts = fetch::Options {
+            tls_version: TlsVersion::Tls12,
+            ..test_server::options(addr)
+        };
+        let chain = fetch::cert_chain("localhost", 443, &opts).unwrap();
+        server.join().unwrap();
+
+        assert_eq!(chain.certs.len(), 1);
+        assert_eq!(
+            chain.connection.protocol_version.as_deref(),
+            Some("TLS 1.2")
+        );
+
+        let err = chain.connection.handshake_error.unwrap();
+        assert!(err.contains("failed to complete TLS handshake"), "{err}");
+
+        let req = chain.connection.certificate_request.unwrap();
+        assert_eq!(req.ca_names, ["CN=Test TLS CA"]);
+        assert!(!req.cert_sent);
+    }
+
+    #[test]
+    fn write_info() {
+        let req = CertificateRequest {
+            ca_names: vec!["CN=Test TLS CA".to_owned()],
+            signature_schemes: vec![
+                "ECDSA_NISTP256_SHA256".to_owned(),
+                "RSA_PSS_SHA256".to_owned(),
+            ],
+            cert_sent: false,
+        };
+
+        let mut out = Vec::new();
+        req.write_info(&mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+Certificate Request:
+  Acceptable CA Names:
+    CN=Test TLS CA
+  Signature Schemes:
+    ECDSA_NISTP256_SHA256
+    RSA_PSS_SHA256
+  Client Certificate Sent: false
+"
+        );
+
+        let req = CertificateRequest {
+            ca_names: Vec::new(),
+            ..req
+        };
+
+        let mut out = Vec::new();
+        req.write_info(&mut out).unwrap();
+        assert!(
+            String::from_utf8(out)
+                .unwrap()
+                .contains("  Acceptable CA Names: <any>\n")
+        );
+    }
+}
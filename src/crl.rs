@@ -0,0 +1,426 @@
+use std::{fs, io};
+
+use crossterm::style::Stylize as _;
+use der::Decode as _;
+use eyre::{WrapErr as _, eyre};
+use x509_cert::{
+    Certificate,
+    crl::CertificateList,
+    ext::pkix::{
+        BasicConstraints, CrlDistributionPoints, KeyUsage,
+        crl::{CrlReason, IssuingDistributionPoint},
+        name::DistributionPointName,
+    },
+};
+
+use crate::{
+    cert::{Cert, find_issuer},
+    revocation::Status,
+    signature, util,
+    verify::Check,
+};
+
+/// Certificate revocation list along with where it was loaded from.
+#[derive(Debug)]
+pub(crate) struct Crl {
+    pub(crate) source: String,
+    pub(crate) list: CertificateList,
+
+    /// Original DER encoding, over which the signature is verified.
+    der: Vec<u8>,
+}
+
+/// Loads all CRLs from a DER or PEM file.
+pub(crate) fn load(path: &camino::Utf8Path) -> eyre::Result<Vec<Crl>> {
+    let data = fs::read(path).wrap_err_with(|| format!("could not open file: {path}"))?;
+    parse(&data, path.as_str()).wrap_err_with(|| format!("could not read CRL file: {path}"))
+}
+
+/// Parses one DER-encoded CRL, or any number of PEM-encoded CRLs.
+pub(crate) fn parse(data: &[u8], source: &str) -> eyre::Result<Vec<Crl>> {
+    let ders = if data.starts_with(b"-----BEGIN") {
+        rustls_pemfile::crls(&mut io::Cursor::new(data))
+            .map(|der| der.map(|der| der.to_vec()))
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        vec![data.to_vec()]
+    };
+
+    if ders.is_empty() {
+        return Err(eyre!("no CRLs found"));
+    }
+
+    ders.into_iter()
+        .map(|der| {
+            Ok(Crl {
+                source: source.to_owned(),
+                list: CertificateList::from_der(&der)?,
+                der,
+            })
+        })
+        .collect()
+}
+
+/// Revocation check of one certificate against the supplied CRLs.
+#[derive(Debug)]
+pub(crate) struct CertCheck {
+    /// Source of the CRL that covers this certificate, if any.
+    pub(crate) crl: Option<String>,
+    pub(crate) checks: Vec<Check>,
+    pub(crate) status: Status,
+
+    /// Why the CRL could not determine the certificate's status, if it could not.
+    pub(crate) unusable: Option<&'static str>,
+}
+
+/// Checks each certificate in the chain against the CRL issued for it, if one was supplied.
+pub(crate) fn check_chain(certs: &[Cert], crls: &[Crl]) -> Vec<CertCheck> {
+    (0..certs.len())
+        .map(|idx| check_cert(idx, certs, crls))
+        .collect()
+}
+
+/// Checks the certificate at `idx` against the first CRL in scope for it.
+pub(crate) fn check_cert(idx: usize, certs: &[Cert], crls: &[Crl]) -> CertCheck {
+    let cert = &certs[idx];
+    let tbs = cert.tbs_certificate();
+
+    let Some(crl) = crls.iter().find(|crl| in_scope(crl, cert)) else {
+        return CertCheck {
+            crl: None,
+            checks: Vec::new(),
+            status: Status::Unknown,
+            unusable: None,
+        };
+    };
+
+    let crl_tbs = &crl.list.tbs_cert_list;
+
+    // CRLs in scope are issued by the certificate's issuer
+    let issuer = find_issuer(idx, certs).map(|idx| &certs[idx]);
+
+    let signed = Check::new(
+        "CRL signed by issuer",
+        match issuer {
+            Some(issuer) => verify_signature(crl, issuer),
+            None => Err("issuer not in chain".to_owned()),
+        },
+    );
+
+    let current = Check::new("CRL is current", check_currency(crl));
+
+    // an unverified or outdated CRL can neither confirm nor refute revocation
+    let unusable = if signed.result.is_err() {
+        Some("CRL signature not verified")
+    } else if current.result.is_err() {
+        Some("CRL is not current")
+    } else {
+        None
+    };
+
+    let checks = vec![
+        signed,
+        Check::new(
+            "issuer may sign CRLs",
+            match issuer.map(|issuer| issuer.tbs_certificate().get_extension::<KeyUsage>()) {
+                Some(Ok(Some((_, usage)))) if !usage.crl_sign() => {
+                    Err("issuer key usage does not allow CRL signing".to_owned())
+                }
+                Some(Err(err)) => Err(format!("malformed key usage: {err}")),
+                Some(Ok(_)) => Ok(()),
+                None => Err("issuer not in chain".to_owned()),
+            },
+        ),
+        current,
+    ];
+
+    let status = crl_tbs
+        .revoked_certificates
+        .iter()
+        .flatten()
+        .find(|entry| entry.serial_number == *tbs.serial_number())
+        .map_or(Status::Good, |entry| Status::Revoked {
+            time: entry.revocation_date,
+            reason: entry
+                .crl_entry_extensions
+                .iter()
+                .flatten()
+                .find(|ext| ext.extn_id == const_oid::db::rfc5280::ID_CE_CRL_REASONS)
+                .and_then(|ext| CrlReason::from_der(ext.extn_value.as_bytes()).ok()),
+        });
+
+    let status = if unusable.is_none() {
+        status
+    } else {
+        Status::Unknown
+    };
+
+    CertCheck {
+        crl: Some(crl.source.clone()),
+        checks,
+        status,
+        unusable,
+    }
+}
+
+/// Writes CRL revocation results for each certificate in text form.
+pub(crate) fn write_report(
+    certs: &[Cert],
+    crls: &[Crl],
+    results: &[CertCheck],
+    mut wrt: impl io::Write,
+) -> io::Result<()> {
+    writeln!(wrt, "Revocation (CRL)")?;
+    writeln!(wrt, "================")?;
+    writeln!(wrt, "CRLs Loaded: {}", crls.len())?;
+
+    for (idx, (cert, result)) in certs.iter().zip(results).enumerate() {
+        writeln!(wrt)?;
+        writeln!(wrt, "[{idx}] {}", cert.tbs_certificate().subject())?;
+
+        let Some(source) = &result.crl else {
+            writeln!(
+                wrt,
+                "  Status: {} (no matching CRL)",
+                result.status.describe()
+            )?;
+            continue;
+        };
+
+        writeln!(wrt, "  CRL: {source}")?;
+
+        for check in &result.checks {
+            match &check.result {
+                Ok(()) => writeln!(wrt, "  {} {}", "✓".green(), check.name)?,
+                Err(reason) => writeln!(wrt, "  {} {}: {reason}", "✗".red(), check.name)?,
+            }
+        }
+
+        let status = result.status.describe();
+        match result.status {
+            Status::Revoked { .. } => writeln!(wrt, "  Status: {}", status.red().bold())?,
+            Status::Unknown => match result.unusable {
+                Some(reason) => writeln!(wrt, "  Status: {status} ({reason})")?,
+                None => writeln!(wrt, "  Status: {status}")?,
+            },
+            Status::Good => writeln!(wrt, "  Status: {status}")?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns true if CRL was issued by the certificate's issuer and its scope covers the certificate.
+fn in_scope(crl: &Crl, cert: &Certificate) -> bool {
+    let tbs = cert.tbs_certificate();
+
+    if crl.list.tbs_cert_list.issuer != *tbs.issuer() {
+        return false;
+    }
+
+    let idp = crl
+        .list
+        .tbs_cert_list
+        .crl_extensions
+        .iter()
+        .flatten()
+        .find(|ext| ext.extn_id == const_oid::db::rfc5280::ID_CE_ISSUING_DISTRIBUTION_POINT)
+        .map(|ext| IssuingDistributionPoint::from_der(ext.extn_value.as_bytes()));
+
+    let idp = match idp {
+        None => return true,
+        Some(Ok(idp)) => idp,
+        Some(Err(err)) => {
+            tracing::warn!(source = %crl.source, "malformed issuing distribution point: {err}");
+            return false;
+        }
+    };
+
+    // indirect CRLs are not supported and attribute certificate CRLs never cover chain certificates
+    if idp.indirect_crl || idp.only_contains_attribute_certs {
+        return false;
+    }
+
+    let is_ca = matches!(
+        tbs.get_extension::<BasicConstraints>(),
+        Ok(Some((_, constraints))) if constraints.ca
+    );
+
+    if (idp.only_contains_user_certs && is_ca) || (idp.only_contains_ca_certs && !is_ca) {
+        return false;
+    }
+
+    let Some(DistributionPointName::FullName(idp_names)) = &idp.distribution_point else {
+        return true;
+    };
+
+    // the certificate must name one of the CRL's distribution points
+    let cert_dps = match tbs.get_extension::<CrlDistributionPoints>() {
+        Ok(Some((_, dps))) => dps.0,
+        _ => return false,
+    };
+
+    cert_dps
+        .iter()
+        .filter_map(|dp| match &dp.distribution_point {
+            Some(DistributionPointName::FullName(names)) => Some(names),
+            _ => None,
+        })
+        .flatten()
+        .any(|name| idp_names.contains(name))
+}
+
+fn verify_signature(crl: &Crl, issuer: &Certificate) -> Result<(), String> {
+    let tbs = signature::tbs_bytes(&crl.der).map_err(|err| err.reason().to_owned())?;
+
+    let signature = crl
+        .list
+        .signature
+        .as_bytes()
+        .ok_or_else(|| "signature has unused bits".to_owned())?;
+
+    signature::verify(
+        tbs,
+        &crl.list.signature_algorithm,
+        signature,
+        issuer.tbs_certificate().subject_public_key_info(),
+    )
+    .map_err(|err| err.reason().to_owned())
+}
+
+fn check_currency(crl: &Crl) -> Result<(), String> {
+    let tbs = &crl.list.tbs_cert_list;
+
+    let (this_update, this_in_future) = util::duration_since_now_fmt(tbs.this_update);
+    if this_in_future {
+        return Err(format!(
+            "not valid until {} ({this_update})",
+            tbs.this_update
+        ));
+    }
+
+    match tbs.next_update {
+        Some(next_update) => match util::duration_since_now_fmt(next_update) {
+            (_, true) => Ok(()),
+            (ago, false) => Err(format!("expired at {next_update} ({ago})")),
+        },
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cert::fixtures;
+
+    fn load_fixture(name: &str) -> Vec<Crl> {
+        parse(&fixtures::read(name), name).unwrap()
+    }
+
+    #[test]
+    fn good_certificate() {
+        let certs = fixtures::chain(&["pki/good.pem", "pki/ca.pem"]);
+        let check = check_cert(0, &certs, &load_fixture("pki/ca.crl"));
+
+        assert_eq!(check.crl.as_deref(), Some("pki/ca.crl"));
+        assert!(check.checks.iter().all(|check| check.result.is_ok()));
+        assert!(matches!(check.status, Status::Good));
+        assert_eq!(check.unusable, None);
+    }
+
+    #[test]
+    fn revoked_certificate() {
+        let certs = fixtures::chain(&["pki/revoked.pem", "pki/ca.pem"]);
+        let check = check_cert(0, &certs, &load_fixture("pki/ca.crl"));
+
+        assert!(matches!(
+            check.status,
+            Status::Revoked {
+                reason: Some(CrlReason::KeyCompromise),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn expired_crl_is_unusable() {
+        let certs = fixtures::chain(&["pki/revoked.pem", "pki/ca.pem"]);
+        let check = check_cert(0, &certs, &load_fixture("pki/expired.crl.pem"));
+
+        assert!(check.checks[0].result.is_ok());
+        assert!(
+            check
+                .checks
+                .iter()
+                .any(|check| check.name == "CRL is current" && check.result.is_err())
+        );
+        assert!(matches!(check.status, Status::Unknown));
+        assert_eq!(check.unusable, Some("CRL is not current"));
+    }
+
+    #[test]
+    fn modified_crl_is_unusable() {
+        let mut der = fixtures::read("pki/ca.crl");
+
+        // change the seconds of thisUpdate, the first UTCTime in the CRL
+        let pos = der.windows(2).position(|tl| tl == [0x17, 0x0d]).unwrap() + 2 + 11;
+        der[pos] = if der[pos] == b'0' { b'1' } else { b'0' };
+
+        let certs = fixtures::chain(&["pki/revoked.pem", "pki/ca.pem"]);
+        let check = check_cert(0, &certs, &parse(&der, "modified").unwrap());
+
+        assert!(check.checks[0].result.is_err());
+        assert!(matches!(check.status, Status::Unknown));
+        assert_eq!(check.unusable, Some("CRL signature not verified"));
+    }
+
+    #[test]
+    fn issuer_missing_from_chain() {
+        let certs = fixtures::certs("pki/good.pem");
+        let check = check_cert(0, &certs, &load_fixture("pki/ca.crl"));
+
+        assert_eq!(
+            check.checks[0].result,
+            Err("issuer not in chain".to_owned())
+        );
+        assert!(matches!(check.status, Status::Unknown));
+    }
+
+    #[test]
+    fn scope_requires_matching_issuer() {
+        let crl = &load_fixture("pki/ca.crl")[0];
+
+        assert!(in_scope(crl, &fixtures::certs("pki/good.pem")[0]));
+        assert!(in_scope(crl, &fixtures::certs("pki/ca.pem")[0]));
+        assert!(!in_scope(crl, &fixtures::certs("generalized-time.pem")[0]));
+    }
+
+    #[test]
+    fn scope_honors_issuing_distribution_point() {
+        let leaf = &fixtures::certs("pki/good.pem")[0];
+        let ca = &fixtures::certs("pki/ca.pem")[0];
+
+        let ca_only = &load_fixture("pki/idp-ca-only.crl.pem")[0];
+        assert!(!in_scope(ca_only, leaf));
+        assert!(in_scope(ca_only, ca));
+
+        assert!(in_scope(&load_fixture("pki/idp-dp.crl.pem")[0], leaf));
+        assert!(!in_scope(
+            &load_fixture("pki/idp-other-dp.crl.pem")[0],
+            leaf
+        ));
+
+        // the root names no distribution points so cannot match one
+        assert!(!in_scope(&load_fixture("pki/idp-dp.crl.pem")[0], ca));
+    }
+
+    #[test]
+    fn checks_each_certificate_in_chain() {
+        let certs = fixtures::chain(&["pki/revoked.pem", "pki/ca.pem"]);
+        let checks = check_chain(&certs, &load_fixture("pki/ca.crl"));
+
+        assert_eq!(checks.len(), 2);
+        assert!(matches!(checks[0].status, Status::Revoked { .. }));
+        assert!(matches!(checks[1].status, Status::Good));
+    }
+}
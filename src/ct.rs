@@ -0,0 +1,621 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+};
+
+use base64::Engine as _;
+use const_oid::db::{rfc5912, rfc6962::CT_PRECERT_SCTS};
+use crossterm::style::Stylize as _;
+use der::{
+    Decode as _, Encode as _, Reader as _, SliceReader, Tag, TagNumber, Tagged as _, asn1::AnyRef,
+};
+use eyre::WrapErr as _;
+use serde::Deserialize;
+use sha2::Digest as _;
+use x509_cert::{
+    ext::{
+        Extension,
+        pkix::sct::{HashAlgorithm, SignatureAlgorithm, SignedCertificateTimestamp},
+    },
+    spki::{AlgorithmIdentifierOwned, SubjectPublicKeyInfoOwned},
+};
+
+use crate::{
+    cert::{Cert, find_issuer},
+    sct::{DeliveredSct, Source},
+    signature,
+};
+
+/// CT log list in the format published by Chrome and Apple (version 3 schema).
+#[derive(Debug, Deserialize)]
+struct LogListJson {
+    operators: Vec<OperatorJson>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OperatorJson {
+    name: String,
+
+    #[serde(default)]
+    logs: Vec<LogJson>,
+
+    #[serde(default)]
+    tiled_logs: Vec<LogJson>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LogJson {
+    description: String,
+    log_id: String,
+    key: String,
+
+    #[serde(default)]
+    state: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+/// Certificate Transparency logs known to the user.
+#[derive(Debug)]
+pub(crate) struct LogList {
+    logs: Vec<Log>,
+}
+
+/// A single CT log from the log list.
+#[derive(Debug)]
+pub(crate) struct Log {
+    pub(crate) description: String,
+    pub(crate) operator: String,
+    pub(crate) log_id: Vec<u8>,
+    pub(crate) key: SubjectPublicKeyInfoOwned,
+
+    /// Log state (e.g., "usable", "retired").
+    pub(crate) state: Option<String>,
+}
+
+impl Log {
+    /// Returns true if SCTs from this log count towards the CT policy.
+    pub(crate) fn is_qualifying(&self) -> bool {
+        matches!(
+            self.state.as_deref(),
+            Some("usable" | "qualified" | "readonly")
+        )
+    }
+}
+
+impl LogList {
+    /// Loads a Chrome or Apple format log list JSON file.
+    pub(crate) fn load(path: &camino::Utf8Path) -> eyre::Result<Self> {
+        let json = fs::read(path).wrap_err_with(|| format!("could not open file: {path}"))?;
+
+        let list = serde_json::from_slice::<LogListJson>(&json)
+            .wrap_err_with(|| format!("could not parse CT log list: {path}"))?;
+
+        let mut logs = Vec::new();
+
+        for operator in list.operators {
+            for log in operator.logs.into_iter().chain(operator.tiled_logs) {
+                let b64 = base64::engine::general_purpose::STANDARD;
+
+                let (log_id, key) = match (b64.decode(&log.log_id), b64.decode(&log.key)) {
+                    (Ok(log_id), Ok(key)) => (log_id, key),
+                    _ => {
+                        tracing::warn!(log = %log.description, "skipping log with invalid base64");
+                        continue;
+                    }
+                };
+
+                let key = match SubjectPublicKeyInfoOwned::from_der(&key) {
+                    Ok(key) => key,
+                    Err(err) => {
+                        tracing::warn!(log = %log.description, "skipping log with invalid key: {err}");
+                        continue;
+                    }
+                };
+
+                logs.push(Log {
+                    description: log.description,
+                    operator: operator.name.clone(),
+                    log_id,
+                    key,
+                    state: log.state.and_then(|state| state.keys().next().cloned()),
+                });
+            }
+        }
+
+        tracing::debug!("loaded {} CT logs from {path}", logs.len());
+
+        Ok(Self { logs })
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.logs.len()
+    }
+
+    fn find(&self, log_id: &[u8]) -> Option<&Log> {
+        self.logs.iter().find(|log| log.log_id == log_id)
+    }
+}
+
+/// Minimum requirements for SCTs accompanying a leaf certificate.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Policy {
+    /// Minimum number of distinct qualifying logs with a valid SCT.
+    pub(crate) min_scts: usize,
+
+    /// Minimum number of distinct log operators among valid SCTs.
+    pub(crate) min_operators: usize,
+}
+
+/// Result of checking one SCT.
+#[derive(Debug)]
+pub(crate) struct SctCheck<'a> {
+    pub(crate) source: Source,
+    pub(crate) log_id: [u8; 32],
+    pub(crate) log: Option<&'a Log>,
+    pub(crate) result: Result<(), signature::Error>,
+}
+
+/// Results of checking all SCTs for the leaf certificate against a policy.
+#[derive(Debug)]
+pub(crate) struct Report<'a> {
+    pub(crate) checks: Vec<SctCheck<'a>>,
+
+    /// Number of distinct qualifying logs with a valid SCT.
+    pub(crate) valid_logs: usize,
+
+    pub(crate) operators: BTreeSet<&'a str>,
+    pub(crate) policy: Policy,
+}
+
+impl Report<'_> {
+    pub(crate) fn is_compliant(&self) -> bool {
+        self.valid_logs >= self.policy.min_scts && self.operators.len() >= self.policy.min_operators
+    }
+}
+
+/// Verifies each SCT against its log's key and evaluates the CT policy.
+///
+/// SCTs embedded in the certificate are verified over the reconstructed precertificate entry, which
+/// requires the issuer to be present in the chain. Other SCTs are verified over the certificate.
+/// Precertificates issued by a dedicated precertificate signing certificate are not supported.
+///
+/// Only one valid SCT per log, from logs which are usable, qualified or read-only, counts towards
+/// the policy.
+pub(crate) fn check<'a>(
+    scts: &[DeliveredSct],
+    certs: &[Cert],
+    log_list: &'a LogList,
+    policy: Policy,
+) -> Report<'a> {
+    let leaf = &certs[0];
+
+    let checks = scts
+        .iter()
+        .map(|delivered| {
+            let log = log_list.find(&delivered.sct.log_id.key_id);
+
+            let result = match log {
+                None => Err(signature::Error::Unsupported(
+                    "log not in log list".to_owned(),
+                )),
+                Some(log) => signed_entry(delivered.source, leaf, certs)
+                    .map_err(signature::Error::Unsupported)
+                    .and_then(|entry| verify_sct(&delivered.sct, &entry, log)),
+            };
+
+            SctCheck {
+                source: delivered.source,
+                log_id: delivered.sct.log_id.key_id,
+                log,
+                result,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let valid_logs = checks
+        .iter()
+        .filter(|check| check.result.is_ok())
+        .filter_map(|check| check.log)
+        .filter(|log| log.is_qualifying())
+        .map(|log| (log.log_id.as_slice(), log))
+        .collect::<BTreeMap<_, _>>();
+
+    Report {
+        valid_logs: valid_logs.len(),
+        operators: valid_logs
+            .values()
+            .map(|log| log.operator.as_str())
+            .collect(),
+        checks,
+        policy,
+    }
+}
+
+/// Writes SCT verification results and policy outcome in text form.
+pub(crate) fn write_report(
+    log_list: &LogList,
+    report: &Report<'_>,
+    mut wrt: impl io::Write,
+) -> io::Result<()> {
+    writeln!(wrt, "Certificate Transparency")?;
+    writeln!(wrt, "========================")?;
+    writeln!(wrt, "Known Logs: {}", log_list.len())?;
+
+    for check in &report.checks {
+        writeln!(wrt)?;
+
+        match check.log {
+            Some(log) => {
+                writeln!(wrt, "[{}] {}", check.source, log.description)?;
+                writeln!(wrt, "  Operator: {}", log.operator)?;
+                if let Some(state) = &log.state {
+                    writeln!(wrt, "  State: {state}")?;
+                }
+            }
+            None => writeln!(
+                wrt,
+                "[{}] unknown log {}",
+                check.source,
+                base64::engine::general_purpose::STANDARD.encode(check.log_id),
+            )?,
+        }
+
+        match &check.result {
+            Ok(()) => writeln!(wrt, "  {} signature verified", "✓".green())?,
+            Err(signature::Error::Invalid(reason)) => {
+                writeln!(wrt, "  {} signature invalid: {reason}", "✗".red())?;
+            }
+            Err(signature::Error::Unsupported(reason)) => {
+                writeln!(wrt, "  {} signature unchecked: {reason}", "✗".red())?;
+            }
+        }
+
+        if check.result.is_ok()
+            && let Some(log) = check.log
+            && !log.is_qualifying()
+        {
+            writeln!(
+                wrt,
+                "  Not counted: log is {}",
+                log.state.as_deref().unwrap_or("in no state")
+            )?;
+        }
+    }
+
+    writeln!(wrt)?;
+    writeln!(
+        wrt,
+        "Policy: valid SCTs from at least {} distinct log(s) run by at least {} distinct operator(s)",
+        report.policy.min_scts, report.policy.min_operators,
+    )?;
+
+    let summary = format!(
+        "valid SCTs from {} log(s) run by {} operator(s)",
+        report.valid_logs,
+        report.operators.len(),
+    );
+
+    if report.is_compliant() {
+        writeln!(
+            wrt,
+            "Result: {} ({summary})",
+            "policy satisfied".green().bold()
+        )?;
+    } else {
+        writeln!(
+            wrt,
+            "Result: {} ({summary})",
+            "policy NOT satisfied".red().bold()
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Log entry type of the signed data, as defined in RFC 6962 §3.1.
+enum SignedEntry {
+    X509(Vec<u8>),
+    Precert {
+        issuer_key_hash: [u8; 32],
+        tbs: Vec<u8>,
+    },
+}
+
+fn signed_entry(source: Source, leaf: &Cert, certs: &[Cert]) -> Result<SignedEntry, String> {
+    match source {
+        Source::OcspStaple => Ok(SignedEntry::X509(leaf.der().to_vec())),
+
+        Source::Certificate => {
+            let issuer = find_issuer(0, certs)
+                .map(|idx| &certs[idx])
+                .ok_or_else(|| "issuer not in chain".to_owned())?;
+
+            let issuer_spki = issuer
+                .tbs_certificate()
+                .subject_public_key_info()
+                .to_der()
+                .map_err(|err| format!("failed to encode issuer key: {err}"))?;
+
+            let tbs = precert_tbs(leaf)
+                .map_err(|err| format!("failed to reconstruct precertificate: {err}"))?;
+
+            Ok(SignedEntry::Precert {
+                issuer_key_hash: sha2::Sha256::digest(issuer_spki).into(),
+                tbs,
+            })
+        }
+    }
+}
+
+/// Removes the SCT list extension from the leaf's original TBS certificate encoding.
+fn precert_tbs(leaf: &Cert) -> der::Result<Vec<u8>> {
+    let cert = AnyRef::from_der(leaf.der())?;
+    let tbs = SliceReader::new(cert.value())?.decode::<AnyRef<'_>>()?;
+
+    let mut reader = SliceReader::new(tbs.value())?;
+    let mut body = Vec::new();
+
+    while !reader.is_finished() {
+        let field = reader.tlv_bytes()?;
+        let tag = AnyRef::from_der(field)?.tag();
+
+        if tag
+            != (Tag::ContextSpecific {
+                constructed: true,
+                number: TagNumber(3),
+            })
+        {
+            body.extend_from_slice(field);
+            continue;
+        }
+
+        // extensions are [3] EXPLICIT SEQUENCE OF Extension
+        let exts = AnyRef::from_der(AnyRef::from_der(field)?.value())?;
+        let mut exts_reader = SliceReader::new(exts.value())?;
+        let mut kept = Vec::new();
+
+        while !exts_reader.is_finished() {
+            let ext = exts_reader.tlv_bytes()?;
+
+            if Extension::from_der(ext)?.extn_id != CT_PRECERT_SCTS {
+                kept.extend_from_slice(ext);
+            }
+        }
+
+        let exts = AnyRef::new(Tag::Sequence, &kept)?.to_der()?;
+        AnyRef::new(tag, &exts)?.encode_to_vec(&mut body)?;
+    }
+
+    AnyRef::new(Tag::Sequence, &body)?.to_der()
+}
+
+/// Verifies SCT signature over the digitally-signed structure from RFC 6962 §3.2.
+fn verify_sct(
+    sct: &SignedCertificateTimestamp,
+    entry: &SignedEntry,
+    log: &Log,
+) -> Result<(), signature::Error> {
+    let mut msg = vec![
+        0, // sct_version: v1
+        0, // signature_type: certificate_timestamp
+    ];
+    msg.extend_from_slice(&sct.timestamp.to_be_bytes());
+
+    match entry {
+        SignedEntry::X509(cert) => {
+            msg.extend_from_slice(&0_u16.to_be_bytes());
+            msg.extend_from_slice(&u24_len(cert.len()));
+            msg.extend_from_slice(cert);
+        }
+        SignedEntry::Precert {
+            issuer_key_hash,
+            tbs,
+        } => {
+            msg.extend_from_slice(&1_u16.to_be_bytes());
+            msg.extend_from_slice(issuer_key_hash);
+            msg.extend_from_slice(&u24_len(tbs.len()));
+            msg.extend_from_slice(tbs);
+        }
+    }
+
+    let extensions = sct.extensions.as_slice();
+    msg.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+    msg.extend_from_slice(extensions);
+
+    let alg = match (
+        &sct.signature.algorithm.signature,
+        &sct.signature.algorithm.hash,
+    ) {
+        (SignatureAlgorithm::Ecdsa, HashAlgorithm::Sha256) => AlgorithmIdentifierOwned {
+            oid: rfc5912::ECDSA_WITH_SHA_256,
+            parameters: None,
+        },
+        (SignatureAlgorithm::Rsa, HashAlgorithm::Sha256) => AlgorithmIdentifierOwned {
+            oid: rfc5912::SHA_256_WITH_RSA_ENCRYPTION,
+            parameters: Some(der::asn1::Null.into()),
+        },
+        (sig, hash) => {
+            return Err(signature::Error::Unsupported(format!(
+                "unsupported signature algorithm {sig:?} with {hash:?}"
+            )));
+        }
+    };
+
+    signature::verify(&msg, &alg, sct.signature.signature.as_slice(), &log.key)
+}
+
+fn u24_len(len: usize) -> [u8; 3] {
+    let [_, hi, mid, lo] = (len as u32).to_be_bytes();
+    [hi, mid, lo]
+}
+
+#[cfg(test)]
+mod tests {
+    use x509_cert::TbsCertificate;
+
+    use super::*;
+    use crate::{cert::fixtures, sct};
+
+    const POLICY: Policy = Policy {
+        min_scts: 2,
+        min_operators: 2,
+    };
+
+    fn log_list() -> LogList {
+        LogList::load(&fixtures::path("ct/log-list.json")).unwrap()
+    }
+
+    #[test]
+    fn counts_distinct_qualifying_logs() {
+        let certs = fixtures::chain(&["ct/leaf.pem", "pki/ca.pem"]);
+        let scts = sct::collect(&certs[0], None);
+        let log_list = log_list();
+
+        let report = check(&scts, &certs, &log_list, POLICY);
+
+        // two SCTs from log A, one each from logs B and C (retired) and one from an unlisted log
+        let descriptions = report
+            .checks
+            .iter()
+            .map(|check| check.log.map(|log| log.description.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            descriptions,
+            [
+                Some("Test Log A"),
+                Some("Test Log A"),
+                Some("Test Log B"),
+                Some("Test Log C"),
+                None,
+            ]
+        );
+
+        assert!(report.checks[..4].iter().all(|check| check.result.is_ok()));
+        assert!(matches!(
+            &report.checks[4].result,
+            Err(signature::Error::Unsupported(reason)) if reason == "log not in log list"
+        ));
+
+        assert_eq!(report.valid_logs, 2);
+        assert_eq!(
+            report.operators.iter().copied().collect::<Vec<_>>(),
+            ["Operator A", "Operator B"]
+        );
+        assert!(report.is_compliant());
+
+        let stricter = check(
+            &scts,
+            &certs,
+            &log_list,
+            Policy {
+                min_scts: 3,
+                ..POLICY
+            },
+        );
+        assert!(!stricter.is_compliant());
+    }
+
+    #[test]
+    fn report_explains_failures() {
+        let certs = fixtures::chain(&["ct/leaf.pem", "pki/ca.pem"]);
+        let scts = sct::collect(&certs[0], None);
+        let log_list = log_list();
+
+        let mut out = Vec::new();
+        write_report(
+            &log_list,
+            &check(&scts, &certs, &log_list, POLICY),
+            &mut out,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.contains("Not counted: log is retired"));
+        assert!(out.contains("signature unchecked: log not in log list"));
+        assert!(!out.contains("signature invalid"));
+    }
+
+    #[test]
+    fn embedded_scts_require_issuer() {
+        let certs = fixtures::certs("ct/leaf.pem");
+        let scts = sct::collect(&certs[0], None);
+        let log_list = log_list();
+
+        let report = check(&scts, &certs, &log_list, POLICY);
+
+        assert!(matches!(
+            &report.checks[0].result,
+            Err(signature::Error::Unsupported(reason)) if reason == "issuer not in chain"
+        ));
+        assert_eq!(report.valid_logs, 0);
+    }
+
+    #[test]
+    fn verify_sct_over_precert_entry() {
+        let certs = fixtures::chain(&["ct/leaf.pem", "pki/ca.pem"]);
+        let scts = sct::collect(&certs[0], None);
+        let log_list = log_list();
+        let log = log_list.find(&scts[0].sct.log_id.key_id).unwrap();
+
+        let entry = signed_entry(Source::Certificate, &certs[0], &certs).unwrap();
+        verify_sct(&scts[0].sct, &entry, log).unwrap();
+
+        // wrong log key
+        let other_log = log_list.find(&scts[2].sct.log_id.key_id).unwrap();
+        assert!(matches!(
+            verify_sct(&scts[0].sct, &entry, other_log),
+            Err(signature::Error::Invalid(_))
+        ));
+
+        // signed over the precertificate, not the final certificate
+        let x509 = signed_entry(Source::OcspStaple, &certs[0], &certs).unwrap();
+        assert!(matches!(
+            verify_sct(&scts[0].sct, &x509, log),
+            Err(signature::Error::Invalid(_))
+        ));
+
+        let SignedEntry::Precert {
+            issuer_key_hash,
+            mut tbs,
+        } = entry
+        else {
+            unreachable!();
+        };
+        let last = tbs.len() - 1;
+        tbs[last] ^= 1;
+        let modified = SignedEntry::Precert {
+            issuer_key_hash,
+            tbs,
+        };
+        assert!(matches!(
+            verify_sct(&scts[0].sct, &modified, log),
+            Err(signature::Error::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn precert_tbs_removes_only_sct_list() {
+        let leaf = &fixtures::certs("ct/leaf.pem")[0];
+
+        let precert = precert_tbs(leaf).unwrap();
+        let precert = TbsCertificate::from_der(&precert).unwrap();
+
+        let ext_ids = |tbs: &TbsCertificate| {
+            tbs.extensions()
+                .into_iter()
+                .flatten()
+                .map(|ext| ext.extn_id)
+                .collect::<Vec<_>>()
+        };
+
+        let mut expected = ext_ids(leaf.tbs_certificate());
+        assert!(expected.contains(&CT_PRECERT_SCTS));
+        expected.retain(|id| *id != CT_PRECERT_SCTS);
+
+        assert_eq!(ext_ids(&precert), expected);
+        assert_eq!(precert.subject(), leaf.tbs_certificate().subject());
+        assert_eq!(
+            precert.serial_number(),
+            leaf.tbs_certificate().serial_number()
+        );
+    }
+}
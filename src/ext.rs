@@ -1,153 +1,483 @@
 use std::{borrow::Cow, net::IpAddr};
 
-use const_oid::AssociatedOid as _;
-use der::Decode;
+use const_oid::{
+    AssociatedOid as _, ObjectIdentifier,
+    db::rfc5912::{ID_QT_CPS, ID_QT_UNOTICE},
+};
+use der::{
+    Any, Decode, Encode as _, Sequence, Tag, Tagged as _,
+    asn1::{BitString, Ia5String},
+};
 use itertools::Itertools;
-use x509_cert::ext::{
-    Extension,
-    pkix::{self, AuthorityKeyIdentifier, crl::dp, name::GeneralName, sct},
+use serde::Serialize;
+use x509_cert::{
+    ext::{
+        Extension,
+        pkix::{self, certpolicy::PolicyQualifierInfo, crl::dp, name::GeneralName, sct},
+    },
+    time::Time,
 };
 
-use crate::util::{oid_desc_or_raw, openssl_hex};
-
-pub(crate) fn interpret_val(ext: &Extension) -> String {
-    match ext.extn_id {
-        pkix::SubjectKeyIdentifier::OID => fmt_subject_key_identifier(ext),
-        pkix::SubjectAltName::OID => fmt_subject_alt_name(ext),
-        pkix::CertificatePolicies::OID => fmt_certificate_policies(ext),
-        pkix::BasicConstraints::OID => fmt_basic_constraints(ext),
-        pkix::AuthorityInfoAccessSyntax::OID => fmt_authority_info_access_syntax(ext),
-        pkix::KeyUsage::OID => fmt_key_usage(ext),
-        pkix::ExtendedKeyUsage::OID => fmt_extended_key_usage(ext),
-        pkix::AuthorityKeyIdentifier::OID => fmt_authority_key_identifier(ext),
-        pkix::CrlDistributionPoints::OID => fmt_crl_distribution_points(ext),
-        sct::SignedCertificateTimestampList::OID => fmt_sct_list(ext),
-        _ => openssl_hex(ext.extn_value.as_bytes(), 80).join("\n    "),
-    }
+use crate::{
+    asn1,
+    model::{DistinguishedName, Hex, Instant, Oid},
+    util::openssl_hex,
+};
+
+/// Decoded extension value.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum Value {
+    SubjectKeyIdentifier {
+        key_id: Hex,
+    },
+    SubjectAltName {
+        names: Vec<GeneralNameInfo>,
+    },
+    CertificatePolicies {
+        policies: Vec<PolicyInfo>,
+    },
+    BasicConstraints {
+        ca: bool,
+        path_len_constraint: Option<u8>,
+    },
+    AuthorityInfoAccess {
+        descriptions: Vec<AccessDescription>,
+    },
+    KeyUsage {
+        usages: Vec<String>,
+    },
+    ExtendedKeyUsage {
+        usages: Vec<Oid>,
+    },
+    AuthorityKeyIdentifier {
+        key_id: Option<Hex>,
+        issuer: Option<Vec<GeneralNameInfo>>,
+        serial: Option<Hex>,
+    },
+    CrlDistributionPoints {
+        points: Vec<DistributionPoint>,
+    },
+    SctList {
+        scts: Vec<Sct>,
+    },
+    NameConstraints {
+        permitted: Option<Vec<GeneralSubtree>>,
+        excluded: Option<Vec<GeneralSubtree>>,
+    },
+    PolicyConstraints {
+        require_explicit_policy: Option<u32>,
+        inhibit_policy_mapping: Option<u32>,
+    },
+    PolicyMappings {
+        mappings: Vec<PolicyMapping>,
+    },
+    InhibitAnyPolicy {
+        skip_certs: u32,
+    },
+    PrivateKeyUsagePeriod {
+        not_before: Option<Instant>,
+        not_after: Option<Instant>,
+    },
+    NetscapeCertType {
+        types: Vec<&'static str>,
+    },
+
+    /// Extension which is not decoded.
+    Unknown {
+        der: Hex,
+    },
+
+    /// Extension which failed to decode.
+    Malformed {
+        error: String,
+        der: Hex,
+    },
 }
 
-fn fmt_key_usage(ext: &Extension) -> String {
-    let key_usage = pkix::KeyUsage::from_der(ext.extn_value.as_bytes()).unwrap();
-    key_usage
-        .0
-        .into_iter()
-        .map(|ku| format!("{ku:?}"))
-        .join(", ")
+/// General name, tagged with its type.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub(crate) enum GeneralNameInfo {
+    /// Other name; the value is the DER encoding of the element inside its explicit tag.
+    Other {
+        type_id: Oid,
+        value: Hex,
+    },
+    Rfc822(String),
+    Dns(String),
+    Directory(DistinguishedName),
+
+    /// EDI party name, left undecoded.
+    EdiParty {
+        der: Hex,
+    },
+    Uri(String),
+    Ip(String),
+    RegisteredId(Oid),
 }
 
-fn fmt_extended_key_usage(ext: &Extension) -> String {
-    let key_usage = pkix::ExtendedKeyUsage::from_der(ext.extn_value.as_bytes()).unwrap();
-    key_usage.0.iter().map(oid_desc_or_raw).join("\n    ")
+#[derive(Debug, Serialize)]
+pub(crate) struct PolicyInfo {
+    pub(crate) policy: Oid,
+    pub(crate) qualifiers: Option<Vec<PolicyQualifier>>,
 }
 
-fn fmt_authority_key_identifier(ext: &Extension) -> String {
-    let aki = pkix::AuthorityKeyIdentifier::from_der(ext.extn_value.as_bytes()).unwrap();
-    let key_id = fmt_aki_key_id(&aki);
-    let issuer = fmt_aki_issuer(&aki);
-    let serial = fmt_aki_serial(&aki);
-    format!("{key_id}{issuer}{serial}")
+#[derive(Debug, Serialize)]
+pub(crate) struct PolicyQualifier {
+    #[serde(flatten)]
+    pub(crate) oid: Oid,
+
+    #[serde(flatten)]
+    pub(crate) value: QualifierValue,
 }
 
-fn fmt_aki_key_id(aki: &AuthorityKeyIdentifier) -> String {
-    if let Some(ref key_id) = aki.key_identifier {
-        format!("KeyId: {}", openssl_hex(key_id.as_bytes(), 20).join("\n"))
-    } else {
-        String::new()
-    }
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum QualifierValue {
+    Cps {
+        uri: String,
+    },
+    UserNotice {
+        organization: Option<String>,
+        notice_numbers: Option<Vec<u64>>,
+        explicit_text: Option<String>,
+    },
+    Unknown {
+        der: Option<Hex>,
+    },
 }
 
-fn fmt_aki_issuer(aki: &AuthorityKeyIdentifier) -> String {
-    if let Some(ref issuer) = aki.authority_cert_issuer {
-        format!(
-            "{}Issuer: {}",
-            if aki.key_identifier.is_some() {
-                "\n    "
-            } else {
-                "    "
-            },
-            issuer.iter().map(fmt_general_name).join(", ")
-        )
-    } else {
-        String::new()
-    }
+/// User notice policy qualifier as defined in [RFC 5280 §4.2.1.4].
+///
+/// Display texts are left undecoded as they may be any of several string types.
+///
+/// [RFC 5280 §4.2.1.4]: https://datatracker.ietf.org/doc/html/rfc5280#section-4.2.1.4
+#[derive(Debug, Sequence)]
+struct UserNotice {
+    notice_ref: Option<NoticeReference>,
+    explicit_text: Option<Any>,
 }
 
-fn fmt_aki_serial(aki: &AuthorityKeyIdentifier) -> String {
-    if let Some(ref serial) = aki.authority_cert_serial_number {
-        format!(
-            "{}Serial: {}",
-            if aki.key_identifier.is_some() || aki.authority_cert_issuer.is_some() {
-                "\n    "
-            } else {
-                "    "
-            },
-            openssl_hex(serial.as_bytes(), 20).join("\n")
-        )
-    } else {
-        String::new()
-    }
+#[derive(Debug, Sequence)]
+struct NoticeReference {
+    organization: Any,
+    notice_numbers: Vec<u64>,
 }
 
-fn fmt_crl_distribution_points(ext: &Extension) -> String {
-    let crl_dp = pkix::CrlDistributionPoints::from_der(ext.extn_value.as_bytes()).unwrap();
-    crl_dp.0.iter().map(fmt_crl_distribution_point).join(", ")
+/// Name constraint subtree; IP address bases are given as `<address>/<mask>`.
+#[derive(Debug, Serialize)]
+pub(crate) struct GeneralSubtree {
+    pub(crate) base: GeneralNameInfo,
+    pub(crate) minimum: u32,
+    pub(crate) maximum: Option<u32>,
 }
 
-fn fmt_crl_distribution_point(dp: &dp::DistributionPoint) -> String {
-    let name = fmt_dp_name(dp);
-    let issuer = fmt_dp_crl_issuer(dp);
-    let reason = fmt_dp_reasons(dp);
-    format!("{name}{issuer}{reason}")
+#[derive(Debug, Serialize)]
+pub(crate) struct PolicyMapping {
+    pub(crate) issuer_domain_policy: Oid,
+    pub(crate) subject_domain_policy: Oid,
 }
 
-fn fmt_dp_name(dp: &dp::DistributionPoint) -> String {
-    if let Some(ref dp_name) = dp.distribution_point {
-        match dp_name {
-            pkix::name::DistributionPointName::FullName(names) => {
+#[derive(Debug, Serialize)]
+pub(crate) struct AccessDescription {
+    pub(crate) method: Oid,
+    pub(crate) location: GeneralNameInfo,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct DistributionPoint {
+    pub(crate) full_name: Option<Vec<GeneralNameInfo>>,
+    pub(crate) relative_name: Option<String>,
+    pub(crate) crl_issuer: Option<Vec<GeneralNameInfo>>,
+    pub(crate) reasons: Option<Vec<&'static str>>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Sct {
+    pub(crate) version: String,
+    pub(crate) log_id: Hex,
+    pub(crate) timestamp: String,
+
+    /// Timestamp as given in the SCT, in milliseconds since the Unix epoch.
+    pub(crate) unix_time_ms: u64,
+    pub(crate) extensions: Hex,
+    pub(crate) signature_algorithm: String,
+    pub(crate) signature: Hex,
+}
+
+/// Netscape certificate type extension, still found in some older roots.
+const NETSCAPE_CERT_TYPE: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.113730.1.1");
+
+/// Names of the Netscape certificate type bits, as used by OpenSSL.
+const NETSCAPE_CERT_TYPES: [&str; 8] = [
+    "SSL Client",
+    "SSL Server",
+    "S/MIME",
+    "Object Signing",
+    "Unused",
+    "SSL CA",
+    "S/MIME CA",
+    "Object Signing CA",
+];
+
+/// Decodes extension value into its structured form.
+pub(crate) fn decode(ext: &Extension) -> Value {
+    let der = ext.extn_value.as_bytes();
+
+    let res = match ext.extn_id {
+        pkix::SubjectKeyIdentifier::OID => decode_subject_key_identifier(der),
+        pkix::SubjectAltName::OID => decode_subject_alt_name(der),
+        pkix::CertificatePolicies::OID => decode_certificate_policies(der),
+        pkix::BasicConstraints::OID => decode_basic_constraints(der),
+        pkix::AuthorityInfoAccessSyntax::OID => decode_authority_info_access_syntax(der),
+        pkix::KeyUsage::OID => decode_key_usage(der),
+        pkix::ExtendedKeyUsage::OID => decode_extended_key_usage(der),
+        pkix::AuthorityKeyIdentifier::OID => decode_authority_key_identifier(der),
+        pkix::CrlDistributionPoints::OID => decode_crl_distribution_points(der),
+        sct::SignedCertificateTimestampList::OID => decode_sct_list(der),
+        pkix::NameConstraints::OID => decode_name_constraints(der),
+        pkix::PolicyConstraints::OID => decode_policy_constraints(der),
+        pkix::PolicyMappings::OID => decode_policy_mappings(der),
+        pkix::InhibitAnyPolicy::OID => decode_inhibit_any_policy(der),
+        pkix::PrivateKeyUsagePeriod::OID => decode_private_key_usage_period(der),
+        NETSCAPE_CERT_TYPE => decode_netscape_cert_type(der),
+        _ => Ok(Value::Unknown {
+            der: Hex(der.to_vec()),
+        }),
+    };
+
+    res.unwrap_or_else(|error| Value::Malformed {
+        error,
+        der: Hex(der.to_vec()),
+    })
+}
+
+impl Value {
+    /// Formats extension value for text output.
+    pub(crate) fn fmt_text(&self) -> String {
+        match self {
+            Self::SubjectKeyIdentifier { key_id } => openssl_hex(&key_id.0, 20).join("\n    "),
+            Self::SubjectAltName { names } => names.iter().map(fmt_general_name_info).join(", "),
+            Self::CertificatePolicies { policies } => fmt_certificate_policies(policies),
+            Self::BasicConstraints {
+                ca,
+                path_len_constraint,
+            } => {
+                let path_len = path_len_constraint
+                    .map_or(Cow::Borrowed("None"), |c| Cow::Owned(c.to_string()));
+                format!("CA: {ca}\n    Path Length Constraint: {path_len}")
+            }
+            Self::AuthorityInfoAccess { descriptions } => descriptions
+                .iter()
+                .map(|desc| {
+                    format!(
+                        "{}  {}",
+                        desc.method.describe(),
+                        fmt_general_name_info(&desc.location)
+                    )
+                })
+                .join("\n    "),
+            Self::KeyUsage { usages } => usages.join(", "),
+            Self::ExtendedKeyUsage { usages } => usages.iter().map(Oid::describe).join("\n    "),
+            Self::AuthorityKeyIdentifier {
+                key_id,
+                issuer,
+                serial,
+            } => fmt_authority_key_identifier(key_id.as_ref(), issuer.as_deref(), serial.as_ref()),
+            Self::CrlDistributionPoints { points } => {
+                points.iter().map(fmt_crl_distribution_point).join(", ")
+            }
+            Self::SctList { scts } => scts.iter().map(fmt_sct_info).join("\n    "),
+            Self::NameConstraints {
+                permitted,
+                excluded,
+            } => fmt_name_constraints(permitted.as_deref(), excluded.as_deref()),
+            Self::PolicyConstraints {
+                require_explicit_policy,
+                inhibit_policy_mapping,
+            } => {
+                let fmt_skip_certs = |skip_certs: &Option<u32>| {
+                    skip_certs.map_or(Cow::Borrowed("None"), |n| Cow::Owned(n.to_string()))
+                };
+
                 format!(
-                    "FullName:\n      {}",
-                    names.iter().map(fmt_general_name).join(", ")
+                    "Require Explicit Policy: {}\n    Inhibit Policy Mapping: {}",
+                    fmt_skip_certs(require_explicit_policy),
+                    fmt_skip_certs(inhibit_policy_mapping),
                 )
             }
-            pkix::name::DistributionPointName::NameRelativeToCRLIssuer(name) => {
-                format!("RelativeName:\n      {name}")
-            }
+            Self::PolicyMappings { mappings } => mappings
+                .iter()
+                .map(|mapping| {
+                    format!(
+                        "{} -> {}",
+                        mapping.issuer_domain_policy.describe(),
+                        mapping.subject_domain_policy.describe()
+                    )
+                })
+                .join("\n    "),
+            Self::InhibitAnyPolicy { skip_certs } => format!("Skip Certs: {skip_certs}"),
+            Self::PrivateKeyUsagePeriod {
+                not_before,
+                not_after,
+            } => [("Not Before", not_before), ("Not After", not_after)]
+                .into_iter()
+                .filter_map(|(label, time)| Some(format!("{label}: {}", time.as_ref()?.time)))
+                .join("\n    "),
+            Self::NetscapeCertType { types } => types.join(", "),
+            Self::Unknown { der } => match asn1::parse(&der.0) {
+                Ok(nodes) => asn1::fmt_tree(&nodes).join("\n    "),
+                Err(_) => openssl_hex(&der.0, 80).join("\n    "),
+            },
+            Self::Malformed { error, der } => format!(
+                "<malformed: {error}>\n    {}",
+                openssl_hex(&der.0, 80).join("\n    ")
+            ),
         }
-    } else {
-        String::new()
     }
 }
 
-fn fmt_dp_crl_issuer(dp: &dp::DistributionPoint) -> String {
-    if let Some(ref issuer) = dp.crl_issuer {
-        format!(
+fn decode_key_usage(der: &[u8]) -> Result<Value, String> {
+    let key_usage = pkix::KeyUsage::from_der(der).map_err(|err| err.to_string())?;
+
+    Ok(Value::KeyUsage {
+        usages: key_usage
+            .0
+            .into_iter()
+            .map(|ku| format!("{ku:?}"))
+            .collect(),
+    })
+}
+
+fn decode_extended_key_usage(der: &[u8]) -> Result<Value, String> {
+    let key_usage = pkix::ExtendedKeyUsage::from_der(der).map_err(|err| err.to_string())?;
+
+    Ok(Value::ExtendedKeyUsage {
+        usages: key_usage.0.iter().map(Oid::new).collect(),
+    })
+}
+
+fn decode_authority_key_identifier(der: &[u8]) -> Result<Value, String> {
+    let aki = pkix::AuthorityKeyIdentifier::from_der(der).map_err(|err| err.to_string())?;
+
+    Ok(Value::AuthorityKeyIdentifier {
+        key_id: aki
+            .key_identifier
+            .map(|key_id| Hex(key_id.as_bytes().to_vec())),
+        issuer: aki
+            .authority_cert_issuer
+            .map(|names| names.iter().map(GeneralNameInfo::new).collect()),
+        serial: aki
+            .authority_cert_serial_number
+            .map(|serial| Hex(serial.as_bytes().to_vec())),
+    })
+}
+
+fn fmt_authority_key_identifier(
+    key_id: Option<&Hex>,
+    issuer: Option<&[GeneralNameInfo]>,
+    serial: Option<&Hex>,
+) -> String {
+    let key_id_str = match key_id {
+        Some(key_id) => format!("KeyId: {}", openssl_hex(&key_id.0, 20).join("\n")),
+        None => String::new(),
+    };
+
+    let issuer_str = match issuer {
+        Some(issuer) => format!(
             "{}Issuer: {}",
-            if dp.distribution_point.is_some() {
+            if key_id.is_some() { "\n    " } else { "    " },
+            issuer.iter().map(fmt_general_name_info).join(", ")
+        ),
+        None => String::new(),
+    };
+
+    let serial_str = match serial {
+        Some(serial) => format!(
+            "{}Serial: {}",
+            if key_id.is_some() || issuer.is_some() {
                 "\n    "
             } else {
                 "    "
             },
-            issuer.iter().map(fmt_general_name).join(", ")
-        )
-    } else {
-        String::new()
-    }
+            openssl_hex(&serial.0, 20).join("\n")
+        ),
+        None => String::new(),
+    };
+
+    format!("{key_id_str}{issuer_str}{serial_str}")
 }
 
-fn fmt_dp_reasons(dp: &dp::DistributionPoint) -> String {
-    if let Some(ref reasons) = dp.reasons {
-        format!(
+fn decode_crl_distribution_points(der: &[u8]) -> Result<Value, String> {
+    let crl_dp = pkix::CrlDistributionPoints::from_der(der).map_err(|err| err.to_string())?;
+
+    Ok(Value::CrlDistributionPoints {
+        points: crl_dp
+            .0
+            .iter()
+            .map(|dp| {
+                let (full_name, relative_name) = match &dp.distribution_point {
+                    Some(pkix::name::DistributionPointName::FullName(names)) => {
+                        (Some(names.iter().map(GeneralNameInfo::new).collect()), None)
+                    }
+                    Some(pkix::name::DistributionPointName::NameRelativeToCRLIssuer(name)) => {
+                        (None, Some(name.to_string()))
+                    }
+                    None => (None, None),
+                };
+
+                DistributionPoint {
+                    full_name,
+                    relative_name,
+                    crl_issuer: dp
+                        .crl_issuer
+                        .as_ref()
+                        .map(|names| names.iter().map(GeneralNameInfo::new).collect()),
+                    reasons: dp
+                        .reasons
+                        .map(|reasons| reasons.into_iter().map(fmt_reason).collect()),
+                }
+            })
+            .collect(),
+    })
+}
+
+fn fmt_crl_distribution_point(dp: &DistributionPoint) -> String {
+    let has_name = dp.full_name.is_some() || dp.relative_name.is_some();
+
+    let name = match (&dp.full_name, &dp.relative_name) {
+        (Some(names), _) => format!(
+            "FullName:\n      {}",
+            names.iter().map(fmt_general_name_info).join(", ")
+        ),
+        (None, Some(name)) => format!("RelativeName:\n      {name}"),
+        (None, None) => String::new(),
+    };
+
+    let issuer = match &dp.crl_issuer {
+        Some(issuer) => format!(
+            "{}Issuer: {}",
+            if has_name { "\n    " } else { "    " },
+            issuer.iter().map(fmt_general_name_info).join(", ")
+        ),
+        None => String::new(),
+    };
+
+    let reasons = match &dp.reasons {
+        Some(reasons) => format!(
             "{}Reasons: {}",
-            if dp.distribution_point.is_some() || dp.crl_issuer.is_some() {
+            if has_name || dp.crl_issuer.is_some() {
                 "\n    "
             } else {
                 "    "
             },
-            reasons.into_iter().map(fmt_reason).join(", ")
-        )
-    } else {
-        String::new()
-    }
+            reasons.join(", ")
+        ),
+        None => String::new(),
+    };
+
+    format!("{name}{issuer}{reasons}")
 }
 
 fn fmt_reason(reason: dp::Reasons) -> &'static str {
@@ -164,36 +494,57 @@ fn fmt_reason(reason: dp::Reasons) -> &'static str {
     }
 }
 
-fn fmt_sct_list(ext: &Extension) -> String {
-    sct::SignedCertificateTimestampList::from_der(ext.extn_value.as_bytes())
-        .expect("Failed to deserialize SCT list")
+fn decode_sct_list(der: &[u8]) -> Result<Value, String> {
+    let scts = sct::SignedCertificateTimestampList::from_der(der)
+        .map_err(|err| format!("failed to deserialize SCT list: {err}"))?
         .parse_timestamps()
-        .expect("Failed to parse SCT timestamps")
+        .map_err(|err| format!("failed to parse SCT timestamps: {err:?}"))?
         .into_iter()
         .map(|ser_sct| {
             ser_sct
                 .parse_timestamp()
-                .expect("Failed to deserialize SCT timestamp")
+                .map(|sct| Sct::new(&sct))
+                .map_err(|err| format!("failed to deserialize SCT timestamp: {err:?}"))
         })
-        .map(fmt_sct)
-        .join("\n    ")
+        .collect::<Result<_, _>>()?;
+
+    Ok(Value::SctList { scts })
+}
+
+impl Sct {
+    pub(crate) fn new(sct: &sct::SignedCertificateTimestamp) -> Self {
+        Self {
+            version: format!("{:?}", sct.version),
+            log_id: Hex(sct.log_id.key_id.to_vec()),
+            timestamp: sct
+                .timestamp()
+                .map_or_else(|_| "<out of range>".to_owned(), |ts| ts.to_string()),
+            unix_time_ms: sct.timestamp,
+            extensions: Hex(sct.extensions.as_slice().to_vec()),
+            signature_algorithm: fmt_signature_and_hash_alg(&sct.signature.algorithm),
+            signature: Hex(sct.signature.signature.as_slice().to_vec()),
+        }
+    }
+}
+
+pub(crate) fn fmt_sct(sct: &sct::SignedCertificateTimestamp) -> String {
+    fmt_sct_info(&Sct::new(sct))
 }
 
-fn fmt_sct(sct: sct::SignedCertificateTimestamp) -> String {
-    let extensions = openssl_hex(sct.extensions.as_slice(), 16).join("\n                  ");
+fn fmt_sct_info(sct: &Sct) -> String {
+    let extensions = openssl_hex(&sct.extensions.0, 16).join("\n                  ");
     format!(
-        "Signed Certificate Timestamp:\n      Version   : {:?}\n      Log ID    : {}\n      Timestamp : {}\n      Extensions: {}\n      Signature : {}\n                  {}",
+        "Signed Certificate Timestamp:\n      Version   : {}\n      Log ID    : {}\n      Timestamp : {}\n      Extensions: {}\n      Signature : {}\n                  {}",
         sct.version,
-        openssl_hex(&sct.log_id.key_id, 16).join("\n                  "),
-        sct.timestamp()
-            .expect("SCT timestamp is outside supported range"),
+        openssl_hex(&sct.log_id.0, 16).join("\n                  "),
+        sct.timestamp,
         if extensions.is_empty() {
             "none"
         } else {
             &extensions
         },
-        fmt_signature_and_hash_alg(&sct.signature.algorithm),
-        openssl_hex(sct.signature.signature.as_slice(), 16).join("\n                  "),
+        sct.signature_algorithm,
+        openssl_hex(&sct.signature.0, 16).join("\n                  "),
     )
 }
 
@@ -205,93 +556,374 @@ fn fmt_signature_and_hash_alg(alg: &sct::SignatureAndHashAlgorithm) -> String {
     )
 }
 
-fn fmt_authority_info_access_syntax(ext: &Extension) -> String {
+fn decode_authority_info_access_syntax(der: &[u8]) -> Result<Value, String> {
     let authority_info_access =
-        pkix::AuthorityInfoAccessSyntax::from_der(ext.extn_value.as_bytes()).unwrap();
+        pkix::AuthorityInfoAccessSyntax::from_der(der).map_err(|err| err.to_string())?;
 
-    authority_info_access
-        .0
-        .into_iter()
-        .map(|access_description| {
-            format!(
-                "{}  {}",
-                oid_desc_or_raw(&access_description.access_method),
-                fmt_general_name(&access_description.access_location)
-            )
-        })
-        .join("\n    ")
+    Ok(Value::AuthorityInfoAccess {
+        descriptions: authority_info_access
+            .0
+            .iter()
+            .map(|access_description| AccessDescription {
+                method: Oid::new(&access_description.access_method),
+                location: GeneralNameInfo::new(&access_description.access_location),
+            })
+            .collect(),
+    })
 }
 
-fn fmt_basic_constraints(ext: &Extension) -> String {
-    let constraints = pkix::BasicConstraints::from_der(ext.extn_value.as_bytes()).unwrap();
-    let path_len = constraints
-        .path_len_constraint
-        .map_or(Cow::Borrowed("None"), |c| Cow::Owned(format!("{c}")));
-    format!(
-        "CA: {}\n    Path Length Constraint: {path_len}",
-        constraints.ca
-    )
+fn decode_basic_constraints(der: &[u8]) -> Result<Value, String> {
+    let constraints = pkix::BasicConstraints::from_der(der).map_err(|err| err.to_string())?;
+
+    Ok(Value::BasicConstraints {
+        ca: constraints.ca,
+        path_len_constraint: constraints.path_len_constraint,
+    })
+}
+
+fn decode_certificate_policies(der: &[u8]) -> Result<Value, String> {
+    let policies = pkix::CertificatePolicies::from_der(der).map_err(|err| err.to_string())?;
+
+    Ok(Value::CertificatePolicies {
+        policies: policies
+            .0
+            .iter()
+            .map(|info| {
+                Ok(PolicyInfo {
+                    policy: Oid::new(&info.policy_identifier),
+                    qualifiers: info
+                        .policy_qualifiers
+                        .as_ref()
+                        .map(|qualifiers| qualifiers.iter().map(decode_policy_qualifier).collect())
+                        .transpose()?,
+                })
+            })
+            .collect::<Result<_, String>>()?,
+    })
 }
 
-fn fmt_certificate_policies(ext: &Extension) -> String {
-    let policies = pkix::CertificatePolicies::from_der(ext.extn_value.as_bytes()).unwrap();
+fn decode_policy_qualifier(qualifier: &PolicyQualifierInfo) -> Result<PolicyQualifier, String> {
+    let value = match (qualifier.policy_qualifier_id, &qualifier.qualifier) {
+        (ID_QT_CPS, Some(uri)) => QualifierValue::Cps {
+            uri: uri
+                .decode_as::<Ia5String>()
+                .map_err(|err| err.to_string())?
+                .to_string(),
+        },
+
+        (ID_QT_UNOTICE, Some(notice)) => {
+            let notice = notice
+                .decode_as::<UserNotice>()
+                .map_err(|err| err.to_string())?;
+
+            let (organization, notice_numbers) = match notice.notice_ref {
+                Some(notice_ref) => (
+                    Some(display_text(&notice_ref.organization)),
+                    Some(notice_ref.notice_numbers),
+                ),
+                None => (None, None),
+            };
+
+            QualifierValue::UserNotice {
+                organization,
+                notice_numbers,
+                explicit_text: notice.explicit_text.as_ref().map(display_text),
+            }
+        }
+
+        (_, qualifier) => QualifierValue::Unknown {
+            der: qualifier
+                .as_ref()
+                .map(|qualifier| Hex(qualifier.to_der().unwrap_or_default())),
+        },
+    };
+
+    Ok(PolicyQualifier {
+        oid: Oid::new(&qualifier.policy_qualifier_id),
+        value,
+    })
+}
+
+/// Decodes a `DisplayText`, which may be an IA5, visible, BMP or UTF-8 string.
+fn display_text(text: &Any) -> String {
+    match text.tag() {
+        Tag::BmpString => {
+            let units = text
+                .value()
+                .chunks_exact(2)
+                .map(|unit| u16::from_be_bytes([unit[0], unit[1]]));
+
+            char::decode_utf16(units)
+                .map(|ch| ch.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect()
+        }
+        _ => String::from_utf8_lossy(text.value()).into_owned(),
+    }
+}
+
+fn fmt_certificate_policies(policies: &[PolicyInfo]) -> String {
     policies
-        .0
-        .into_iter()
+        .iter()
         .map(|info| {
             let qualifiers = info
-                .policy_qualifiers
+                .qualifiers
+                .as_ref()
                 .map(|qualifiers| {
                     format!(
                         " (qualifiers: {})",
                         qualifiers
-                            .into_iter()
-                            .map(|qualifier| oid_desc_or_raw(&qualifier.policy_qualifier_id))
+                            .iter()
+                            .map(|qualifier| qualifier.oid.describe())
                             .join(", ")
                     )
                 })
                 .unwrap_or_default();
 
-            format!("{}{}", oid_desc_or_raw(&info.policy_identifier), qualifiers)
+            format!("{}{}", info.policy.describe(), qualifiers)
         })
         .join("\n    ")
 }
 
-fn fmt_subject_alt_name(ext: &Extension) -> String {
-    let san = pkix::SubjectAltName::from_der(ext.extn_value.as_bytes()).unwrap();
-    san.0
-        .into_iter()
-        .map(|name| fmt_general_name(&name))
-        .join(", ")
+fn decode_name_constraints(der: &[u8]) -> Result<Value, String> {
+    let constraints = pkix::NameConstraints::from_der(der).map_err(|err| err.to_string())?;
+
+    let subtrees = |subtrees: Option<pkix::constraints::name::GeneralSubtrees>| {
+        subtrees.map(|subtrees| {
+            subtrees
+                .iter()
+                .map(|subtree| GeneralSubtree {
+                    base: GeneralNameInfo::new(&subtree.base),
+                    minimum: subtree.minimum,
+                    maximum: subtree.maximum,
+                })
+                .collect()
+        })
+    };
+
+    Ok(Value::NameConstraints {
+        permitted: subtrees(constraints.permitted_subtrees),
+        excluded: subtrees(constraints.excluded_subtrees),
+    })
+}
+
+fn fmt_name_constraints(
+    permitted: Option<&[GeneralSubtree]>,
+    excluded: Option<&[GeneralSubtree]>,
+) -> String {
+    let fmt_subtrees = |label: &str, subtrees: &[GeneralSubtree]| {
+        let mut subtrees = subtrees.iter().map(|subtree| {
+            let range = match (subtree.minimum, subtree.maximum) {
+                (0, None) => String::new(),
+                (min, None) => format!(" (min: {min})"),
+                (min, Some(max)) => format!(" (min: {min}, max: {max})"),
+            };
+
+            format!("  {}{range}", fmt_general_name_info(&subtree.base))
+        });
+
+        format!("{label}:\n    {}", subtrees.join("\n    "))
+    };
+
+    [
+        permitted.map(|subtrees| fmt_subtrees("Permitted", subtrees)),
+        excluded.map(|subtrees| fmt_subtrees("Excluded", subtrees)),
+    ]
+    .into_iter()
+    .flatten()
+    .join("\n    ")
+}
+
+fn decode_policy_constraints(der: &[u8]) -> Result<Value, String> {
+    let constraints = pkix::PolicyConstraints::from_der(der).map_err(|err| err.to_string())?;
+
+    Ok(Value::PolicyConstraints {
+        require_explicit_policy: constraints.require_explicit_policy,
+        inhibit_policy_mapping: constraints.inhibit_policy_mapping,
+    })
+}
+
+fn decode_policy_mappings(der: &[u8]) -> Result<Value, String> {
+    let mappings = pkix::PolicyMappings::from_der(der).map_err(|err| err.to_string())?;
+
+    Ok(Value::PolicyMappings {
+        mappings: mappings
+            .0
+            .iter()
+            .map(|mapping| PolicyMapping {
+                issuer_domain_policy: Oid::new(&mapping.issuer_domain_policy),
+                subject_domain_policy: Oid::new(&mapping.subject_domain_policy),
+            })
+            .collect(),
+    })
 }
 
-fn fmt_subject_key_identifier(ext: &Extension) -> String {
-    let ski = pkix::SubjectKeyIdentifier::from_der(ext.extn_value.as_bytes()).unwrap();
-    let mut iter = openssl_hex(ski.0.as_bytes(), 20);
-    iter.join("\n    ")
+fn decode_inhibit_any_policy(der: &[u8]) -> Result<Value, String> {
+    let inhibit = pkix::InhibitAnyPolicy::from_der(der).map_err(|err| err.to_string())?;
+
+    Ok(Value::InhibitAnyPolicy {
+        skip_certs: inhibit.0,
+    })
+}
+
+fn decode_private_key_usage_period(der: &[u8]) -> Result<Value, String> {
+    let period = pkix::PrivateKeyUsagePeriod::from_der(der).map_err(|err| err.to_string())?;
+
+    Ok(Value::PrivateKeyUsagePeriod {
+        not_before: period
+            .not_before
+            .map(|time| Instant::new(Time::GeneralTime(time))),
+        not_after: period
+            .not_after
+            .map(|time| Instant::new(Time::GeneralTime(time))),
+    })
 }
 
-//TODO: remove debug format for OtherName, EdiPartyName
-fn fmt_general_name(name: &GeneralName) -> String {
+fn decode_netscape_cert_type(der: &[u8]) -> Result<Value, String> {
+    let bits = BitString::from_der(der).map_err(|err| err.to_string())?;
+
+    Ok(Value::NetscapeCertType {
+        types: bits
+            .bits()
+            .zip(NETSCAPE_CERT_TYPES)
+            .filter_map(|(set, name)| set.then_some(name))
+            .collect(),
+    })
+}
+
+fn decode_subject_alt_name(der: &[u8]) -> Result<Value, String> {
+    let san = pkix::SubjectAltName::from_der(der).map_err(|err| err.to_string())?;
+
+    Ok(Value::SubjectAltName {
+        names: san.0.iter().map(GeneralNameInfo::new).collect(),
+    })
+}
+
+fn decode_subject_key_identifier(der: &[u8]) -> Result<Value, String> {
+    let ski = pkix::SubjectKeyIdentifier::from_der(der).map_err(|err| err.to_string())?;
+
+    Ok(Value::SubjectKeyIdentifier {
+        key_id: Hex(ski.0.as_bytes().to_vec()),
+    })
+}
+
+impl GeneralNameInfo {
+    pub(crate) fn new(name: &GeneralName) -> Self {
+        match name {
+            GeneralName::OtherName(other) => Self::Other {
+                type_id: Oid::new(&other.type_id),
+                value: Hex(other.value.to_der().unwrap_or_default()),
+            },
+            GeneralName::Rfc822Name(rfc) => Self::Rfc822(rfc.as_str().to_owned()),
+            GeneralName::DnsName(dns) => Self::Dns(dns.as_str().to_owned()),
+            GeneralName::DirectoryName(dir) => Self::Directory(DistinguishedName::new(dir)),
+            GeneralName::EdiPartyName(edi) => Self::EdiParty {
+                der: Hex(edi.to_der().unwrap_or_default()),
+            },
+            GeneralName::UniformResourceIdentifier(uri) => Self::Uri(uri.as_str().to_owned()),
+            GeneralName::IpAddress(ip) => Self::Ip(match ip_try_from_bytes(ip.as_bytes()) {
+                Some(ip) => ip.to_string(),
+                None => match ip_range_try_from_bytes(ip.as_bytes()) {
+                    Some((addr, mask)) => format!("{addr}/{mask}"),
+                    None => openssl_hex(ip.as_bytes(), usize::MAX).join(""),
+                },
+            }),
+            GeneralName::RegisteredId(id) => Self::RegisteredId(Oid::new(id)),
+        }
+    }
+}
+
+pub(crate) fn fmt_general_name(name: &GeneralName) -> String {
+    fmt_general_name_info(&GeneralNameInfo::new(name))
+}
+
+fn fmt_general_name_info(name: &GeneralNameInfo) -> String {
     match name {
-        GeneralName::OtherName(other) => format!("OTHER{other:?}"),
-        GeneralName::Rfc822Name(rfc) => format!("RFC:{}", rfc.as_str()),
-        GeneralName::DnsName(dns) => format!("DNS:{}", dns.as_str()),
-        GeneralName::DirectoryName(dir) => format!("DIR:{dir}"),
-        GeneralName::EdiPartyName(edi) => format!("EDI:{edi:?}"),
-        GeneralName::UniformResourceIdentifier(uri) => format!("URI:{}", uri.as_str()),
-        GeneralName::IpAddress(ip) => match ip_try_from_bytes(ip.as_bytes()) {
-            Some(ip) => format!("IP:{ip}"),
-            None => format!("IP:{ip:?}"),
-        },
-        GeneralName::RegisteredId(id) => oid_desc_or_raw(id),
+        GeneralNameInfo::Other { type_id, value } => {
+            format!(
+                "OTHER:{}:{}",
+                type_id.describe(),
+                openssl_hex(&value.0, usize::MAX).join("")
+            )
+        }
+        GeneralNameInfo::Rfc822(rfc) => format!("RFC:{rfc}"),
+        GeneralNameInfo::Dns(dns) => format!("DNS:{dns}"),
+        GeneralNameInfo::Directory(dir) => format!("DIR:{}", dir.rfc4514),
+        GeneralNameInfo::EdiParty { der } => {
+            format!("EDI:{}", openssl_hex(&der.0, usize::MAX).join(""))
+        }
+        GeneralNameInfo::Uri(uri) => format!("URI:{uri}"),
+        GeneralNameInfo::Ip(ip) => format!("IP:{ip}"),
+        GeneralNameInfo::RegisteredId(id) => id.describe().to_owned(),
+    }
+}
+
+/// Splits an IP address range, as used by name constraints, into address and mask.
+fn ip_range_try_from_bytes(bytes: &[u8]) -> Option<(IpAddr, IpAddr)> {
+    if !matches!(bytes.len(), 8 | 32) {
+        return None;
     }
+
+    let (addr, mask) = bytes.split_at(bytes.len() / 2);
+    Some((ip_try_from_bytes(addr)?, ip_try_from_bytes(mask)?))
 }
 
-fn ip_try_from_bytes(bytes: &[u8]) -> Option<IpAddr> {
+pub(crate) fn ip_try_from_bytes(bytes: &[u8]) -> Option<IpAddr> {
     Some(match bytes.len() {
         4 => IpAddr::from(<[u8; 4]>::try_from(bytes).unwrap()),
         16 => IpAddr::from(<[u8; 16]>::try_from(bytes).unwrap()),
         _ => return None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cert::fixtures;
+
+    /// Returns text form of each extension in the `name-constraints.pem` fixture.
+    fn name_constraints_fixture_text() -> Vec<String> {
+        let certs = fixtures::certs("name-constraints.pem");
+
+        certs[0]
+            .tbs_certificate()
+            .extensions()
+            .into_iter()
+            .flatten()
+            .map(|ext| decode(ext).fmt_text())
+            .collect()
+    }
+
+    #[test]
+    fn name_constraints_text() {
+        assert_eq!(
+            name_constraints_fixture_text()[1],
+            "\
+Permitted:
+      DNS:.example.com
+      DNS:example.org
+      IP:10.0.0.0/255.0.0.0
+      IP:2001:db8::/ffff:ffff::
+      RFC:.example.com
+      DIR:O=Example\\, Inc.,C=US
+      URI:.example.net
+    Excluded:
+      DNS:bad.example.com
+      IP:192.168.0.0/255.255.0.0"
+        );
+    }
+
+    #[test]
+    fn policy_controls_text() {
+        let text = name_constraints_fixture_text();
+
+        assert_eq!(
+            text[2],
+            "Require Explicit Policy: 0\n    Inhibit Policy Mapping: 3"
+        );
+        assert_eq!(
+            text[3],
+            "1.3.6.1.4.1.99.1 -> 1.3.6.1.4.1.99.2\n    anyPolicy -> Domain Validated"
+        );
+        assert_eq!(text[4], "Skip Certs: 2");
+    }
+}
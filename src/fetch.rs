@@ -1,7 +1,8 @@
 use std::{
-    io::{Read as _, Write as _},
-    net::TcpStream,
-    sync::Arc,
+    io::{self, Read as _, Write as _},
+    net::{IpAddr, TcpStream},
+    str::FromStr,
+    sync::{Arc, Mutex},
 };
 
 use der::Decode;
@@ -9,26 +10,371 @@ use error_reporter::Report;
 use eyre::WrapErr as _;
 use rustls_pki_types::ServerName;
 use rustls_platform_verifier::BuilderVerifierExt as _;
-use x509_cert::Certificate;
+use serde::Serialize;
 
-pub(crate) fn cert_chain(host: &str, port: u16) -> eyre::Result<Vec<Certificate>> {
-    let server_name = ServerName::try_from(host)
-        .with_context(|| format!("failed to convert given host (\"{host}\") to server name"))?
+use crate::{
+    cert::Cert,
+    client_auth::{CertificateRequest, ClientIdentity, RecordingResolver},
+    net::{self, Deadline},
+    proxy::Proxy,
+    sct::{self, DeliveredSct},
+    starttls,
+};
+
+/// TLS protocol versions which may be offered to the remote host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum TlsVersion {
+    /// Offer TLS 1.2 and TLS 1.3.
+    Auto,
+
+    /// Offer TLS 1.2 only.
+    #[value(name = "1.2")]
+    Tls12,
+
+    /// Offer TLS 1.3 only.
+    #[value(name = "1.3")]
+    Tls13,
+}
+
+impl TlsVersion {
+    fn protocol_versions(self) -> &'static [&'static rustls::SupportedProtocolVersion] {
+        const TLS12: &[&rustls::SupportedProtocolVersion] = &[&rustls::version::TLS12];
+        const TLS13: &[&rustls::SupportedProtocolVersion] = &[&rustls::version::TLS13];
+
+        match self {
+            Self::Auto => rustls::ALL_VERSIONS,
+            Self::Tls12 => TLS12,
+            Self::Tls13 => TLS13,
+        }
+    }
+}
+
+/// Curl-style `host:port:addr` override for the address connected to.
+#[derive(Debug, Clone)]
+pub(crate) struct Resolve {
+    host: String,
+    port: u16,
+    addr: IpAddr,
+}
+
+impl FromStr for Resolve {
+    type Err = String;
+
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        let mut parts = val.splitn(3, ':');
+
+        let (Some(host), Some(port), Some(addr)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err("expected format is <host>:<port>:<addr>".to_owned());
+        };
+
+        let port = port
+            .parse()
+            .map_err(|err| format!("invalid port \"{port}\": {err}"))?;
+
+        let addr = addr
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse()
+            .map_err(|err| format!("invalid address \"{addr}\": {err}"))?;
+
+        Ok(Self {
+            host: host.to_owned(),
+            port,
+            addr,
+        })
+    }
+}
+
+/// Options controlling how the certificate chain is fetched.
+#[derive(Debug, Clone)]
+pub(crate) struct Options {
+    /// Address to connect to instead of the host and port.
+    pub(crate) connect: Option<String>,
+
+    /// Address overrides for specific host and port combinations.
+    pub(crate) resolve: Vec<Resolve>,
+
+    /// Time limits for each phase of the fetch.
+    pub(crate) timeouts: net::Timeouts,
+
+    /// Proxy through which to connect.
+    pub(crate) proxy: Option<Proxy>,
+
+    /// Server name to send in the SNI extension instead of the host.
+    pub(crate) sni: Option<String>,
+
+    /// Whether to send the SNI extension at all.
+    pub(crate) enable_sni: bool,
+
+    pub(crate) tls_version: TlsVersion,
+    pub(crate) alpn_protocols: Vec<String>,
+
+    /// Plaintext protocol to upgrade from before starting the TLS handshake.
+    pub(crate) starttls: Option<starttls::Protocol>,
+
+    /// Send an HTTP request after the handshake instead of closing the connection immediately.
+    pub(crate) http_probe: bool,
+
+    /// Client certificate and key to present if the server requests one.
+    pub(crate) client_identity: Option<Arc<ClientIdentity>>,
+}
+
+/// Certificate chain and handshake details from a remote host.
+#[derive(Debug)]
+pub(crate) struct RemoteChain {
+    pub(crate) certs: Vec<Cert>,
+    pub(crate) connection: ConnectionInfo,
+
+    /// DER-encoded OCSP response stapled by the server, if any.
+    pub(crate) ocsp_response: Option<Vec<u8>>,
+
+    /// SCTs for the leaf certificate from each delivery method.
+    pub(crate) scts: Vec<DeliveredSct>,
+}
+
+/// Parameters negotiated during the TLS handshake.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ConnectionInfo {
+    #[serde(rename = "peer_address")]
+    pub(crate) peer_addr: Option<String>,
+    pub(crate) proxy: Option<String>,
+    pub(crate) server_name: Option<String>,
+    pub(crate) protocol_version: Option<String>,
+    pub(crate) cipher_suite: Option<String>,
+    pub(crate) key_exchange_group: Option<String>,
+    pub(crate) alpn_protocol: Option<String>,
+    pub(crate) handshake_kind: Option<String>,
+    pub(crate) certificate_request: Option<CertificateRequest>,
+
+    /// Reason the handshake failed after the server sent its certificates, if it did.
+    pub(crate) handshake_error: Option<String>,
+}
+
+impl ConnectionInfo {
+    fn from_conn(
+        conn: &rustls::ClientConnection,
+        sock: &TcpStream,
+        proxy: Option<&Proxy>,
+        server_name: Option<&str>,
+        certificate_request: Option<CertificateRequest>,
+        handshake_error: Option<String>,
+    ) -> Self {
+        Self {
+            peer_addr: sock.peer_addr().ok().map(|addr| addr.to_string()),
+            proxy: proxy.map(ToString::to_string),
+            server_name: server_name.map(ToOwned::to_owned),
+            protocol_version: conn.protocol_version().map(|version| match version {
+                rustls::ProtocolVersion::TLSv1_2 => "TLS 1.2".to_owned(),
+                rustls::ProtocolVersion::TLSv1_3 => "TLS 1.3".to_owned(),
+                version => format!("{version:?}"),
+            }),
+            cipher_suite: conn
+                .negotiated_cipher_suite()
+                .map(|suite| format!("{:?}", suite.suite())),
+            key_exchange_group: conn
+                .negotiated_key_exchange_group()
+                .map(|group| format!("{:?}", group.name())),
+            alpn_protocol: conn
+                .alpn_protocol()
+                .map(|proto| String::from_utf8_lossy(proto).into_owned()),
+            handshake_kind: conn.handshake_kind().map(|kind| {
+                match kind {
+                    rustls::HandshakeKind::Full => "full",
+                    rustls::HandshakeKind::FullWithHelloRetryRequest => {
+                        "full (with HelloRetryRequest)"
+                    }
+                    rustls::HandshakeKind::Resumed => "resumed",
+                }
+                .to_owned()
+            }),
+            certificate_request,
+            handshake_error,
+        }
+    }
+
+    /// Writes connection details in text form.
+    pub(crate) fn write_info(&self, mut wrt: impl io::Write) -> io::Result<()> {
+        fn or_none(val: &Option<String>) -> &str {
+            val.as_deref().unwrap_or("<none>")
+        }
+
+        writeln!(wrt, "Connection")?;
+        writeln!(wrt, "==========")?;
+        writeln!(wrt, "Peer Address: {}", or_none(&self.peer_addr))?;
+        if let Some(proxy) = &self.proxy {
+            writeln!(wrt, "Proxy: {proxy}")?;
+        }
+        writeln!(wrt, "Server Name (SNI): {}", or_none(&self.server_name))?;
+        writeln!(wrt, "Protocol Version: {}", or_none(&self.protocol_version))?;
+        writeln!(wrt, "Cipher Suite: {}", or_none(&self.cipher_suite))?;
+        writeln!(
+            wrt,
+            "Key Exchange Group: {}",
+            or_none(&self.key_exchange_group)
+        )?;
+        writeln!(wrt, "ALPN Protocol: {}", or_none(&self.alpn_protocol))?;
+        writeln!(wrt, "Handshake: {}", or_none(&self.handshake_kind))?;
+
+        match &self.certificate_request {
+            Some(req) => req.write_info(&mut wrt)?,
+            None => writeln!(wrt, "Certificate Request: <none>")?,
+        }
+
+        if let Some(err) = &self.handshake_error {
+            writeln!(wrt, "Handshake Error: {err}")?;
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn cert_chain(host: &str, port: u16, opts: &Options) -> eyre::Result<RemoteChain> {
+    let sni = opts.sni.as_deref().unwrap_or(host);
+
+    let server_name = ServerName::try_from(sni)
+        .with_context(|| format!("failed to convert given host (\"{sni}\") to server name"))?
         .to_owned();
 
+    let client_cert_resolver = Arc::new(RecordingResolver::new(opts.client_identity.as_deref())?);
+
     let mut config =
-        rustls::ClientConfig::builder_with_protocol_versions(&[&rustls::version::TLS12])
+        rustls::ClientConfig::builder_with_protocol_versions(opts.tls_version.protocol_versions())
             .with_platform_verifier()?
-            .with_no_client_auth();
+            .with_client_cert_resolver(Arc::clone(&client_cert_resolver) as _);
+
+    config.alpn_protocols = opts
+        .alpn_protocols
+        .iter()
+        .map(|proto| proto.as_bytes().to_vec())
+        .collect();
+
+    config.enable_sni = opts.enable_sni;
+
+    let cert_verifier = Arc::new(NoopServerCertVerifier::default());
 
     config
         .dangerous()
-        .set_certificate_verifier(Arc::new(NoopServerCertVerifier));
+        .set_certificate_verifier(Arc::clone(&cert_verifier) as _);
+
+    let addr = connect_addr(host, port, opts);
+    tracing::debug!(%addr, "connecting");
 
     let mut conn = rustls::ClientConnection::new(Arc::new(config), server_name)?;
-    let mut sock = TcpStream::connect(format!("{host}:{port}"))
-        .wrap_err_with(|| format!("failed to connect to host: {host}:{port}"))?;
-    let mut tls = rustls::Stream::new(&mut conn, &mut sock);
+    let mut sock = match &opts.proxy {
+        Some(proxy) => proxy.connect(&addr, &opts.timeouts)?,
+        None => net::connect(&addr, &opts.timeouts)?,
+    };
+
+    if let Some(proto) = opts.starttls {
+        let deadline = Deadline::start("STARTTLS negotiation", opts.timeouts.io);
+        deadline.arm(&sock)?;
+
+        starttls::upgrade(proto, &mut sock, sni).map_err(|err| deadline.map_err(err))?;
+    }
+
+    tracing::debug!("performing TLS handshake");
+
+    let deadline = Deadline::start("TLS handshake", opts.timeouts.io);
+    let mut handshake_error = None;
+
+    while conn.is_handshaking() {
+        deadline.arm(&sock)?;
+
+        if let Err(err) = conn.complete_io(&mut sock) {
+            let err = deadline
+                .map_err(eyre::Report::new(err).wrap_err("failed to complete TLS handshake"));
+
+            // servers requiring a client certificate may abort after sending their own chain
+            if conn.peer_certificates().is_none() {
+                return Err(err);
+            }
+
+            tracing::warn!("{err:#}; reporting the certificates received before the failure");
+            handshake_error = Some(format!("{err:#}"));
+            break;
+        }
+    }
+
+    if opts.http_probe && handshake_error.is_none() {
+        http_probe(&mut conn, &mut sock, host, &opts.timeouts);
+    }
+
+    // server certificates are available once the handshake completed or got far enough to fail
+    // in the client authentication stage
+    let certs: Vec<Cert> = conn
+        .peer_certificates()
+        .map(|c| c.iter().filter_map(|c| Cert::from_der(c).ok()).collect())
+        .unwrap_or_default();
+
+    // SNI is never sent for IP addresses
+    let sent_sni = (opts.enable_sni && sni.parse::<IpAddr>().is_err()).then_some(sni);
+    let connection = ConnectionInfo::from_conn(
+        &conn,
+        &sock,
+        opts.proxy.as_ref(),
+        sent_sni,
+        client_cert_resolver.request(),
+        handshake_error,
+    );
+
+    if connection.handshake_error.is_none() {
+        conn.send_close_notify();
+        if let Err(err) = conn.complete_io(&mut sock) {
+            tracing::debug!("failed to close connection cleanly: {}", Report::new(err));
+        }
+    }
+
+    let ocsp_response = cert_verifier.ocsp_response.lock().unwrap().take();
+
+    let scts = certs
+        .first()
+        .map(|leaf| sct::collect(leaf, ocsp_response.as_deref()))
+        .unwrap_or_default();
+
+    Ok(RemoteChain {
+        certs,
+        connection,
+        ocsp_response,
+        scts,
+    })
+}
+
+/// Determines the address to connect to, applying `--connect` and `--resolve` overrides.
+fn connect_addr(host: &str, port: u16, opts: &Options) -> String {
+    if let Some(addr) = &opts.connect {
+        return addr.clone();
+    }
+
+    match opts
+        .resolve
+        .iter()
+        .find(|resolve| resolve.host.eq_ignore_ascii_case(host) && resolve.port == port)
+    {
+        Some(Resolve {
+            addr: IpAddr::V6(addr),
+            ..
+        }) => format!("[{addr}]:{port}"),
+        Some(Resolve { addr, .. }) => format!("{addr}:{port}"),
+        None => format!("{host}:{port}"),
+    }
+}
+
+/// Sends a simple HTTP request over the established connection and reads the response.
+fn http_probe(
+    conn: &mut rustls::ClientConnection,
+    sock: &mut TcpStream,
+    host: &str,
+    timeouts: &net::Timeouts,
+) {
+    let deadline = Deadline::start("read", timeouts.io);
+
+    if let Err(err) = deadline.arm(sock) {
+        tracing::warn!("failed to set up HTTP probe: {err}");
+        return;
+    }
+
+    let mut tls = rustls::Stream::new(conn, sock);
 
     let req = format!(
         r#"GET / HTTP/1.1
@@ -44,33 +390,32 @@ Accept-Encoding: identity
 
     tracing::debug!("writing to socket:\n{req}");
 
-    tls.write_all(req.as_bytes())
-        .wrap_err("failed to write to socket")?;
-    tls.flush().wrap_err("failed to flush socket")?;
+    if let Err(err) = tls.write_all(req.as_bytes()).and_then(|()| tls.flush()) {
+        tracing::warn!("failed to write HTTP probe to {host}: {}", Report::new(err));
+        return;
+    }
 
     let mut plaintext = Vec::new();
     match tls.read_to_end(&mut plaintext) {
-        Ok(_) => {}
+        Ok(_) => {
+            let status_line = plaintext.split(|&byte| byte == b'\r').next().unwrap_or(&[]);
+            tracing::info!(
+                "HTTP probe response: {}",
+                String::from_utf8_lossy(status_line)
+            );
+        }
         Err(err) => {
-            tracing::warn!("failed to read from {host}: {}", Report::new(err));
+            let err = deadline.map_err(eyre::Report::new(err));
+            tracing::warn!("failed to read from {host}: {err:#}");
         }
     }
-
-    // peer_certificates method will return certificates by now
-    // because app data has already been written
-    Ok(tls
-        .conn
-        .peer_certificates()
-        .map(|c| {
-            c.iter()
-                .filter_map(|c| Certificate::from_der(c).ok())
-                .collect()
-        })
-        .unwrap_or_default())
 }
 
-#[derive(Debug)]
-struct NoopServerCertVerifier;
+/// Accepts any server certificate, keeping the stapled OCSP response for inspection.
+#[derive(Debug, Default)]
+struct NoopServerCertVerifier {
+    ocsp_response: Mutex<Option<Vec<u8>>>,
+}
 
 impl rustls::client::danger::ServerCertVerifier for NoopServerCertVerifier {
     fn verify_server_cert(
@@ -78,9 +423,13 @@ impl rustls::client::danger::ServerCertVerifier for NoopServerCertVerifier {
         _end_entity: &rustls_pki_types::CertificateDer<'_>,
         _intermediates: &[rustls_pki_types::CertificateDer<'_>],
         _server_name: &ServerName<'_>,
-        _ocsp_response: &[u8],
+        ocsp_response: &[u8],
         _now: rustls_pki_types::UnixTime,
     ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        if !ocsp_response.is_empty() {
+            *self.ocsp_response.lock().unwrap() = Some(ocsp_response.to_vec());
+        }
+
         Ok(rustls::client::danger::ServerCertVerified::assertion())
     }
 
@@ -108,3 +457,220 @@ impl rustls::client::danger::ServerCertVerifier for NoopServerCertVerifier {
             .supported_schemes()
     }
 }
+
+#[cfg(test)]
+pub(crate) mod test_server {
+    use std::{
+        io::{Read as _, Write as _},
+        net::{SocketAddr, TcpListener},
+        sync::Arc,
+        thread,
+        time::Duration,
+    };
+
+    use rustls::server::WebPkiClientVerifier;
+
+    use super::{Options, TlsVersion};
+    use crate::{cert::fixtures, net};
+
+    /// Returns options connecting to a local test server at `addr`.
+    pub(crate) fn options(addr: SocketAddr) -> Options {
+        Options {
+            connect: Some(addr.to_string()),
+            resolve: Vec::new(),
+            timeouts: net::Timeouts::new(Duration::from_secs(5), Duration::from_secs(5)),
+            proxy: None,
+            sni: None,
+            enable_sni: true,
+            tls_version: TlsVersion::Auto,
+            alpn_protocols: Vec::new(),
+            starttls: None,
+            http_probe: false,
+            client_identity: None,
+        }
+    }
+
+    /// Builds a server config presenting the `tls/server.pem` fixture.
+    ///
+    /// When `require_client_cert` is set, clients must present a certificate issued by the
+    /// `tls/ca.pem` fixture.
+    pub(crate) fn config(require_client_cert: bool) -> rustls::ServerConfig {
+        let certs = rustls_pemfile::certs(&mut &*fixtures::read("tls/server.pem"))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let key = rustls_pemfile::private_key(&mut &*fixtures::read("tls/server.key"))
+            .unwrap()
+            .unwrap();
+
+        let builder = rustls::ServerConfig::builder();
+
+        let builder = if require_client_cert {
+            let mut roots = rustls::RootCertStore::empty();
+            for cert in rustls_pemfile::certs(&mut &*fixtures::read("tls/ca.pem")) {
+                roots.add(cert.unwrap()).unwrap();
+            }
+
+            builder.with_client_cert_verifier(
+                WebPkiClientVerifier::builder(Arc::new(roots))
+                    .build()
+                    .unwrap(),
+            )
+        } else {
+            builder.with_no_client_auth()
+        };
+
+        builder.with_single_cert(certs, key).unwrap()
+    }
+
+    /// Accepts a single TLS connection using `config`.
+    ///
+    /// The returned handle yields the plaintext received from the client. A complete HTTP request
+    /// is answered with an empty response before closing the connection.
+    pub(crate) fn spawn(config: rustls::ServerConfig) -> (SocketAddr, thread::JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (mut sock, _) = listener.accept().unwrap();
+            sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+            let mut conn = rustls::ServerConnection::new(Arc::new(config)).unwrap();
+            let mut tls = rustls::Stream::new(&mut conn, &mut sock);
+
+            let mut received = Vec::new();
+            let mut buf = [0; 1024];
+
+            // stops on close_notify, connection close or handshake failure
+            while let Ok(n @ 1..) = tls.read(&mut buf) {
+                received.extend_from_slice(&buf[..n]);
+
+                if received.ends_with(b"\r\n\r\n") {
+                    tls.write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                        .unwrap();
+                    tls.conn.send_close_notify();
+                    tls.flush().unwrap();
+                    break;
+                }
+            }
+
+            received
+        });
+
+        (addr, handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn options(connect: Option<&str>, resolve: &[&str]) -> Options {
+        Options {
+            connect: connect.map(ToOwned::to_owned),
+            resolve: resolve.iter().map(|val| val.parse().unwrap()).collect(),
+            timeouts: net::Timeouts::new(Duration::ZERO, Duration::ZERO),
+            proxy: None,
+            sni: None,
+            enable_sni: true,
+            tls_version: TlsVersion::Auto,
+            alpn_protocols: Vec::new(),
+            starttls: None,
+            http_probe: false,
+            client_identity: None,
+        }
+    }
+
+    #[test]
+    fn parse_resolve() {
+        let resolve = "example.test:443:192.0.2.1".parse::<Resolve>().unwrap();
+        assert_eq!(resolve.host, "example.test");
+        assert_eq!(resolve.port, 443);
+        assert_eq!(resolve.addr, "192.0.2.1".parse::<IpAddr>().unwrap());
+
+        // IPv6 addresses contain colons and may be bracketed
+        for val in [
+            "example.test:443:2001:db8::1",
+            "example.test:443:[2001:db8::1]",
+        ] {
+            let resolve = val.parse::<Resolve>().unwrap();
+            assert_eq!(resolve.addr, "2001:db8::1".parse::<IpAddr>().unwrap());
+        }
+    }
+
+    #[test]
+    fn parse_resolve_errors() {
+        for (val, expected) in [
+            (
+                "example.test:443",
+                "expected format is <host>:<port>:<addr>",
+            ),
+            ("example.test", "expected format is <host>:<port>:<addr>"),
+            ("example.test:https:192.0.2.1", "invalid port \"https\""),
+            ("example.test:65536:192.0.2.1", "invalid port \"65536\""),
+            (
+                "example.test:443:other.test",
+                "invalid address \"other.test\"",
+            ),
+        ] {
+            let err = val.parse::<Resolve>().unwrap_err();
+            assert!(err.starts_with(expected), "{val}: {err}");
+        }
+    }
+
+    #[test]
+    fn connect_addr_overrides() {
+        let opts = options(
+            None,
+            &[
+                "Example.Test:443:192.0.2.1",
+                "example.test:8443:2001:db8::1",
+            ],
+        );
+
+        assert_eq!(connect_addr("example.test", 443, &opts), "192.0.2.1:443");
+        assert_eq!(
+            connect_addr("example.test", 8443, &opts),
+            "[2001:db8::1]:8443"
+        );
+        assert_eq!(connect_addr("example.test", 993, &opts), "example.test:993");
+        assert_eq!(connect_addr("other.test", 443, &opts), "other.test:443");
+
+        // --connect takes precedence
+        let opts = options(Some("192.0.2.9:4443"), &["example.test:443:192.0.2.1"]);
+        assert_eq!(connect_addr("example.test", 443, &opts), "192.0.2.9:4443");
+    }
+
+    #[test]
+    fn closes_after_handshake() {
+        let (addr, server) = test_server::spawn(test_server::config(false));
+
+        let chain = cert_chain("localhost", 443, &test_server::options(addr)).unwrap();
+
+        assert_eq!(chain.certs.len(), 1);
+        assert!(chain.connection.handshake_error.is_none());
+
+        // the connection is closed without sending any application data
+        assert_eq!(server.join().unwrap(), b"");
+    }
+
+    #[test]
+    fn http_probe_sends_request() {
+        let (addr, server) = test_server::spawn(test_server::config(false));
+
+        let opts = Options {
+            http_probe: true,
+            ..test_server::options(addr)
+        };
+        let chain = cert_chain("localhost", 443, &opts).unwrap();
+
+        assert_eq!(chain.certs.len(), 1);
+
+        let received = String::from_utf8(server.join().unwrap()).unwrap();
+        assert!(
+            received.starts_with("GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n"),
+            "{received:?}"
+        );
+    }
+}
@@ -0,0 +1,389 @@
+use std::{io, net::IpAddr};
+
+use const_oid::db::rfc4519;
+use crossterm::style::Stylize as _;
+use der::{Tag, Tagged as _, asn1::Ia5String};
+use x509_cert::{
+    Certificate,
+    ext::pkix::{SubjectAltName, name::GeneralName},
+};
+
+use crate::ext;
+
+/// Outcome of matching a hostname against a leaf certificate.
+#[derive(Debug)]
+pub(crate) enum Outcome {
+    /// Hostname is covered by this certificate entry.
+    Matched { entry: String },
+
+    /// Hostname is not covered; contains each candidate entry and why it did not match.
+    NotMatched { reasons: Vec<(String, String)> },
+}
+
+/// Checks whether `hostname` is covered by the leaf certificate, following RFC 6125.
+///
+/// DNS names are matched case-insensitively and may use a wildcard as the complete left-most
+/// label. IP addresses only match IP address SAN entries. The subject CN is only consulted when the
+/// certificate has no DNS or IP address SAN entries.
+pub(crate) fn check(leaf: &Certificate, hostname: &str) -> Outcome {
+    let hostname = hostname.trim_end_matches('.');
+    let ip = hostname
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+        .ok();
+
+    let san_names = match leaf.tbs_certificate().get_extension::<SubjectAltName>() {
+        Ok(Some((_, san))) => san.0,
+        Ok(None) => Vec::new(),
+        Err(err) => {
+            return Outcome::NotMatched {
+                reasons: vec![(
+                    "Subject Alternate Name".to_owned(),
+                    format!("extension is malformed: {err}"),
+                )],
+            };
+        }
+    };
+
+    let mut reasons = Vec::new();
+    let mut has_identifiers = false;
+
+    for name in &san_names {
+        let result = match (name, ip) {
+            (GeneralName::DnsName(dns), None) => {
+                has_identifiers = true;
+                match_dns_name(dns, hostname)
+            }
+
+            (GeneralName::DnsName(_), Some(_)) => {
+                has_identifiers = true;
+                Err("IP addresses never match DNS names".to_owned())
+            }
+
+            (GeneralName::IpAddress(san_ip), _) => {
+                has_identifiers = true;
+
+                match (ext::ip_try_from_bytes(san_ip.as_bytes()), ip) {
+                    (Some(san_ip), Some(ip)) if san_ip == ip => Ok(()),
+                    (Some(_), Some(_)) => Err("different address".to_owned()),
+                    (Some(_), None) => Err("hostname is not an IP address".to_owned()),
+                    (None, _) => Err("malformed IP address entry".to_owned()),
+                }
+            }
+
+            _ => continue,
+        };
+
+        let entry = ext::fmt_general_name(name);
+
+        match result {
+            Ok(()) => return Outcome::Matched { entry },
+            Err(reason) => reasons.push((entry, reason)),
+        }
+    }
+
+    if has_identifiers {
+        return Outcome::NotMatched { reasons };
+    }
+
+    // legacy fallback for certificates without DNS or IP address SAN entries
+    let common_names = leaf
+        .tbs_certificate()
+        .subject()
+        .iter()
+        .filter(|atv| atv.oid == rfc4519::CN)
+        .filter(|atv| {
+            matches!(
+                atv.value.tag(),
+                Tag::Utf8String | Tag::PrintableString | Tag::Ia5String
+            )
+        })
+        .filter_map(|atv| String::from_utf8(atv.value.value().to_vec()).ok())
+        .collect::<Vec<_>>();
+
+    for cn in common_names {
+        let entry = format!("CN:{cn}");
+
+        let result = match ip {
+            Some(_) => Err("IP addresses never match subject CN".to_owned()),
+            None => match Ia5String::new(&cn) {
+                Ok(dns) => match_dns_name(&dns, hostname),
+                Err(_) => Err("not a valid DNS name".to_owned()),
+            },
+        };
+
+        match result {
+            Ok(()) => {
+                return Outcome::Matched {
+                    entry: format!("{entry} (deprecated subject CN fallback)"),
+                };
+            }
+            Err(reason) => reasons.push((entry, reason)),
+        }
+    }
+
+    if reasons.is_empty() {
+        reasons.push((
+            "<none>".to_owned(),
+            "certificate has no SAN entries or subject CN".to_owned(),
+        ));
+    }
+
+    Outcome::NotMatched { reasons }
+}
+
+fn match_dns_name(pattern: &Ia5String, hostname: &str) -> Result<(), String> {
+    let pattern = pattern.as_str().trim_end_matches('.');
+
+    if pattern.eq_ignore_ascii_case(hostname) {
+        return Ok(());
+    }
+
+    let Some((left_most, pattern_rest)) = pattern.split_once('.') else {
+        return Err("different name".to_owned());
+    };
+
+    if pattern_rest.contains('*') {
+        return Err("wildcard is only allowed in the left-most label".to_owned());
+    }
+
+    if !left_most.contains('*') {
+        return Err("different name".to_owned());
+    }
+
+    if left_most != "*" {
+        return Err("partial-label wildcards are not supported".to_owned());
+    }
+
+    if !pattern_rest.contains('.') {
+        return Err("wildcard must be followed by at least two labels".to_owned());
+    }
+
+    let Some((host_left_most, host_rest)) = hostname.split_once('.') else {
+        return Err("hostname has a single label".to_owned());
+    };
+
+    if host_left_most.is_empty() {
+        return Err("wildcard does not match an empty label".to_owned());
+    }
+
+    if host_left_most.starts_with("xn--") {
+        return Err("wildcard does not match internationalized labels".to_owned());
+    }
+
+    if !host_rest.eq_ignore_ascii_case(pattern_rest) {
+        return Err(
+            if host_rest
+                .to_ascii_lowercase()
+                .ends_with(&format!(".{}", pattern_rest.to_ascii_lowercase()))
+            {
+                "wildcard matches only a single label".to_owned()
+            } else {
+                "different name".to_owned()
+            },
+        );
+    }
+
+    Ok(())
+}
+
+/// Writes hostname verification result in text form.
+pub(crate) fn write_report(
+    hostname: &str,
+    outcome: &Outcome,
+    mut wrt: impl io::Write,
+) -> io::Result<()> {
+    writeln!(wrt, "Hostname")?;
+    writeln!(wrt, "========")?;
+    writeln!(wrt, "Name: {hostname}")?;
+
+    match outcome {
+        Outcome::Matched { entry } => {
+            writeln!(wrt, "Result: {} {entry}", "matches".green().bold())?;
+        }
+
+        Outcome::NotMatched { reasons } => {
+            writeln!(wrt, "Result: {}", "does NOT match".red().bold())?;
+
+            for (entry, reason) in reasons {
+                writeln!(wrt, "  {entry}: {reason}")?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cert::fixtures;
+
+    fn match_pattern(pattern: &str, hostname: &str) -> Result<(), String> {
+        match_dns_name(&Ia5String::new(pattern).unwrap(), hostname)
+    }
+
+    fn check_fixture(name: &str, hostname: &str) -> Outcome {
+        check(&fixtures::certs(name)[0], hostname)
+    }
+
+    fn assert_matched(outcome: Outcome, expected: &str) {
+        match outcome {
+            Outcome::Matched { entry } => assert_eq!(entry, expected),
+            Outcome::NotMatched { reasons } => panic!("expected match, got {reasons:?}"),
+        }
+    }
+
+    fn not_matched(outcome: Outcome) -> Vec<(String, String)> {
+        match outcome {
+            Outcome::Matched { entry } => panic!("expected no match, got {entry}"),
+            Outcome::NotMatched { reasons } => reasons,
+        }
+    }
+
+    #[test]
+    fn exact_names() {
+        assert_eq!(match_pattern("example.test", "example.test"), Ok(()));
+        assert_eq!(match_pattern("Example.TEST", "example.test"), Ok(()));
+        assert_eq!(match_pattern("example.test.", "example.test"), Ok(()));
+        assert_eq!(
+            match_pattern("example.test", "www.example.test"),
+            Err("different name".to_owned())
+        );
+        assert_eq!(
+            match_pattern("localhost", "otherhost"),
+            Err("different name".to_owned())
+        );
+    }
+
+    #[test]
+    fn wildcards() {
+        assert_eq!(match_pattern("*.example.test", "www.example.test"), Ok(()));
+        assert_eq!(match_pattern("*.example.test", "WWW.Example.Test"), Ok(()));
+
+        for (pattern, hostname, reason) in [
+            (
+                "*.example.test",
+                "a.b.example.test",
+                "wildcard matches only a single label",
+            ),
+            ("*.example.test", "example.test", "different name"),
+            ("*.example.test", "www.other.test", "different name"),
+            (
+                "*.example.test",
+                ".example.test",
+                "wildcard does not match an empty label",
+            ),
+            (
+                "*.example.test",
+                "xn--bcher-kva.example.test",
+                "wildcard does not match internationalized labels",
+            ),
+            (
+                "w*.example.test",
+                "www.example.test",
+                "partial-label wildcards are not supported",
+            ),
+            (
+                "*.*.example.test",
+                "a.b.example.test",
+                "wildcard is only allowed in the left-most label",
+            ),
+            (
+                "www.*.test",
+                "www.example.test",
+                "wildcard is only allowed in the left-most label",
+            ),
+            (
+                "*.test",
+                "example.test",
+                "wildcard must be followed by at least two labels",
+            ),
+            ("*.example.test", "localhost", "hostname has a single label"),
+        ] {
+            assert_eq!(
+                match_pattern(pattern, hostname),
+                Err(reason.to_owned()),
+                "{pattern} vs {hostname}"
+            );
+        }
+    }
+
+    #[test]
+    fn dns_sans() {
+        let san = "hostname/san.pem";
+
+        assert_matched(check_fixture(san, "example.test"), "DNS:example.test");
+        assert_matched(check_fixture(san, "example.test."), "DNS:example.test");
+        assert_matched(
+            check_fixture(san, "mixed.example.test"),
+            "DNS:Mixed.Example.Test.",
+        );
+        assert_matched(
+            check_fixture(san, "www.wild.example.test"),
+            "DNS:*.wild.example.test",
+        );
+
+        // a CN is not consulted when SAN entries are present
+        let reasons = not_matched(check_fixture(san, "cn.example.test"));
+        assert_eq!(reasons.len(), 5);
+        assert!(reasons.iter().all(|(entry, _)| !entry.starts_with("CN:")));
+    }
+
+    #[test]
+    fn ip_sans() {
+        let san = "hostname/san.pem";
+
+        assert_matched(check_fixture(san, "192.0.2.1"), "IP:192.0.2.1");
+        assert_matched(check_fixture(san, "2001:db8::1"), "IP:2001:db8::1");
+        assert_matched(check_fixture(san, "[2001:0db8::0001]"), "IP:2001:db8::1");
+
+        let reasons = not_matched(check_fixture(san, "192.0.2.2"));
+        assert!(reasons.contains(&(
+            "DNS:example.test".to_owned(),
+            "IP addresses never match DNS names".to_owned()
+        )));
+        assert!(reasons.contains(&("IP:192.0.2.1".to_owned(), "different address".to_owned())));
+
+        let reasons = not_matched(check_fixture(san, "other.test"));
+        assert!(reasons.contains(&(
+            "IP:192.0.2.1".to_owned(),
+            "hostname is not an IP address".to_owned()
+        )));
+    }
+
+    #[test]
+    fn cn_fallback() {
+        let cn_only = "hostname/cn-only.pem";
+
+        assert_matched(
+            check_fixture(cn_only, "CN.example.test"),
+            "CN:cn.example.test (deprecated subject CN fallback)",
+        );
+
+        assert_eq!(
+            not_matched(check_fixture(cn_only, "other.example.test")),
+            [("CN:cn.example.test".to_owned(), "different name".to_owned())]
+        );
+
+        assert_eq!(
+            not_matched(check_fixture(cn_only, "192.0.2.1")),
+            [(
+                "CN:cn.example.test".to_owned(),
+                "IP addresses never match subject CN".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn no_names() {
+        assert_eq!(
+            not_matched(check_fixture("hostname/no-names.pem", "example.test")),
+            [(
+                "<none>".to_owned(),
+                "certificate has no SAN entries or subject CN".to_owned()
+            )]
+        );
+    }
+}
@@ -0,0 +1,502 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    io::{Read as _, Write as _},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use eyre::{WrapErr as _, bail, eyre};
+
+use crate::{
+    net::{self, Deadline},
+    proxy::{self, Proxy},
+};
+
+/// Largest response accepted, headers included; large enough for the biggest public CRLs.
+const MAX_RESPONSE_LEN: u64 = 64 * 1_024 * 1_024;
+
+/// Minimal HTTP client used to fetch revocation information and issuer certificates.
+///
+/// Exists as a trait so that alternative transports (or canned responses) can be substituted.
+pub(crate) trait Fetcher {
+    /// Fetches `url` with a GET request, returning the response body.
+    fn get(&self, url: &str) -> eyre::Result<Vec<u8>>;
+
+    /// Sends `body` to `url` with a POST request, returning the response body.
+    fn post(&self, url: &str, content_type: &str, body: &[u8]) -> eyre::Result<Vec<u8>>;
+}
+
+/// Plain HTTP/1.1 fetcher, honoring the configured timeouts and proxy.
+#[derive(Debug)]
+pub(crate) struct HttpFetcher {
+    timeouts: net::Timeouts,
+
+    /// Proxy to use for all requests. When absent, the proxy is taken from the environment for
+    /// each request's host.
+    proxy: Option<Proxy>,
+}
+
+impl HttpFetcher {
+    pub(crate) fn new(timeouts: net::Timeouts, proxy: Option<Proxy>) -> Self {
+        Self { timeouts, proxy }
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<(&str, &[u8])>,
+    ) -> eyre::Result<Response> {
+        let (host, port, path) = parse_url(url)?;
+        let addr = authority(&host, port);
+
+        let proxy = match &self.proxy {
+            Some(proxy) => Some(proxy.clone()),
+            None => Proxy::from_env(&host)?,
+        };
+
+        let mut sock = match &proxy {
+            Some(proxy) => proxy.connect(&addr, &self.timeouts)?,
+            None => net::connect(&addr, &self.timeouts)?,
+        };
+
+        let deadline = Deadline::start("HTTP request", self.timeouts.io);
+        deadline.arm(&sock)?;
+
+        let host_header = match port {
+            80 if host.contains(':') => format!("[{host}]"),
+            80 => host.clone(),
+            _ => addr.clone(),
+        };
+
+        let mut req = format!(
+            "{method} {path} HTTP/1.1\r\nHost: {host_header}\r\nConnection: close\r\n\
+            User-Agent: inspect-cert-chain/{}\r\nAccept-Encoding: identity\r\n",
+            env!("CARGO_PKG_VERSION"),
+        )
+        .into_bytes();
+
+        if let Some((content_type, body)) = body {
+            req.extend_from_slice(
+                format!(
+                    "Content-Type: {content_type}\r\nContent-Length: {}\r\n",
+                    body.len()
+                )
+                .as_bytes(),
+            );
+            req.extend_from_slice(b"\r\n");
+            req.extend_from_slice(body);
+        } else {
+            req.extend_from_slice(b"\r\n");
+        }
+
+        tracing::debug!(%url, "sending HTTP {method} request");
+
+        let mut res = Vec::new();
+
+        sock.write_all(&req)
+            .and_then(|()| (&mut sock).take(MAX_RESPONSE_LEN + 1).read_to_end(&mut res))
+            .map_err(eyre::Report::new)
+            .map_err(|err| deadline.map_err(err))?;
+
+        if res.len() as u64 > MAX_RESPONSE_LEN {
+            bail!(
+                "response too large (over {} MiB)",
+                MAX_RESPONSE_LEN / 1_024 / 1_024
+            );
+        }
+
+        parse_response(&res)
+    }
+}
+
+impl Fetcher for HttpFetcher {
+    fn get(&self, url: &str) -> eyre::Result<Vec<u8>> {
+        const MAX_REDIRECTS: usize = 5;
+
+        let mut url = url.to_owned();
+
+        for _ in 0..=MAX_REDIRECTS {
+            let res = self
+                .request("GET", &url, None)
+                .wrap_err_with(|| format!("failed to fetch {url}"))?;
+
+            match (res.status, res.location) {
+                (300..=399, Some(location)) => {
+                    tracing::debug!(%url, %location, "following redirect");
+
+                    url = if location.starts_with('/') {
+                        let (host, port, _) = parse_url(&url)?;
+                        format!("http://{}{location}", authority(&host, port))
+                    } else {
+                        location
+                    };
+                }
+                (200..=299, _) => return Ok(res.body),
+                (status, _) => bail!("failed to fetch {url}: HTTP status {status}"),
+            }
+        }
+
+        Err(eyre!("failed to fetch {url}: too many redirects"))
+    }
+
+    fn post(&self, url: &str, content_type: &str, body: &[u8]) -> eyre::Result<Vec<u8>> {
+        let res = self
+            .request("POST", url, Some((content_type, body)))
+            .wrap_err_with(|| format!("failed to post to {url}"))?;
+
+        match res.status {
+            200..=299 => Ok(res.body),
+            status => bail!("failed to post to {url}: HTTP status {status}"),
+        }
+    }
+}
+
+/// Request URL and body.
+type CacheKey = (String, Vec<u8>);
+
+/// Fetcher wrapper which remembers responses (and errors) for the rest of the run.
+///
+/// When a cache directory is set, successful GET responses are also stored there and served from
+/// it on later runs, allowing offline use with a pre-populated directory. Stored responses never
+/// expire, so a directory is only suitable for responses which do not go stale (e.g., issuer
+/// certificates).
+pub(crate) struct CachingFetcher<F> {
+    inner: F,
+    cache: RefCell<HashMap<CacheKey, Result<Vec<u8>, String>>>,
+    dir: Option<Utf8PathBuf>,
+}
+
+impl<F: Fetcher> CachingFetcher<F> {
+    pub(crate) fn new(inner: F) -> Self {
+        Self {
+            inner,
+            cache: RefCell::default(),
+            dir: None,
+        }
+    }
+
+    /// Persists GET responses in `dir`.
+    pub(crate) fn with_dir(mut self, dir: Utf8PathBuf) -> Self {
+        self.dir = Some(dir);
+        self
+    }
+
+    fn cached(
+        &self,
+        key: CacheKey,
+        fetch: impl FnOnce() -> eyre::Result<Vec<u8>>,
+    ) -> eyre::Result<Vec<u8>> {
+        if let Some(res) = self.cache.borrow().get(&key) {
+            tracing::debug!(url = %key.0, "using cached response");
+            return res.clone().map_err(|err| eyre!(err));
+        }
+
+        let res = fetch().map_err(|err| format!("{err:#}"));
+        self.cache.borrow_mut().insert(key, res.clone());
+
+        res.map_err(|err| eyre!(err))
+    }
+}
+
+impl<F: Fetcher> Fetcher for CachingFetcher<F> {
+    fn get(&self, url: &str) -> eyre::Result<Vec<u8>> {
+        self.cached((url.to_owned(), Vec::new()), || {
+            let Some(dir) = &self.dir else {
+                return self.inner.get(url);
+            };
+
+            let path = cache_path(dir, url);
+
+            if let Ok(data) = fs::read(&path) {
+                tracing::debug!(%url, %path, "using response from cache directory");
+                return Ok(data);
+            }
+
+            let data = self.inner.get(url)?;
+
+            if let Err(err) = fs::create_dir_all(dir).and_then(|()| fs::write(&path, &data)) {
+                tracing::warn!(%path, "failed to write to cache directory: {err}");
+            }
+
+            Ok(data)
+        })
+    }
+
+    fn post(&self, url: &str, content_type: &str, body: &[u8]) -> eyre::Result<Vec<u8>> {
+        self.cached((url.to_owned(), body.to_vec()), || {
+            self.inner.post(url, content_type, body)
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Response {
+    status: u16,
+    location: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Returns path of the file caching `url` in `dir`, named after the URL with unsafe characters
+/// replaced (e.g., `http___example.com_ca.der`).
+fn cache_path(dir: &Utf8Path, url: &str) -> Utf8PathBuf {
+    let name = url
+        .chars()
+        .map(|ch| match ch {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' => ch,
+            _ => '_',
+        })
+        .collect::<String>();
+
+    dir.join(name)
+}
+
+fn authority(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    }
+}
+
+/// Splits an `http://` URL into host, port and path.
+fn parse_url(url: &str) -> eyre::Result<(String, u16, String)> {
+    let rest = url
+        .get(..7)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("http://"))
+        .map(|_| &url[7..])
+        .ok_or_else(|| eyre!("unsupported URL (only http:// is supported): {url}"))?;
+
+    let (authority, path) = match rest.find(['/', '?']) {
+        Some(idx) if rest[idx..].starts_with('/') => (&rest[..idx], rest[idx..].to_owned()),
+        Some(idx) => (&rest[..idx], format!("/{}", &rest[idx..])),
+        None => (rest, "/".to_owned()),
+    };
+
+    let (host, port) = match proxy::split_host_port(authority) {
+        Some((host, port)) => (
+            host,
+            port.parse()
+                .map_err(|err| eyre!("invalid port in URL {url}: {err}"))?,
+        ),
+        None => (authority, 80),
+    };
+
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    if host.is_empty() {
+        bail!("URL has no host: {url}");
+    }
+
+    Ok((host.to_owned(), port, path))
+}
+
+fn parse_response(res: &[u8]) -> eyre::Result<Response> {
+    let header_end = res
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| eyre!("malformed HTTP response: no end of headers"))?;
+
+    let head = String::from_utf8_lossy(&res[..header_end]);
+    let mut lines = head.split("\r\n");
+
+    let status_line = lines.next().unwrap_or_default();
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| eyre!("malformed HTTP status line: {status_line}"))?;
+
+    let mut location = None;
+    let mut chunked = false;
+    let mut content_len = None;
+
+    for line in lines {
+        let Some((name, val)) = line.split_once(':') else {
+            continue;
+        };
+
+        let val = val.trim();
+
+        match name.trim().to_ascii_lowercase().as_str() {
+            "location" => location = Some(val.to_owned()),
+            "transfer-encoding" => chunked = val.eq_ignore_ascii_case("chunked"),
+            "content-length" => content_len = val.parse::<usize>().ok(),
+            _ => {}
+        }
+    }
+
+    let body = &res[header_end + 4..];
+
+    let body = if chunked {
+        decode_chunked(body)?
+    } else {
+        match content_len {
+            Some(len) if len > body.len() => bail!("HTTP response body truncated"),
+            Some(len) => body[..len].to_vec(),
+            None => body.to_vec(),
+        }
+    };
+
+    Ok(Response {
+        status,
+        location,
+        body,
+    })
+}
+
+fn decode_chunked(mut body: &[u8]) -> eyre::Result<Vec<u8>> {
+    let mut out = Vec::new();
+
+    loop {
+        let line_end = body
+            .windows(2)
+            .position(|window| window == b"\r\n")
+            .ok_or_else(|| eyre!("malformed chunked HTTP body"))?;
+
+        let size = String::from_utf8_lossy(&body[..line_end]);
+        let size = size.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16)
+            .map_err(|_| eyre!("malformed chunk size in HTTP body: {size}"))?;
+
+        body = &body[line_end + 2..];
+
+        if size == 0 {
+            return Ok(out);
+        }
+
+        if body.len() < size {
+            bail!("HTTP response body truncated");
+        }
+
+        out.extend_from_slice(&body[..size]);
+        body = body[size..].strip_prefix(b"\r\n").unwrap_or(&body[size..]);
+    }
+}
+
+/// Fetcher serving canned responses by URL, recording each request made.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub(crate) struct StubFetcher {
+    responses: HashMap<String, Vec<u8>>,
+    pub(crate) requests: RefCell<Vec<String>>,
+}
+
+#[cfg(test)]
+impl StubFetcher {
+    /// Serves `data` for GET and POST requests to `url`.
+    pub(crate) fn with(mut self, url: &str, data: Vec<u8>) -> Self {
+        self.responses.insert(url.to_owned(), data);
+        self
+    }
+}
+
+#[cfg(test)]
+impl Fetcher for StubFetcher {
+    fn get(&self, url: &str) -> eyre::Result<Vec<u8>> {
+        self.requests.borrow_mut().push(url.to_owned());
+
+        self.responses
+            .get(url)
+            .cloned()
+            .ok_or_else(|| eyre!("failed to fetch {url}: HTTP status 404"))
+    }
+
+    fn post(&self, url: &str, _content_type: &str, _body: &[u8]) -> eyre::Result<Vec<u8>> {
+        self.get(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::BufRead as _, net::TcpListener, thread, time::Duration};
+
+    use super::*;
+
+    /// Fetches `path` from a local server which answers each request with the next response.
+    fn get_with(path: &str, responses: Vec<Vec<u8>>) -> eyre::Result<Vec<u8>> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = thread::spawn(move || {
+            for res in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut rdr = std::io::BufReader::new(stream.try_clone().unwrap());
+
+                let mut line = String::new();
+                while line != "\r\n" {
+                    line.clear();
+                    rdr.read_line(&mut line).unwrap();
+                }
+
+                // the client may stop reading an oversized response
+                let _ = stream.write_all(&res);
+            }
+        });
+
+        let timeouts = net::Timeouts::new(Duration::from_secs(5), Duration::from_secs(5));
+        let res = HttpFetcher::new(timeouts, None).get(&format!("http://{addr}{path}"));
+
+        server.join().unwrap();
+
+        res
+    }
+
+    #[test]
+    fn get_follows_redirects() {
+        let body = get_with(
+            "/old",
+            vec![
+                b"HTTP/1.1 301 Moved\r\nLocation: /new\r\n\r\n".to_vec(),
+                b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n"
+                    .to_vec(),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(body, b"abc");
+    }
+
+    #[test]
+    fn get_rejects_error_status() {
+        let err = get_with("/", vec![b"HTTP/1.1 404 Not Found\r\n\r\n".to_vec()]).unwrap_err();
+
+        assert!(format!("{err:#}").contains("HTTP status 404"));
+    }
+
+    #[test]
+    fn get_limits_response_size() {
+        let mut res = b"HTTP/1.1 200 OK\r\n\r\n".to_vec();
+        res.resize(MAX_RESPONSE_LEN as usize + 1, b'x');
+
+        let err = get_with("/", vec![res]).unwrap_err();
+
+        assert!(format!("{err:#}").contains("response too large"));
+    }
+
+    #[test]
+    fn parse_response_content_length() {
+        let res = parse_response(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nabc").unwrap();
+        assert_eq!(res.status, 200);
+        assert_eq!(res.body, b"ab");
+
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nabc").is_err());
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\n").is_err());
+    }
+
+    #[test]
+    fn split_url() {
+        assert_eq!(
+            parse_url("http://crl.test/ca.crl").unwrap(),
+            ("crl.test".to_owned(), 80, "/ca.crl".to_owned())
+        );
+        assert_eq!(
+            parse_url("HTTP://[::1]:8080?q").unwrap(),
+            ("::1".to_owned(), 8080, "/?q".to_owned())
+        );
+        assert!(parse_url("https://crl.test/").is_err());
+        assert!(parse_url("http:///ca.crl").is_err());
+    }
+}
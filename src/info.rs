@@ -1,65 +1,64 @@
 use std::io;
 
-use byteorder::{BigEndian, ByteOrder as _};
-use const_oid::{
-    ObjectIdentifier,
-    db::rfc5912::{ID_EC_PUBLIC_KEY, RSA_ENCRYPTION},
+use crate::{
+    cert::Cert,
+    model::{self, Exponent, KeyParams},
+    signature, util,
 };
 use crossterm::style::Stylize as _;
 use itertools::Itertools as _;
-use pkcs1::der::Decode as _;
-use x509_cert::Certificate;
-
-use crate::{ext, util};
 
 pub(crate) fn write_cert_info(
-    cert: &Certificate,
+    cert: &Cert,
+    signature: &signature::Status,
     mut wrt: impl io::Write,
     stylize: bool,
 ) -> io::Result<()> {
-    let tbs = cert.tbs_certificate();
+    let info = model::CertInfo::new(cert, signature);
 
-    let tbs_cert = tbs;
     writeln!(
         wrt,
         "Subject: {}",
-        tbs_cert.subject().to_string().yellow().bold()
+        info.subject.rfc4514.as_str().yellow().bold()
     )?;
 
-    writeln!(wrt, "Issuer: {}", tbs.issuer().to_string().blue().bold())?;
+    writeln!(
+        wrt,
+        "Issuer: {}",
+        info.issuer.rfc4514.as_str().blue().bold()
+    )?;
 
-    writeln!(wrt, "Version: {:?}", tbs.version())?;
+    writeln!(wrt, "Version: V{}", info.version)?;
     writeln!(
         wrt,
         "Serial Number:\n  {}",
-        util::openssl_hex(tbs.serial_number().as_bytes(), 20).join("\n  ")
+        util::openssl_hex(&info.serial_number.0, 20).join("\n  ")
     )?;
 
     writeln!(
         wrt,
         "Signature Algorithm: {}",
-        util::oid_desc_or_raw(&cert.signature_algorithm().oid)
+        info.signature_algorithm.describe()
     )?;
-    util::assert_null_params(cert.signature_algorithm());
 
     // TODO: doesn't work ?
     writeln!(
         wrt,
         "Issuer Serial Number:\n  {}",
-        tbs.issuer_unique_id()
+        info.issuer_unique_id
             .as_ref()
-            .map(|serial| util::openssl_hex(serial.as_bytes().unwrap(), 20).join("\n  "))
+            .map(|serial| util::openssl_hex(&serial.0, 20).join("\n  "))
             .unwrap_or_else(|| "<unknown>".to_owned())
     )?;
 
-    let (nbf, nbf_in_future) = util::duration_since_now_fmt(tbs.validity().not_before);
-    let (exp, exp_in_future) = util::duration_since_now_fmt(tbs.validity().not_after);
+    let nbf = &info.validity.not_before;
+    let exp = &info.validity.not_after;
 
     writeln!(
         wrt,
         "{}",
         if stylize {
-            if !nbf_in_future && exp_in_future {
+            if !nbf.in_future && exp.in_future {
                 "Validity:".green().bold()
             } else {
                 "Validity:".red()
@@ -72,108 +71,138 @@ pub(crate) fn write_cert_info(
     writeln!(
         wrt,
         "  Not Before: {} ({})",
-        tbs.validity().not_before,
+        nbf.time,
         if stylize {
-            if nbf_in_future {
-                nbf.red().bold()
+            if nbf.in_future {
+                nbf.relative().red().bold()
             } else {
-                nbf.green().bold()
+                nbf.relative().green().bold()
             }
         } else {
-            nbf.stylize()
+            nbf.relative().stylize()
         },
     )?;
 
     writeln!(
         wrt,
         "  Not After: {} ({})",
-        tbs.validity().not_after,
+        exp.time,
         if stylize {
-            if exp_in_future {
-                exp.green().bold()
+            if exp.in_future {
+                exp.relative().green().bold()
             } else {
-                exp.red().bold()
+                exp.relative().red().bold()
             }
         } else {
-            exp.stylize()
+            exp.relative().stylize()
         },
     )?;
 
-    // if let Some(name_constraints) = anchor.name_constraints {
-    //     writeln!(wrt, "Name Constraints: {:?}", name_constraints);
-    // }
-
     writeln!(wrt, "Subject Public Key Info:")?;
 
-    let spki = tbs_cert.subject_public_key_info();
-    let alg = &spki.algorithm;
+    let algorithm = info.public_key.algorithm.describe();
 
-    match () {
-        _ if alg.oid == ID_EC_PUBLIC_KEY => {
-            let ec_subtype = alg
-                .parameters
-                .as_ref()
-                .unwrap()
-                .decode_as::<ObjectIdentifier>()
-                .unwrap();
+    match &info.public_key.params {
+        KeyParams::Ec { curve, point } => {
+            let public_key = util::openssl_hex(&point.0, 15).join("\n    ");
 
-            let ec_type = util::oid_desc_or_raw(&alg.oid);
-            let ec_subtype = util::oid_desc_or_raw(&ec_subtype);
-
-            let public_key_bytes = spki.subject_public_key.as_bytes().unwrap();
-            let public_key = util::openssl_hex(public_key_bytes, 15).join("\n    ");
-
-            writeln!(wrt, "  Algorithm: {ec_type} ({ec_subtype})")?;
+            writeln!(wrt, "  Algorithm: {algorithm} ({})", curve.describe())?;
             writeln!(wrt, "  Public Key:\n    {public_key}")?;
         }
 
-        _ if alg.oid == RSA_ENCRYPTION => {
-            let algorithm = util::oid_desc_or_raw(&alg.oid);
+        KeyParams::Rsa {
+            bits,
+            exponent,
+            modulus,
+        } => {
             writeln!(wrt, "  Algorithm: {algorithm}")?;
-
-            let rsa_details =
-                pkcs1::RsaPublicKey::from_der(spki.subject_public_key.as_bytes().unwrap()).unwrap();
-
             writeln!(wrt, "  RSA:")?;
-
-            let exp_bytes = rsa_details.public_exponent.as_bytes();
-            let exp = BigEndian::read_uint(exp_bytes, exp_bytes.len());
-            writeln!(wrt, "    Exponent: {exp} (0x{exp:0x})")?;
-            let mod_bytes = rsa_details.modulus.as_bytes();
+            match exponent {
+                Exponent::Small(exponent) => {
+                    writeln!(wrt, "    Exponent: {exponent} (0x{exponent:0x})")?;
+                }
+                Exponent::Large(exponent) => writeln!(
+                    wrt,
+                    "    Exponent:\n      {}",
+                    util::openssl_hex(&exponent.0, 32).join("\n      ")
+                )?,
+            }
             writeln!(
                 wrt,
-                "    Modulus({} bit):\n      {}",
-                mod_bytes.len() * 8,
-                util::openssl_hex(mod_bytes, 32).join("\n      ")
+                "    Modulus({bits} bit):\n      {}",
+                util::openssl_hex(&modulus.0, 32).join("\n      ")
             )?;
         }
 
-        _ => {
-            let alg = util::oid_desc_or_raw(&alg.oid);
-            writeln!(wrt, "  Algorithm: {alg}")?;
+        KeyParams::Other { .. } => {
+            writeln!(wrt, "  Algorithm: {algorithm}")?;
         }
     }
 
-    if let Some(extensions) = tbs.extensions() {
+    if !info.extensions.is_empty() {
         writeln!(wrt, "Extensions:")?;
 
-        for ext in extensions {
+        for ext in &info.extensions {
             writeln!(
                 wrt,
                 "  ID: {}{}",
-                util::oid_desc_or_raw(&ext.extn_id),
+                ext.oid.describe(),
                 if ext.critical { " (critical)" } else { "" }
             )?;
-            writeln!(wrt, "  Extension value:\n    {}", ext::interpret_val(ext))?;
+            writeln!(wrt, "  Extension value:\n    {}", ext.value.fmt_text())?;
             writeln!(wrt)?;
         }
     }
 
+    writeln!(wrt, "Fingerprints:")?;
+    writeln!(
+        wrt,
+        "  SHA-1:\n    {}",
+        util::openssl_hex(&info.fingerprints.sha1.0, 20).join("\n    ")
+    )?;
+    writeln!(
+        wrt,
+        "  SHA-256:\n    {}",
+        util::openssl_hex(&info.fingerprints.sha256.0, 20).join("\n    ")
+    )?;
+    writeln!(
+        wrt,
+        "  SPKI SHA-256:\n    {}",
+        util::openssl_hex(&info.fingerprints.spki_sha256.0, 20).join("\n    ")
+    )?;
+    writeln!(
+        wrt,
+        "  SPKI SHA-256 (base64):\n    {}",
+        info.fingerprints.spki_sha256_base64
+    )?;
+    writeln!(
+        wrt,
+        "  Subject Hash (OpenSSL):\n    {}",
+        info.fingerprints.subject_hash
+    )?;
+
     writeln!(wrt, "Signature:")?;
     writeln!(
         wrt,
         "  {}",
-        util::openssl_hex(cert.signature().as_bytes().unwrap(), 20).join("\n  ")
+        util::openssl_hex(&info.signature.value.0, 20).join("\n  ")
+    )?;
+
+    let signature_status = signature.describe();
+    writeln!(
+        wrt,
+        "  Verification: {}",
+        if stylize {
+            if signature.is_valid() {
+                signature_status.green().bold()
+            } else if signature.is_invalid() {
+                signature_status.red().bold()
+            } else {
+                signature_status.yellow()
+            }
+        } else {
+            signature_status.stylize()
+        }
     )?;
 
     Ok(())
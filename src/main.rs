@@ -1,20 +1,42 @@
 use std::{
     fs,
     io::{self, Read as _, Write as _},
+    process::ExitCode,
+    sync::Arc,
 };
 
 use clap::{CommandFactory as _, Parser};
-use der::{Decode as _, Encode as _};
+use der::Decode as _;
 use eyre::{WrapErr as _, eyre};
+use itertools::Itertools as _;
 use pem_rfc7468::{LineEnding, PemLabel as _};
 use x509_cert::Certificate;
 
+mod aia;
+mod asn1;
+mod cert;
+mod chain;
+mod client_auth;
+mod crl;
+mod ct;
 mod ext;
 mod fetch;
+mod hostname;
+mod http;
 mod info;
 mod logging;
+mod model;
+mod net;
+mod ocsp;
+mod openssl;
+mod proxy;
+mod revocation;
+mod sct;
+mod signature;
+mod starttls;
 mod tui;
 mod util;
+mod verify;
 
 cfg_if::cfg_if! {
     if #[cfg(windows)] {
@@ -25,20 +47,105 @@ cfg_if::cfg_if! {
 }
 
 #[derive(Debug, Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Download certificate chain from remote host.
     #[clap(long, conflicts_with = "file")]
     host: Option<String>,
 
-    /// Port to use with --host.
-    #[clap(long, conflicts_with = "file", default_value_t = 443)]
-    port: u16,
+    /// Port to use with --host. Defaults to 443, or the standard port of the --starttls protocol.
+    #[clap(long, conflicts_with = "file")]
+    port: Option<u16>,
+
+    /// Upgrade a plaintext connection to TLS using the given protocol before fetching the chain.
+    #[clap(long, conflicts_with = "file", value_enum)]
+    starttls: Option<starttls::Protocol>,
+
+    /// Connect to this address (<addr>:<port>) instead of --host, e.g. to inspect a specific node.
+    #[clap(long, conflicts_with = "file", value_name = "ADDR:PORT")]
+    connect: Option<String>,
+
+    /// Connect to <addr> when fetching from <host>:<port>. May be repeated.
+    #[clap(long, conflicts_with = "file", value_name = "HOST:PORT:ADDR")]
+    resolve: Vec<fetch::Resolve>,
+
+    /// Time limit, in seconds, for each of DNS lookup and TCP connect, with --host and for HTTP
+    /// requests. 0 disables.
+    #[clap(long, value_name = "SECS", default_value = "10", value_parser = net::parse_timeout)]
+    connect_timeout: std::time::Duration,
+
+    /// Time limit, in seconds, for each of the TLS handshake and read phases with --host, and for
+    /// each HTTP request. 0 disables.
+    #[clap(long, value_name = "SECS", default_value = "30", value_parser = net::parse_timeout)]
+    timeout: std::time::Duration,
+
+    /// Proxy URL (http:// or socks5://) to connect through, also used for OCSP, CRL and CA Issuers
+    /// requests. Defaults to HTTPS_PROXY or ALL_PROXY for each host not matching NO_PROXY.
+    #[clap(long, value_name = "URL")]
+    proxy: Option<proxy::Proxy>,
+
+    /// Server name to send in the SNI extension. Defaults to --host.
+    #[clap(long, conflicts_with_all = ["file", "no_sni"])]
+    sni: Option<String>,
+
+    /// Do not send the SNI extension.
+    #[clap(long, conflicts_with = "file")]
+    no_sni: bool,
+
+    /// TLS protocol version(s) to offer with --host.
+    #[clap(long, conflicts_with = "file", value_enum, default_value_t = fetch::TlsVersion::Auto)]
+    tls_version: fetch::TlsVersion,
+
+    /// ALPN protocol to offer with --host. May be repeated.
+    #[clap(long, conflicts_with = "file")]
+    alpn: Vec<String>,
+
+    /// Send an HTTP request to --host after the TLS handshake completes.
+    #[clap(long, conflicts_with_all = ["file", "starttls"])]
+    http_probe: bool,
+
+    /// Client certificate (PEM, or PKCS#12 without --client-key) to present if requested by --host.
+    #[clap(long, conflicts_with = "file")]
+    client_cert: Option<camino::Utf8PathBuf>,
+
+    /// Private key (PEM) for --client-cert. Defaults to reading it from --client-cert.
+    #[clap(long, requires = "client_cert")]
+    client_key: Option<camino::Utf8PathBuf>,
+
+    /// Password for a PKCS#12 --client-cert. Visible to other local users; prefer
+    /// --client-cert-password-file.
+    #[clap(long, requires = "client_cert", value_name = "PASSWORD")]
+    client_cert_password: Option<String>,
+
+    /// File containing the password for a PKCS#12 --client-cert.
+    #[clap(
+        long,
+        requires = "client_cert",
+        conflicts_with = "client_cert_password",
+        value_name = "FILE"
+    )]
+    client_cert_password_file: Option<camino::Utf8PathBuf>,
 
     /// When provided, writes downloaded chain to file in PEM format.
     #[clap(long, conflicts_with = "file")]
     dump: Option<camino::Utf8PathBuf>,
 
+    /// Writes the chain to file in PEM format, ordered leaf-to-root without duplicates or unrelated
+    /// certificates.
+    #[clap(long, value_name = "FILE")]
+    fix_chain: Option<camino::Utf8PathBuf>,
+
+    /// Whether --fix-chain keeps, drops or appends the self-signed root.
+    #[clap(long, requires = "fix_chain", value_enum, default_value_t = chain::RootPolicy::Keep)]
+    fix_root: chain::RootPolicy,
+
+    /// Directory of PEM intermediates (and roots) used by --fix-chain to fill gaps in the chain.
+    #[clap(long, requires = "fix_chain", value_name = "DIR")]
+    intermediates_dir: Option<camino::Utf8PathBuf>,
+
     /// Inspect a local certificate chain in PEM format.
     #[clap(long, conflicts_with = "host")]
     file: Option<camino::Utf8PathBuf>,
@@ -47,26 +154,136 @@ struct Args {
     #[arg(short, long)]
     interactive: bool,
 
-    #[arg(short, long, action = clap::ArgAction::Count)]
+    /// Format of the certificate details written to stdout. Reports from --verify, --crl,
+    /// --check-revocation and --ct-log-list are only available as text.
+    #[arg(long, visible_alias = "format", value_enum, default_value_t = model::OutputFormat::Text)]
+    output: model::OutputFormat,
+
+    /// Hostname to check the leaf certificate against. Defaults to --sni or --host.
+    #[arg(long)]
+    hostname: Option<String>,
+
+    /// Verify chain against trust anchors and report result of each link. Exits with status 1 if
+    /// the chain is not trusted.
+    #[arg(long, conflicts_with = "interactive")]
+    verify: bool,
+
+    /// PEM bundle of trust anchors to use with --verify instead of the platform store.
+    #[arg(long, requires = "verify")]
+    ca_file: Option<camino::Utf8PathBuf>,
+
+    /// Directory of PEM trust anchors to use with --verify instead of the platform store.
+    #[arg(long, requires = "verify")]
+    ca_dir: Option<camino::Utf8PathBuf>,
+
+    /// CRL file (DER or PEM) to check chain certificates against. May be repeated.
+    #[arg(long, conflicts_with = "interactive", value_name = "FILE")]
+    crl: Vec<camino::Utf8PathBuf>,
+
+    /// Download issuers missing from the end of the chain using AIA CA Issuers URLs.
+    #[arg(long, conflicts_with = "interactive")]
+    fetch_issuers: bool,
+
+    /// Directory in which CA Issuers downloads are cached; when pre-populated, no requests are made
+    /// for them. OCSP responses and CRLs are always fetched afresh.
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<camino::Utf8PathBuf>,
+
+    /// Query OCSP responders and CRL distribution points named in the chain over HTTP.
+    #[arg(long, conflicts_with = "interactive")]
+    check_revocation: bool,
+
+    /// CT log list JSON (Chrome or Apple format) used to identify logs and verify SCT signatures.
+    #[arg(long, conflicts_with = "interactive", value_name = "FILE")]
+    ct_log_list: Option<camino::Utf8PathBuf>,
+
+    /// Minimum number of distinct usable, qualified or read-only logs with a valid SCT required by the
+    /// CT policy.
+    #[arg(long, requires = "ct_log_list", default_value_t = 2)]
+    ct_min_scts: usize,
+
+    /// Minimum number of distinct log operators among valid SCTs required by the CT policy.
+    #[arg(long, requires = "ct_log_list", default_value_t = 2)]
+    ct_min_operators: usize,
+
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
     verbose: u8,
 }
 
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Dump the ASN.1 structure of DER or PEM input with offsets and decoded values.
+    Asn1(asn1::Args),
+}
+
 // let anchor = &TLS_SERVER_ROOTS.0[3]; // seems to have wrong modulus ?!?
 
-fn main() -> eyre::Result<()> {
+fn main() -> eyre::Result<ExitCode> {
     color_eyre::install()?;
 
     let args = Args::parse();
 
     logging::init(args.verbose)?;
 
+    if let Some(Command::Asn1(asn1_args)) = &args.command {
+        asn1::run(asn1_args, io::stdout())?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
     rustls::crypto::aws_lc_rs::default_provider()
         .install_default()
         .unwrap();
 
-    let certs = if let Some(host) = &args.host {
+    let mut connection = None;
+    let mut ocsp_response = None;
+    let mut scts = None;
+
+    let mut certs = if let Some(host) = &args.host {
         tracing::info!(%host, "fetching certificate chain from remote host");
-        fetch::cert_chain(host, args.port)?
+
+        let opts = fetch::Options {
+            connect: args.connect.clone(),
+            resolve: args.resolve.clone(),
+            timeouts: net::Timeouts::new(args.connect_timeout, args.timeout),
+            proxy: match &args.proxy {
+                Some(proxy) => Some(proxy.clone()),
+                None => proxy::Proxy::from_env(host)?,
+            },
+            sni: args.sni.clone(),
+            enable_sni: !args.no_sni,
+            tls_version: args.tls_version,
+            alpn_protocols: args.alpn.clone(),
+            starttls: args.starttls,
+            http_probe: args.http_probe,
+            client_identity: args
+                .client_cert
+                .as_deref()
+                .map(|cert_path| {
+                    let password = match &args.client_cert_password_file {
+                        Some(path) => Some(client_auth::read_password(path)?),
+                        None => args.client_cert_password.clone(),
+                    };
+
+                    client_auth::ClientIdentity::load(
+                        cert_path,
+                        args.client_key.as_deref(),
+                        password.as_deref(),
+                    )
+                })
+                .transpose()?
+                .map(Arc::new),
+        };
+
+        let port = args
+            .port
+            .or_else(|| args.starttls.map(starttls::Protocol::default_port))
+            .unwrap_or(443);
+
+        let chain = fetch::cert_chain(host, port, &opts)?;
+        connection = Some(chain.connection);
+        ocsp_response = Some(chain.ocsp_response);
+        scts = Some(chain.scts);
+        chain.certs
     } else if let Some(path) = &args.file {
         let mut input = if path == "-" {
             if args.interactive {
@@ -105,7 +322,7 @@ fn main() -> eyre::Result<()> {
         tracing::debug!("parsing certificate chain");
         certs
             .into_iter()
-            .map(|der| x509_cert::Certificate::from_der(&der))
+            .map(|der| cert::Cert::from_der(&der))
             .collect::<Result<_, _>>()?
     } else {
         return Err(eyre!("use --host or --file"));
@@ -118,50 +335,260 @@ fn main() -> eyre::Result<()> {
         return Err(eyre!("chain contained 0 certificates"));
     }
 
+    let text_output = args.output == model::OutputFormat::Text;
+
+    if !text_output
+        && (args.interactive
+            || args.verify
+            || args.check_revocation
+            || !args.crl.is_empty()
+            || args.ct_log_list.is_some())
+    {
+        return Err(eyre!(
+            "--output {} cannot be combined with --interactive, --verify, --crl, \
+            --check-revocation or --ct-log-list",
+            args.output
+        ));
+    }
+
+    let new_fetcher = || {
+        http::CachingFetcher::new(http::HttpFetcher::new(
+            net::Timeouts::new(args.connect_timeout, args.timeout),
+            args.proxy.clone(),
+        ))
+    };
+
+    let completion = if args.fetch_issuers {
+        // issuer certificates do not go stale, unlike revocation information
+        let mut fetcher = new_fetcher();
+
+        if let Some(dir) = &args.cache_dir {
+            fetcher = fetcher.with_dir(dir.clone());
+        }
+
+        let completion = aia::complete_chain(&certs, &fetcher);
+        certs.extend(
+            completion
+                .fetched
+                .iter()
+                .map(|fetched| fetched.cert.clone()),
+        );
+        Some(completion)
+    } else {
+        None
+    };
+
+    let hostname = args
+        .hostname
+        .as_deref()
+        .or(args.sni.as_deref())
+        .or(args.host.as_deref());
+
+    let mut stdout = io::stdout();
+    let mut exit_code = ExitCode::SUCCESS;
+
     if args.interactive {
         let mut tui = tui::init()?;
         let mut app = tui::App::new(&certs);
         app.run(&mut tui)?;
         tui::restore()?;
+    } else if !text_output {
+        let mut document = model::Document::new(&certs, &signature::chain_statuses(&certs));
+
+        if let Some(completion) = &completion {
+            for (info, fetched) in document.certificates[n_certs..]
+                .iter_mut()
+                .zip(&completion.fetched)
+            {
+                info.fetched_from = Some(fetched.url.clone());
+            }
+        }
+
+        document.connection = connection.clone();
+        document.ocsp_staple = ocsp_response
+            .as_ref()
+            .map(|res| model::OcspStaple::new(res.as_deref(), &certs));
+        document.scts = scts
+            .as_ref()
+            .map(|scts| scts.iter().map(model::DeliveredSct::new).collect());
+        document.chain = Some(model::ChainStructure::new(chain::analyze(
+            &certs[..n_certs],
+        )));
+        document.hostname = hostname.map(|hostname| {
+            model::HostnameCheck::new(hostname, hostname::check(&certs[0], hostname))
+        });
+
+        model::write_document(&document, args.output, &mut stdout)?;
     } else {
-        let mut stdout = io::stdout();
+        if let Some(connection) = &connection {
+            connection.write_info(&mut stdout)?;
+            writeln!(&mut stdout)?;
+        }
 
-        for cert in &certs {
-            writeln!(&mut stdout, "Certificate")?;
-            writeln!(&mut stdout, "===========")?;
+        if let Some(ocsp_response) = &ocsp_response {
+            let staple = ocsp_response
+                .as_deref()
+                .map(|der| ocsp::inspect(der, &certs));
 
-            info::write_cert_info(cert, &mut stdout, false)?;
+            ocsp::write_report(staple.as_ref(), &certs[0], &mut stdout)?;
+            writeln!(&mut stdout)?;
+        }
+
+        if let Some(scts) = &scts {
+            sct::write_report(scts, &mut stdout)?;
+            writeln!(&mut stdout)?;
+        }
+
+        if let Some(completion) = &completion {
+            aia::write_report(n_certs, completion, &mut stdout)?;
+            writeln!(&mut stdout)?;
+        }
+
+        let signatures = signature::chain_statuses(&certs);
+
+        for (idx, (cert, signature)) in certs.iter().zip(&signatures).enumerate() {
+            match completion
+                .as_ref()
+                .and_then(|completion| completion.fetched.get(idx.checked_sub(n_certs)?))
+            {
+                Some(fetched) => {
+                    let origin = if args.host.is_some() {
+                        "not served by host"
+                    } else {
+                        "not in input"
+                    };
+                    let title = format!("Certificate ({origin})");
+
+                    writeln!(&mut stdout, "{title}")?;
+                    writeln!(&mut stdout, "{}", "=".repeat(title.len()))?;
+                    writeln!(&mut stdout, "Fetched From: {}", fetched.url)?;
+                }
+                None => {
+                    writeln!(&mut stdout, "Certificate")?;
+                    writeln!(&mut stdout, "===========")?;
+                }
+            }
+
+            info::write_cert_info(cert, signature, &mut stdout, false)?;
 
             writeln!(&mut stdout)?;
             writeln!(&mut stdout)?;
         }
+
+        // structure is analyzed as sent, without any fetched issuers
+        let served = &certs[..n_certs];
+        chain::write_report(served, &chain::analyze(served), &mut stdout)?;
+        writeln!(&mut stdout)?;
     }
 
-    if let Some(dump_path) = args.dump {
-        tracing::info!(%dump_path, "writing chain");
+    if let Some(hostname) = hostname
+        && !args.interactive
+        && text_output
+    {
+        let outcome = hostname::check(&certs[0], hostname);
+        hostname::write_report(hostname, &outcome, &mut stdout)?;
+        writeln!(&mut stdout)?;
+    }
+
+    if let Some(path) = &args.ct_log_list {
+        let log_list = ct::LogList::load(path)?;
 
-        let mut der_buf = Vec::with_capacity(1_024);
+        let scts = match scts {
+            Some(scts) => scts,
+            None => sct::collect(&certs[0], None),
+        };
+
+        let policy = ct::Policy {
+            min_scts: args.ct_min_scts,
+            min_operators: args.ct_min_operators,
+        };
 
-        let pem_cap = certs.len() * 2_048; // ~2Kb per cert
+        let report = ct::check(&scts, &certs, &log_list, policy);
+        ct::write_report(&log_list, &report, &mut stdout)?;
+        writeln!(&mut stdout)?;
+    }
 
-        let pem_chain = certs.into_iter().try_fold(
-            String::with_capacity(pem_cap),
-            |buf, cert| -> eyre::Result<_> {
-                der_buf.clear();
+    if !args.crl.is_empty() {
+        let crls = args
+            .crl
+            .iter()
+            .map(|path| crl::load(path))
+            .flatten_ok()
+            .collect::<eyre::Result<Vec<_>>>()?;
+
+        let results = crl::check_chain(&certs, &crls);
+        crl::write_report(&certs, &crls, &results, &mut stdout)?;
+        writeln!(&mut stdout)?;
+    }
 
-                cert.encode_to_vec(&mut der_buf)
-                    .wrap_err("failed to convert certificate back to DER encoding")?;
+    if args.check_revocation {
+        let results = revocation::check_live(&certs, &new_fetcher());
+        revocation::write_report(&certs, &results, &mut stdout)?;
+        writeln!(&mut stdout)?;
+    }
 
-                let pem = pem_rfc7468::encode_string(Certificate::PEM_LABEL, LINE_ENDING, &der_buf)
-                    .wrap_err("failed to encode DER certificate to PEM format")?;
+    if args.verify {
+        let store = verify::TrustStore::load(args.ca_file.as_deref(), args.ca_dir.as_deref())?;
+        let report = verify::verify_chain(&certs, &store, hostname)?;
+        verify::write_report(&certs, &store, &report, &mut stdout)?;
 
-                Ok(buf + &pem)
-            },
+        if report.verdict.is_err() {
+            exit_code = ExitCode::FAILURE;
+        }
+    }
+
+    if let Some(fix_path) = &args.fix_chain {
+        let served = &certs[..n_certs];
+
+        // fetched issuers can also fill gaps
+        let mut extra = match &args.intermediates_dir {
+            Some(dir) => verify::read_pem_dir(dir)?,
+            None => Vec::new(),
+        };
+        extra.extend_from_slice(&certs[n_certs..]);
+
+        let structure = chain::analyze(served);
+        let repair = chain::repair(served, &structure, &extra, args.fix_root);
+
+        tracing::info!(%fix_path, "writing repaired chain");
+        write_pem_chain(
+            fix_path,
+            repair
+                .certs
+                .iter()
+                .map(|origin| origin.cert(served, &extra)),
         )?;
 
-        fs::write(&dump_path, pem_chain)
-            .wrap_err_with(|| format!("failed to dump downloaded cert chain to {dump_path}"))?;
+        if !args.interactive && text_output {
+            chain::write_repair_report(served, &extra, &repair, fix_path, &mut stdout)?;
+            writeln!(&mut stdout)?;
+        }
     }
 
-    Ok(())
+    if let Some(dump_path) = &args.dump {
+        tracing::info!(%dump_path, "writing chain");
+
+        // only the chain as served; fetched issuers are not part of it
+        write_pem_chain(dump_path, &certs[..n_certs])?;
+    }
+
+    Ok(exit_code)
+}
+
+/// Writes certificates to file in PEM format.
+fn write_pem_chain<'a>(
+    path: &camino::Utf8Path,
+    certs: impl IntoIterator<Item = &'a cert::Cert>,
+) -> eyre::Result<()> {
+    let pem_chain = certs.into_iter().try_fold(
+        String::with_capacity(8_192), // ~2Kb per cert
+        |buf, cert| -> eyre::Result<_> {
+            let pem = pem_rfc7468::encode_string(Certificate::PEM_LABEL, LINE_ENDING, cert.der())
+                .wrap_err("failed to encode DER certificate to PEM format")?;
+
+            Ok(buf + &pem)
+        },
+    )?;
+
+    fs::write(path, pem_chain).wrap_err_with(|| format!("failed to write cert chain to {path}"))
 }
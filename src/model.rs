@@ -0,0 +1,1028 @@
+//! Structured certificate model shared by the text and machine-readable output formats.
+
+use std::{fmt, io};
+
+use base64::Engine as _;
+use byteorder::{BigEndian, ByteOrder as _};
+use const_oid::{
+    ObjectIdentifier,
+    db::rfc5912::{ID_EC_PUBLIC_KEY, RSA_ENCRYPTION},
+};
+use der::{
+    Encode as _, Tag, Tagged as _,
+    asn1::{Ia5StringRef, PrintableStringRef, TeletexStringRef, Utf8StringRef},
+};
+use eyre::bail;
+use itertools::Itertools as _;
+use serde::Serialize;
+use sha1::Digest as _;
+use x509_cert::{attr::AttributeTypeAndValue, time::Time};
+
+use crate::{
+    cert::Cert, chain, ext, fetch, hostname, ocsp, openssl, revocation, sct, signature, util,
+};
+
+/// Format of the certificate details written to stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum OutputFormat {
+    /// Human-readable text.
+    Text,
+
+    /// JSON document following the versioned schema.
+    Json,
+
+    /// YAML document with the same schema as JSON.
+    Yaml,
+
+    /// TOML document with the same schema as JSON, omitting absent (null) fields.
+    Toml,
+
+    /// Text in the layout of `openssl x509 -text -noout`.
+    Openssl,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Text => "text",
+            Self::Json => "json",
+            Self::Yaml => "yaml",
+            Self::Toml => "toml",
+            Self::Openssl => "openssl",
+        })
+    }
+}
+
+/// Version of the machine-readable schema, incremented on incompatible changes.
+pub(crate) const SCHEMA_VERSION: u32 = 1;
+
+/// Top-level document for machine-readable output.
+///
+/// Sections other than `certificates` are filled in by the caller when applicable.
+#[derive(Debug, Serialize)]
+pub(crate) struct Document {
+    pub(crate) schema_version: u32,
+    pub(crate) certificates: Vec<CertInfo>,
+
+    /// Handshake details, when the chain was fetched from a host.
+    pub(crate) connection: Option<fetch::ConnectionInfo>,
+
+    /// Stapled OCSP response, when the chain was fetched from a host.
+    pub(crate) ocsp_staple: Option<OcspStaple>,
+
+    /// Leaf SCTs from each delivery method, when the chain was fetched from a host.
+    pub(crate) scts: Option<Vec<DeliveredSct>>,
+
+    /// Order and completeness of the chain as given, without fetched issuers.
+    pub(crate) chain: Option<ChainStructure>,
+
+    /// Result of matching the leaf against a hostname.
+    pub(crate) hostname: Option<HostnameCheck>,
+}
+
+impl Document {
+    pub(crate) fn new(certs: &[Cert], signatures: &[signature::Status]) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            certificates: certs
+                .iter()
+                .zip(signatures)
+                .map(|(cert, signature)| CertInfo::new(cert, signature))
+                .collect(),
+            connection: None,
+            ocsp_staple: None,
+            scts: None,
+            chain: None,
+            hostname: None,
+        }
+    }
+}
+
+/// Stapled OCSP response details.
+#[derive(Debug, Serialize)]
+pub(crate) struct OcspStaple {
+    /// Whether the leaf requires a stapled response (OCSP Must-Staple).
+    pub(crate) must_staple: bool,
+
+    /// Decoded response, if one was stapled and could be decoded.
+    pub(crate) response: Option<OcspResponse>,
+
+    /// Why the stapled response could not be decoded.
+    pub(crate) error: Option<String>,
+}
+
+impl OcspStaple {
+    pub(crate) fn new(der: Option<&[u8]>, certs: &[Cert]) -> Self {
+        let (response, error) = match der.map(|der| ocsp::inspect(der, certs)) {
+            None => (None, None),
+            Some(Ok(res)) => (Some(OcspResponse::new(res)), None),
+            Some(Err(err)) => (None, Some(format!("{err:#}"))),
+        };
+
+        Self {
+            must_staple: ocsp::must_staple(&certs[0]),
+            response,
+            error,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct OcspResponse {
+    pub(crate) responder_id: String,
+    pub(crate) produced_at: Instant,
+    pub(crate) responses: Vec<OcspCertResponse>,
+    pub(crate) signature: OcspSignature,
+}
+
+impl OcspResponse {
+    fn new(res: ocsp::Response) -> Self {
+        Self {
+            responder_id: res.responder_id,
+            produced_at: Instant::new(res.produced_at),
+            responses: res
+                .responses
+                .into_iter()
+                .map(|res| {
+                    let (revocation_time, revocation_reason) = match res.status {
+                        revocation::Status::Revoked { time, reason } => (
+                            Some(Instant::new(time)),
+                            reason.map(|reason| format!("{reason:?}")),
+                        ),
+                        _ => (None, None),
+                    };
+
+                    OcspCertResponse {
+                        certificate_index: res.cert_idx,
+                        serial_number: Hex(res.serial),
+                        status: match res.status {
+                            revocation::Status::Good => "good",
+                            revocation::Status::Revoked { .. } => "revoked",
+                            revocation::Status::Unknown => "unknown",
+                        },
+                        revocation_time,
+                        revocation_reason,
+                        this_update: Instant::new(res.this_update),
+                        next_update: res.next_update.map(Instant::new),
+                    }
+                })
+                .collect(),
+            signature: match res.signature {
+                Ok(signer) => OcspSignature {
+                    valid: true,
+                    description: signer.describe(),
+                },
+                Err(reason) => OcspSignature {
+                    valid: false,
+                    description: reason,
+                },
+            },
+        }
+    }
+}
+
+/// Status of a single certificate in an OCSP response.
+#[derive(Debug, Serialize)]
+pub(crate) struct OcspCertResponse {
+    /// Index of the matching certificate in the chain, if any.
+    pub(crate) certificate_index: Option<usize>,
+    pub(crate) serial_number: Hex,
+
+    /// One of `good`, `revoked` or `unknown`.
+    pub(crate) status: &'static str,
+    pub(crate) revocation_time: Option<Instant>,
+    pub(crate) revocation_reason: Option<String>,
+    pub(crate) this_update: Instant,
+    pub(crate) next_update: Option<Instant>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct OcspSignature {
+    pub(crate) valid: bool,
+    pub(crate) description: String,
+}
+
+/// SCT along with how it was delivered.
+#[derive(Debug, Serialize)]
+pub(crate) struct DeliveredSct {
+    pub(crate) source: sct::Source,
+
+    #[serde(flatten)]
+    pub(crate) sct: ext::Sct,
+}
+
+impl DeliveredSct {
+    pub(crate) fn new(delivered: &sct::DeliveredSct) -> Self {
+        Self {
+            source: delivered.source,
+            sct: ext::Sct::new(&delivered.sct),
+        }
+    }
+}
+
+/// Chain structure as determined by [`chain::analyze`].
+#[derive(Debug, Serialize)]
+pub(crate) struct ChainStructure {
+    /// Indices of certificates on the path from the leaf, in issuing order.
+    pub(crate) path: Vec<usize>,
+    pub(crate) diagnostics: Vec<ChainDiagnostic>,
+}
+
+impl ChainStructure {
+    pub(crate) fn new(structure: chain::Structure) -> Self {
+        Self {
+            path: structure.path,
+            diagnostics: structure
+                .diagnostics
+                .into_iter()
+                .map(|diagnostic| ChainDiagnostic {
+                    description: diagnostic.describe(),
+                    diagnostic,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ChainDiagnostic {
+    #[serde(flatten)]
+    pub(crate) diagnostic: chain::Diagnostic,
+    pub(crate) description: String,
+}
+
+/// Result of matching the leaf against a hostname.
+#[derive(Debug, Serialize)]
+pub(crate) struct HostnameCheck {
+    pub(crate) name: String,
+    pub(crate) matched: bool,
+
+    /// Certificate entry covering the hostname, if any.
+    pub(crate) entry: Option<String>,
+
+    /// Candidate entries which did not match, along with the reason.
+    pub(crate) mismatches: Vec<HostnameMismatch>,
+}
+
+impl HostnameCheck {
+    pub(crate) fn new(name: &str, outcome: hostname::Outcome) -> Self {
+        let (entry, mismatches) = match outcome {
+            hostname::Outcome::Matched { entry } => (Some(entry), Vec::new()),
+            hostname::Outcome::NotMatched { reasons } => (
+                None,
+                reasons
+                    .into_iter()
+                    .map(|(entry, reason)| HostnameMismatch { entry, reason })
+                    .collect(),
+            ),
+        };
+
+        Self {
+            name: name.to_owned(),
+            matched: entry.is_some(),
+            entry,
+            mismatches,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct HostnameMismatch {
+    pub(crate) entry: String,
+    pub(crate) reason: String,
+}
+
+/// Writes document in the given format.
+pub(crate) fn write_document(
+    document: &Document,
+    format: OutputFormat,
+    mut wrt: impl io::Write,
+) -> eyre::Result<()> {
+    wrt.write_all(render_document(document, format)?.as_bytes())?;
+    Ok(())
+}
+
+/// Serializes document in the given format, ending with a newline.
+fn render_document(document: &Document, format: OutputFormat) -> eyre::Result<String> {
+    Ok(match format {
+        OutputFormat::Text => bail!("text output is not rendered from a document"),
+        OutputFormat::Json => serde_json::to_string_pretty(document)? + "\n",
+        OutputFormat::Yaml => serde_yaml_ng::to_string(document)?,
+        OutputFormat::Toml => toml::to_string_pretty(document)?,
+        OutputFormat::Openssl => document.certificates.iter().map(openssl::render).collect(),
+    })
+}
+
+/// Decoded certificate.
+#[derive(Debug, Serialize)]
+pub(crate) struct CertInfo {
+    pub(crate) subject: DistinguishedName,
+    pub(crate) issuer: DistinguishedName,
+
+    /// X.509 version number (1, 2 or 3).
+    pub(crate) version: u8,
+
+    pub(crate) serial_number: Hex,
+    pub(crate) signature_algorithm: Oid,
+    pub(crate) issuer_unique_id: Option<Hex>,
+    pub(crate) validity: Validity,
+    pub(crate) public_key: PublicKey,
+    pub(crate) extensions: Vec<Extension>,
+    pub(crate) fingerprints: Fingerprints,
+    pub(crate) signature: Signature,
+
+    /// CA Issuers URL the certificate was downloaded from, if it was not part of the chain given.
+    pub(crate) fetched_from: Option<String>,
+}
+
+impl CertInfo {
+    pub(crate) fn new(cert: &Cert, signature: &signature::Status) -> Self {
+        let tbs = cert.tbs_certificate();
+
+        Self {
+            subject: DistinguishedName::new(tbs.subject()),
+            issuer: DistinguishedName::new(tbs.issuer()),
+            version: tbs.version() as u8 + 1,
+            serial_number: Hex(tbs.serial_number().as_bytes().to_vec()),
+            signature_algorithm: Oid::new(&cert.signature_algorithm().oid),
+            issuer_unique_id: tbs
+                .issuer_unique_id()
+                .as_ref()
+                .map(|id| Hex(id.raw_bytes().to_vec())),
+            validity: Validity {
+                not_before: Instant::new(tbs.validity().not_before),
+                not_after: Instant::new(tbs.validity().not_after),
+            },
+            public_key: PublicKey::new(tbs.subject_public_key_info()),
+            extensions: tbs
+                .extensions()
+                .into_iter()
+                .flatten()
+                .map(|ext| Extension {
+                    oid: Oid::new(&ext.extn_id),
+                    critical: ext.critical,
+                    value: ext::decode(ext),
+                })
+                .collect(),
+            fingerprints: Fingerprints::new(cert),
+            signature: Signature {
+                value: Hex(cert.signature().raw_bytes().to_vec()),
+                verification: signature.clone(),
+            },
+            fetched_from: None,
+        }
+    }
+}
+
+/// Byte string, serialized as lowercase hex.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Hex(pub(crate) Vec<u8>);
+
+impl Serialize for Hex {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let hex = self
+            .0
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+
+        serializer.serialize_str(&hex)
+    }
+}
+
+/// Object identifier along with its well-known name, if any.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Oid {
+    pub(crate) oid: String,
+    pub(crate) name: Option<String>,
+}
+
+impl Oid {
+    pub(crate) fn new(oid: &ObjectIdentifier) -> Self {
+        let raw = oid.to_string();
+        let desc = util::oid_desc_or_raw(oid);
+
+        Self {
+            name: (desc != raw).then_some(desc),
+            oid: raw,
+        }
+    }
+
+    /// Returns name of OID, falling back to dotted notation.
+    pub(crate) fn describe(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.oid)
+    }
+}
+
+/// Distinguished name in both RFC 4514 string form and as a list of RDNs in encoded order.
+#[derive(Debug, Serialize)]
+pub(crate) struct DistinguishedName {
+    pub(crate) rfc4514: String,
+    pub(crate) rdns: Vec<Vec<Attribute>>,
+}
+
+impl DistinguishedName {
+    pub(crate) fn new(name: &x509_cert::name::Name) -> Self {
+        Self {
+            rfc4514: name.to_string(),
+            rdns: name
+                .iter_rdn()
+                .map(|rdn| rdn.iter().map(Attribute::new).collect())
+                .collect(),
+        }
+    }
+}
+
+/// Single attribute of a relative distinguished name.
+#[derive(Debug, Serialize)]
+pub(crate) struct Attribute {
+    #[serde(flatten)]
+    pub(crate) oid: Oid,
+
+    /// Attribute value; strings are decoded and other types are given as `#` followed by hex DER.
+    pub(crate) value: String,
+}
+
+impl Attribute {
+    fn new(atv: &AttributeTypeAndValue) -> Self {
+        let value = match atv.value.tag() {
+            Tag::PrintableString => PrintableStringRef::try_from(&atv.value)
+                .ok()
+                .map(|val| val.as_str().to_owned()),
+            Tag::Utf8String => Utf8StringRef::try_from(&atv.value)
+                .ok()
+                .map(|val| val.as_str().to_owned()),
+            Tag::Ia5String => Ia5StringRef::try_from(&atv.value)
+                .ok()
+                .map(|val| val.as_str().to_owned()),
+            Tag::TeletexString => TeletexStringRef::try_from(&atv.value)
+                .ok()
+                .map(|val| val.as_str().to_owned()),
+            _ => None,
+        };
+
+        let value = value.unwrap_or_else(|| {
+            let der = atv.value.to_der().unwrap_or_default();
+            format!(
+                "#{}",
+                der.iter()
+                    .map(|byte| format!("{byte:02x}"))
+                    .collect::<String>()
+            )
+        });
+
+        Self {
+            oid: Oid::new(&atv.oid),
+            value,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Validity {
+    pub(crate) not_before: Instant,
+    pub(crate) not_after: Instant,
+}
+
+/// Point in time along with its distance from now.
+#[derive(Debug, Serialize)]
+pub(crate) struct Instant {
+    /// RFC 3339 timestamp.
+    pub(crate) time: String,
+
+    pub(crate) unix_time: u64,
+
+    /// Whole days until (positive) or since (negative) this time.
+    pub(crate) days_from_now: i64,
+
+    #[serde(skip)]
+    pub(crate) in_future: bool,
+}
+
+impl Instant {
+    pub(crate) fn new(time: Time) -> Self {
+        let unix_time = time.to_unix_duration().as_secs();
+        let secs_from_now = unix_time as i64 - chrono::Utc::now().timestamp();
+
+        Self {
+            time: time.to_string(),
+            unix_time,
+            days_from_now: secs_from_now / 86_400,
+            in_future: secs_from_now >= 0,
+        }
+    }
+
+    /// Returns human-readable distance from now (e.g., "in 12 days" or "3 days ago").
+    pub(crate) fn relative(&self) -> String {
+        if self.in_future {
+            format!("in {} days", self.days_from_now)
+        } else {
+            format!("{} days ago", -self.days_from_now)
+        }
+    }
+}
+
+/// Subject public key and its algorithm-specific details.
+#[derive(Debug, Serialize)]
+pub(crate) struct PublicKey {
+    pub(crate) algorithm: Oid,
+
+    #[serde(flatten)]
+    pub(crate) params: KeyParams,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum KeyParams {
+    Ec {
+        curve: Oid,
+        point: Hex,
+    },
+    Rsa {
+        bits: usize,
+        exponent: Exponent,
+        modulus: Hex,
+    },
+    Other {
+        key: Hex,
+    },
+}
+
+/// RSA public exponent, serialized as a number if it fits in 64 bits and as hex otherwise.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub(crate) enum Exponent {
+    Small(u64),
+    Large(Hex),
+}
+
+impl PublicKey {
+    fn new(spki: &x509_cert::spki::SubjectPublicKeyInfoOwned) -> Self {
+        use pkcs1::der::Decode as _;
+
+        let alg = &spki.algorithm;
+        let key = spki.subject_public_key.raw_bytes();
+
+        let curve = alg
+            .parameters
+            .as_ref()
+            .filter(|_| alg.oid == ID_EC_PUBLIC_KEY)
+            .and_then(|params| params.decode_as::<ObjectIdentifier>().ok());
+
+        let params = match curve {
+            Some(curve) => KeyParams::Ec {
+                curve: Oid::new(&curve),
+                point: Hex(key.to_vec()),
+            },
+
+            None if alg.oid == RSA_ENCRYPTION => match pkcs1::RsaPublicKey::from_der(key) {
+                Ok(rsa) => {
+                    let exp_bytes = rsa.public_exponent.as_bytes();
+                    let modulus = rsa.modulus.as_bytes();
+
+                    KeyParams::Rsa {
+                        bits: modulus.len() * 8,
+                        exponent: match exp_bytes.len() {
+                            1..=8 => {
+                                Exponent::Small(BigEndian::read_uint(exp_bytes, exp_bytes.len()))
+                            }
+                            _ => Exponent::Large(Hex(exp_bytes.to_vec())),
+                        },
+                        modulus: Hex(modulus.to_vec()),
+                    }
+                }
+                Err(_) => KeyParams::Other {
+                    key: Hex(key.to_vec()),
+                },
+            },
+
+            None => KeyParams::Other {
+                key: Hex(key.to_vec()),
+            },
+        };
+
+        Self {
+            algorithm: Oid::new(&alg.oid),
+            params,
+        }
+    }
+}
+
+/// Certificate extension with its decoded value.
+#[derive(Debug, Serialize)]
+pub(crate) struct Extension {
+    #[serde(flatten)]
+    pub(crate) oid: Oid,
+
+    pub(crate) critical: bool,
+    pub(crate) value: ext::Value,
+}
+
+/// Hashes identifying the certificate, its public key and its subject.
+#[derive(Debug, Serialize)]
+pub(crate) struct Fingerprints {
+    /// SHA-1 of the DER-encoded certificate.
+    pub(crate) sha1: Hex,
+
+    /// SHA-256 of the DER-encoded certificate.
+    pub(crate) sha256: Hex,
+
+    /// SHA-256 of the DER-encoded subject public key info.
+    pub(crate) spki_sha256: Hex,
+
+    /// Same as `spki_sha256`, base64-encoded as used for HPKP and key pinning.
+    pub(crate) spki_sha256_base64: String,
+
+    /// OpenSSL subject name hash, as used for `c_rehash` file names.
+    pub(crate) subject_hash: String,
+}
+
+impl Fingerprints {
+    fn new(cert: &Cert) -> Self {
+        let tbs = cert.tbs_certificate();
+
+        let der = cert.der();
+        let spki_der = tbs.subject_public_key_info().to_der().unwrap_or_default();
+        let spki_sha256 = sha2::Sha256::digest(&spki_der).to_vec();
+
+        Self {
+            sha1: Hex(sha1::Sha1::digest(der).to_vec()),
+            sha256: Hex(sha2::Sha256::digest(der).to_vec()),
+            spki_sha256_base64: base64::engine::general_purpose::STANDARD.encode(&spki_sha256),
+            spki_sha256: Hex(spki_sha256),
+            subject_hash: format!("{:08x}", openssl_name_hash(tbs.subject())),
+        }
+    }
+}
+
+/// Computes OpenSSL's `X509_NAME_hash`.
+///
+/// This is the first four bytes, little-endian, of the SHA-1 of the name's canonical encoding: each
+/// RDN encoded as a sorted SET with string values converted to lowercased, whitespace-collapsed
+/// UTF8Strings, concatenated without the outer SEQUENCE.
+fn openssl_name_hash(name: &x509_cert::name::Name) -> u32 {
+    let mut canon = Vec::new();
+
+    for rdn in name.iter_rdn() {
+        let mut atvs = rdn
+            .iter()
+            .map(|atv| {
+                AttributeTypeAndValue {
+                    oid: atv.oid,
+                    value: canonical_value(&atv.value).unwrap_or_else(|| atv.value.clone()),
+                }
+                .to_der()
+                .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+        atvs.sort();
+
+        let len = atvs.iter().map(Vec::len).sum::<usize>();
+        let header = der::Length::try_from(len)
+            .and_then(|len| der::Header::new(Tag::Set, len).to_der())
+            .unwrap_or_default();
+
+        canon.extend(header);
+        canon.extend(atvs.concat());
+    }
+
+    let digest = sha1::Sha1::digest(&canon);
+    u32::from_le_bytes([digest[0], digest[1], digest[2], digest[3]])
+}
+
+/// Converts a string attribute value to the canonical form used by [`openssl_name_hash`].
+///
+/// Returns `None` for non-string values, which are hashed unchanged.
+fn canonical_value(value: &der::Any) -> Option<der::Any> {
+    let bytes = value.value();
+
+    let text = match value.tag() {
+        Tag::Utf8String => String::from_utf8(bytes.to_vec()).ok()?,
+        Tag::PrintableString | Tag::TeletexString | Tag::Ia5String | Tag::VisibleString => {
+            bytes.iter().map(|&byte| char::from(byte)).collect()
+        }
+        Tag::BmpString => char::decode_utf16(
+            bytes
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]])),
+        )
+        .collect::<Result<_, _>>()
+        .ok()?,
+        _ => return None,
+    };
+
+    let text = text
+        .split(|ch: char| ch.is_ascii_whitespace() || ch == '\x0b')
+        .filter(|word| !word.is_empty())
+        .join(" ")
+        .to_ascii_lowercase();
+
+    der::Any::new(Tag::Utf8String, text.into_bytes()).ok()
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Signature {
+    pub(crate) value: Hex,
+    pub(crate) verification: signature::Status,
+}
+
+#[cfg(test)]
+mod tests {
+    use der::Decode as _;
+
+    use super::*;
+
+    /// Builds documents for each chain in the `roots` directory.
+    fn fixture_documents() -> Vec<Document> {
+        let dir = camino::Utf8Path::new(env!("CARGO_MANIFEST_DIR")).join("roots");
+
+        let mut paths = dir
+            .read_dir_utf8()
+            .unwrap()
+            .map(|entry| entry.unwrap().into_path())
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert!(!paths.is_empty(), "no fixtures in {dir}");
+
+        paths
+            .iter()
+            .map(|path| {
+                let pem = std::fs::read(path).unwrap();
+                let certs = rustls_pemfile::certs(&mut io::Cursor::new(pem))
+                    .map(|der| Cert::from_der(&der.unwrap()).unwrap())
+                    .collect::<Vec<_>>();
+
+                Document::new(&certs, &signature::chain_statuses(&certs))
+            })
+            .collect()
+    }
+
+    /// Returns the JSON form of the extensions of the first certificate in a fixture file.
+    fn fixture_extensions(name: &str) -> serde_json::Value {
+        let certs = crate::cert::fixtures::certs(name);
+        let document = Document::new(&certs, &signature::chain_statuses(&certs));
+
+        to_json_value(&document)["certificates"][0]["extensions"].take()
+    }
+
+    fn to_json_value(document: &Document) -> serde_json::Value {
+        serde_json::from_str(&render_document(document, OutputFormat::Json).unwrap()).unwrap()
+    }
+
+    /// Removes null object members, which have no TOML representation.
+    fn strip_nulls(val: &mut serde_json::Value) {
+        match val {
+            serde_json::Value::Object(map) => {
+                map.retain(|_, val| !val.is_null());
+                map.values_mut().for_each(strip_nulls);
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn yaml_round_trips_to_json() {
+        for document in fixture_documents() {
+            let yaml = render_document(&document, OutputFormat::Yaml).unwrap();
+            let from_yaml = serde_yaml_ng::from_str::<serde_json::Value>(&yaml).unwrap();
+
+            assert_eq!(from_yaml, to_json_value(&document));
+        }
+    }
+
+    #[test]
+    fn toml_round_trips_to_json() {
+        for document in fixture_documents() {
+            let toml = render_document(&document, OutputFormat::Toml).unwrap();
+            let from_toml = toml::from_str::<serde_json::Value>(&toml).unwrap();
+
+            let mut json = to_json_value(&document);
+            strip_nulls(&mut json);
+
+            assert_eq!(from_toml, json);
+        }
+    }
+
+    #[test]
+    fn report_sections() {
+        let certs = crate::cert::fixtures::chain(&["pki/revoked.pem", "pki/ca.pem"]);
+        let ct_leaf = crate::cert::fixtures::certs("ct/leaf.pem");
+
+        let mut document = Document::new(&certs, &signature::chain_statuses(&certs));
+        document.ocsp_staple = Some(OcspStaple::new(
+            Some(&crate::cert::fixtures::read("pki/ocsp-revoked.der")),
+            &certs,
+        ));
+        document.scts = Some(
+            sct::collect(&ct_leaf[0], None)
+                .iter()
+                .map(DeliveredSct::new)
+                .collect(),
+        );
+        document.chain = Some(ChainStructure::new(chain::analyze(&certs)));
+        document.hostname = Some(HostnameCheck::new(
+            "other.test",
+            hostname::check(&certs[0], "other.test"),
+        ));
+
+        let json = to_json_value(&document);
+
+        let staple = &json["ocsp_staple"];
+        assert_eq!(staple["must_staple"], false);
+        assert_eq!(staple["response"]["responses"][0]["certificate_index"], 0);
+        assert_eq!(staple["response"]["responses"][0]["status"], "revoked");
+        assert_eq!(
+            staple["response"]["responses"][0]["revocation_reason"],
+            "KeyCompromise"
+        );
+        assert_eq!(staple["response"]["signature"]["valid"], true);
+
+        assert_eq!(json["scts"].as_array().unwrap().len(), 5);
+        assert_eq!(json["scts"][0]["source"], "certificate_extension");
+
+        assert_eq!(json["chain"]["path"], serde_json::json!([0, 1]));
+        assert_eq!(json["chain"]["diagnostics"][0]["kind"], "root_included");
+        assert_eq!(json["chain"]["diagnostics"][0]["index"], 1);
+
+        assert_eq!(json["hostname"]["matched"], false);
+        assert!(
+            !json["hostname"]["mismatches"]
+                .as_array()
+                .unwrap()
+                .is_empty()
+        );
+
+        let from_yaml = serde_yaml_ng::from_str::<serde_json::Value>(
+            &render_document(&document, OutputFormat::Yaml).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(from_yaml, json);
+
+        let from_toml = toml::from_str::<serde_json::Value>(
+            &render_document(&document, OutputFormat::Toml).unwrap(),
+        )
+        .unwrap();
+        let mut json = json;
+        strip_nulls(&mut json);
+        assert_eq!(from_toml, json);
+    }
+
+    /// Expected values are from `openssl x509 -subject_hash` and the SPKI pinning recipe.
+    #[test]
+    fn fingerprints_match_openssl() {
+        let expected = [
+            ("66cb4e9d", "B/ap+qAli0QgK/GmwkqkWS5Ta20el3pYN4MTgORZMjo="),
+            ("e113c810", "UQ0g5cR/Y89mayD2GvYrwJmkKsgk/6RDotp8kLGAipE="),
+            ("f51bb24c", "joBG7EysAVpQfODS0BVKS0Do5CsxZc+lRlcUNREtF+U="),
+            ("9846683b", "oC+voZLIy4HLE0FVT5wFtxzKKokLDRKY1oNkfJYe+98="),
+            ("6b99d060", "bb+uANN7nNc/j7R95lkXrwDg3d9C286sIMF8AnXuIJU="),
+            ("2b349938", "bEZLmlsjOl6HTadlwm8EUBDS3c/0V5TwtMfkqvpQFJU="),
+            ("136f8e91", "q3Xq4Mpxp7b/brD0VvePfYl2nFWolip+INNFfNHNbTU="),
+        ];
+
+        for (document, (subject_hash, spki_pin)) in fixture_documents().iter().zip(expected) {
+            let fingerprints = &document.certificates[0].fingerprints;
+
+            assert_eq!(fingerprints.subject_hash, subject_hash);
+            assert_eq!(fingerprints.spki_sha256_base64, spki_pin);
+        }
+    }
+
+    #[test]
+    fn other_names_are_structured() {
+        let exts = fixture_extensions("other-names.pem");
+
+        assert_eq!(
+            exts[0]["value"]["names"],
+            serde_json::json!([
+                {
+                    "type": "other",
+                    "value": {
+                        "type_id": { "oid": "1.3.6.1.4.1.311.20.2.3", "name": null },
+                        "value": "0c1175736572406578616d706c652e74657374",
+                    },
+                },
+                {
+                    "type": "edi_party",
+                    "value": { "der": "300da10b0c09454449205061727479" },
+                },
+                { "type": "dns", "value": "other-names.test" },
+            ])
+        );
+    }
+
+    #[test]
+    fn rsa_exponents() {
+        assert_eq!(serde_json::to_value(Exponent::Small(65537)).unwrap(), 65537);
+
+        // 2^64 + 1 does not fit in 64 bits
+        let certs = crate::cert::fixtures::certs("large-exponent.pem");
+        let document = Document::new(&certs, &signature::chain_statuses(&certs));
+        assert_eq!(
+            to_json_value(&document)["certificates"][0]["public_key"]["exponent"],
+            "010000000000000001"
+        );
+    }
+
+    #[test]
+    fn name_constraints_and_policy_controls() {
+        let exts = fixture_extensions("name-constraints.pem");
+
+        let subtree = |ty: &str, value: &str| {
+            serde_json::json!({
+                "base": { "type": ty, "value": value },
+                "minimum": 0,
+                "maximum": null,
+            })
+        };
+
+        let constraints = &exts[1]["value"];
+        assert_eq!(constraints["type"], "name_constraints");
+        assert_eq!(
+            constraints["permitted"],
+            serde_json::json!([
+                subtree("dns", ".example.com"),
+                subtree("dns", "example.org"),
+                subtree("ip", "10.0.0.0/255.0.0.0"),
+                subtree("ip", "2001:db8::/ffff:ffff::"),
+                subtree("rfc822", ".example.com"),
+                {
+                    "base": {
+                        "type": "directory",
+                        "value": {
+                            "rfc4514": "O=Example\\, Inc.,C=US",
+                            "rdns": [
+                                [{ "oid": "2.5.4.6", "name": "c", "value": "US" }],
+                                [{ "oid": "2.5.4.10", "name": "o", "value": "Example, Inc." }],
+                            ],
+                        },
+                    },
+                    "minimum": 0,
+                    "maximum": null,
+                },
+                subtree("uri", ".example.net"),
+            ])
+        );
+        assert_eq!(
+            constraints["excluded"],
+            serde_json::json!([
+                subtree("dns", "bad.example.com"),
+                subtree("ip", "192.168.0.0/255.255.0.0"),
+            ])
+        );
+
+        assert_eq!(
+            exts[2]["value"],
+            serde_json::json!({
+                "type": "policy_constraints",
+                "require_explicit_policy": 0,
+                "inhibit_policy_mapping": 3,
+            })
+        );
+
+        assert_eq!(
+            exts[3]["value"],
+            serde_json::json!({
+                "type": "policy_mappings",
+                "mappings": [
+                    {
+                        "issuer_domain_policy": { "oid": "1.3.6.1.4.1.99.1", "name": null },
+                        "subject_domain_policy": { "oid": "1.3.6.1.4.1.99.2", "name": null },
+                    },
+                    {
+                        "issuer_domain_policy": { "oid": "2.5.29.32.0", "name": "anyPolicy" },
+                        "subject_domain_policy": {
+                            "oid": "2.23.140.1.2.1",
+                            "name": "Domain Validated",
+                        },
+                    },
+                ],
+            })
+        );
+
+        assert_eq!(
+            exts[4]["value"],
+            serde_json::json!({ "type": "inhibit_any_policy", "skip_certs": 2 })
+        );
+
+        // YAML and TOML carry the same structure
+        let certs = crate::cert::fixtures::certs("name-constraints.pem");
+        let document = Document::new(&certs, &signature::chain_statuses(&certs));
+        let json = to_json_value(&document);
+
+        let from_yaml = serde_yaml_ng::from_str::<serde_json::Value>(
+            &render_document(&document, OutputFormat::Yaml).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(from_yaml, json);
+
+        let from_toml = toml::from_str::<serde_json::Value>(
+            &render_document(&document, OutputFormat::Toml).unwrap(),
+        )
+        .unwrap();
+        let mut json = json;
+        strip_nulls(&mut json);
+        assert_eq!(from_toml, json);
+    }
+}
@@ -0,0 +1,252 @@
+use std::{
+    io,
+    net::{SocketAddr, TcpStream, ToSocketAddrs as _},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use eyre::{WrapErr as _, eyre};
+
+/// Time limits for each phase of a remote fetch.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Timeouts {
+    /// Limit for DNS resolution and, separately, for establishing the TCP connection.
+    pub(crate) connect: Option<Duration>,
+
+    /// Limit for each of proxy negotiation, STARTTLS negotiation, TLS handshake and reading.
+    pub(crate) io: Option<Duration>,
+}
+
+impl Timeouts {
+    /// Constructs timeouts, where zero durations disable the corresponding limit.
+    pub(crate) fn new(connect: Duration, io: Duration) -> Self {
+        Self {
+            connect: Some(connect).filter(|timeout| !timeout.is_zero()),
+            io: Some(io).filter(|timeout| !timeout.is_zero()),
+        }
+    }
+}
+
+/// Parses a timeout given in seconds (e.g., `5`, `2.5`, `500ms`).
+pub(crate) fn parse_timeout(val: &str) -> Result<Duration, String> {
+    let (num, scale) = match val.strip_suffix("ms") {
+        Some(ms) => (ms, 1_000.0),
+        None => (val.strip_suffix('s').unwrap_or(val), 1.0),
+    };
+
+    let secs = num
+        .trim()
+        .parse::<f64>()
+        .map_err(|err| format!("invalid timeout \"{val}\": {err}"))?
+        / scale;
+
+    if secs < 0.0 {
+        return Err(format!("invalid timeout \"{val}\""));
+    }
+
+    Duration::try_from_secs_f64(secs).map_err(|_| format!("invalid timeout \"{val}\""))
+}
+
+/// Resolves `host:port` to socket addresses, bounded by the connect timeout.
+pub(crate) fn resolve(addr: &str, timeouts: &Timeouts) -> eyre::Result<Vec<SocketAddr>> {
+    tracing::debug!(%addr, "resolving address");
+
+    let Some(timeout) = timeouts.connect else {
+        return Ok(addr
+            .to_socket_addrs()
+            .wrap_err_with(|| format!("failed to resolve address: {addr}"))?
+            .collect());
+    };
+
+    // std offers no way to bound name resolution so do it on another thread
+    let (tx, rx) = mpsc::channel();
+    let owned_addr = addr.to_owned();
+
+    thread::spawn(move || {
+        let res = owned_addr
+            .to_socket_addrs()
+            .map(|addrs| addrs.collect::<Vec<_>>());
+        let _ = tx.send(res);
+    });
+
+    match rx.recv_timeout(timeout) {
+        Ok(res) => res.wrap_err_with(|| format!("failed to resolve address: {addr}")),
+        Err(_) => Err(eyre!(
+            "DNS lookup timed out after {} resolving {addr}",
+            fmt_duration(timeout)
+        )),
+    }
+}
+
+/// Resolves and connects to `host:port`, bounding each phase by the connect timeout.
+pub(crate) fn connect(addr: &str, timeouts: &Timeouts) -> eyre::Result<TcpStream> {
+    let addrs = resolve(addr, timeouts)?;
+
+    let mut last_err = None;
+
+    for sock_addr in &addrs {
+        tracing::debug!(%sock_addr, "connecting");
+
+        let res = match timeouts.connect {
+            Some(timeout) => TcpStream::connect_timeout(sock_addr, timeout),
+            None => TcpStream::connect(sock_addr),
+        };
+
+        match res {
+            Ok(sock) => return Ok(sock),
+            Err(err) => {
+                tracing::debug!(%sock_addr, "failed to connect: {err}");
+                last_err = Some(err);
+            }
+        }
+    }
+
+    Err(match (last_err, timeouts.connect) {
+        (Some(err), Some(timeout)) if is_timeout(&err) => eyre!(
+            "TCP connect timed out after {} connecting to {addr}",
+            fmt_duration(timeout)
+        ),
+        (Some(err), _) => eyre!(err).wrap_err(format!("failed to connect to host: {addr}")),
+        (None, _) => eyre!("failed to connect to host: {addr} did not resolve to any address"),
+    })
+}
+
+/// Deadline for one phase of a remote fetch.
+#[derive(Debug)]
+pub(crate) struct Deadline {
+    phase: &'static str,
+    timeout: Option<Duration>,
+    at: Option<Instant>,
+}
+
+impl Deadline {
+    /// Starts the named phase, which must complete within `timeout`.
+    pub(crate) fn start(phase: &'static str, timeout: Option<Duration>) -> Self {
+        Self {
+            phase,
+            timeout,
+            at: timeout.map(|timeout| Instant::now() + timeout),
+        }
+    }
+
+    /// Limits blocking socket operations to the time remaining in this phase.
+    pub(crate) fn arm(&self, sock: &TcpStream) -> eyre::Result<()> {
+        let Some(at) = self.at else {
+            sock.set_read_timeout(None)?;
+            sock.set_write_timeout(None)?;
+            return Ok(());
+        };
+
+        let remaining = at
+            .checked_duration_since(Instant::now())
+            .filter(|remaining| !remaining.is_zero())
+            .ok_or_else(|| eyre!(self.timed_out()))?;
+
+        sock.set_read_timeout(Some(remaining))?;
+        sock.set_write_timeout(Some(remaining))?;
+
+        Ok(())
+    }
+
+    /// Converts socket timeouts found in `err` into an error naming this phase.
+    pub(crate) fn map_err(&self, err: eyre::Report) -> eyre::Report {
+        let timed_out = err
+            .chain()
+            .filter_map(|err| err.downcast_ref::<io::Error>())
+            .any(is_timeout);
+
+        if timed_out && self.timeout.is_some() {
+            err.wrap_err(self.timed_out())
+        } else {
+            err
+        }
+    }
+
+    fn timed_out(&self) -> String {
+        format!(
+            "{} timed out after {}",
+            self.phase,
+            fmt_duration(self.timeout.unwrap_or_default())
+        )
+    }
+}
+
+/// Returns true if error was caused by a socket timeout.
+pub(crate) fn is_timeout(err: &io::Error) -> bool {
+    // read timeouts are reported as WouldBlock on Unix platforms
+    matches!(
+        err.kind(),
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    )
+}
+
+fn fmt_duration(duration: Duration) -> String {
+    format!("{}s", duration.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Read as _, net::TcpListener};
+
+    use super::*;
+
+    #[test]
+    fn timeouts() {
+        assert_eq!(parse_timeout("5"), Ok(Duration::from_secs(5)));
+        assert_eq!(parse_timeout("2.5s"), Ok(Duration::from_millis(2_500)));
+        assert_eq!(parse_timeout("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_timeout(" 1 "), Ok(Duration::from_secs(1)));
+        assert_eq!(parse_timeout("0"), Ok(Duration::ZERO));
+
+        for val in ["", "s", "ms", "-1", "five", "1m", "inf", "NaN", "1e30"] {
+            assert!(parse_timeout(val).is_err(), "{val:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn zero_disables_timeout() {
+        let timeouts = Timeouts::new(Duration::ZERO, Duration::from_secs(1));
+
+        assert_eq!(timeouts.connect, None);
+        assert_eq!(timeouts.io, Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn deadline_names_phase() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let timeouts = Timeouts::new(Duration::from_secs(5), Duration::from_millis(50));
+        let mut sock = connect(&listener.local_addr().unwrap().to_string(), &timeouts).unwrap();
+
+        let deadline = Deadline::start("TLS handshake", timeouts.io);
+        deadline.arm(&sock).unwrap();
+
+        // the peer never sends anything
+        let err = sock.read(&mut [0; 1]).unwrap_err();
+        let err = deadline.map_err(eyre::Report::new(err));
+
+        assert_eq!(err.to_string(), "TLS handshake timed out after 0.05s");
+
+        // other errors are left alone
+        let err = deadline.map_err(eyre!("connection closed"));
+        assert_eq!(err.to_string(), "connection closed");
+    }
+
+    #[test]
+    fn connect_failure() {
+        // bind then drop a listener to find a port which refuses connections
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+
+        let timeouts = Timeouts::new(Duration::from_secs(5), Duration::ZERO);
+        let err = connect(&addr, &timeouts).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            format!("failed to connect to host: {addr}")
+        );
+    }
+}
@@ -0,0 +1,615 @@
+use std::io;
+
+use const_oid::{
+    ObjectIdentifier,
+    db::{rfc5280::ID_KP_OCSP_SIGNING, rfc5912, rfc6960::ID_PKIX_OCSP_BASIC},
+};
+use der::{
+    Choice, Decode as _, Encode as _, Enumerated, Sequence,
+    asn1::{BitString, GeneralizedTime, Null, OctetString},
+};
+use eyre::{bail, eyre};
+use sha1::Digest as _;
+use x509_cert::{
+    Certificate,
+    ext::{
+        Extensions,
+        pkix::{ExtendedKeyUsage, crl::CrlReason},
+    },
+    name::Name,
+    serial_number::SerialNumber,
+    spki::AlgorithmIdentifierOwned,
+    time::Time,
+};
+
+use crate::{
+    cert::{Cert, find_issuer, is_issuer},
+    revocation::Status,
+    signature, util,
+};
+
+/// TLS Feature extension (RFC 7633), used to require OCSP stapling.
+pub(crate) const ID_PE_TLS_FEATURE: ObjectIdentifier =
+    ObjectIdentifier::new_unwrap("1.3.6.1.5.5.7.1.24");
+
+/// TLS `status_request` extension number, as listed in the TLS Feature extension.
+const STATUS_REQUEST: u16 = 5;
+
+/// OCSP response as defined in [RFC 6960 §4.2.1].
+///
+/// ```text
+/// OCSPResponse ::= SEQUENCE {
+///    responseStatus         OCSPResponseStatus,
+///    responseBytes          [0] EXPLICIT ResponseBytes OPTIONAL }
+/// ```
+///
+/// [RFC 6960 §4.2.1]: https://datatracker.ietf.org/doc/html/rfc6960#section-4.2.1
+#[derive(Debug, Sequence)]
+struct OcspResponse {
+    response_status: ResponseStatus,
+
+    #[asn1(context_specific = "0", optional = "true")]
+    response_bytes: Option<ResponseBytes>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enumerated)]
+#[repr(u32)]
+enum ResponseStatus {
+    Successful = 0,
+    MalformedRequest = 1,
+    InternalError = 2,
+    TryLater = 3,
+    SigRequired = 5,
+    Unauthorized = 6,
+}
+
+#[derive(Debug, Sequence)]
+struct ResponseBytes {
+    response_type: ObjectIdentifier,
+    response: OctetString,
+}
+
+/// Basic OCSP response as defined in [RFC 6960 §4.2.1].
+///
+/// ```text
+/// BasicOCSPResponse ::= SEQUENCE {
+///    tbsResponseData      ResponseData,
+///    signatureAlgorithm   AlgorithmIdentifier,
+///    signature            BIT STRING,
+///    certs            [0] EXPLICIT SEQUENCE OF Certificate OPTIONAL }
+/// ```
+///
+/// [RFC 6960 §4.2.1]: https://datatracker.ietf.org/doc/html/rfc6960#section-4.2.1
+#[derive(Debug, Sequence)]
+pub(crate) struct BasicOcspResponse {
+    pub(crate) tbs_response_data: ResponseData,
+    signature_algorithm: AlgorithmIdentifierOwned,
+    signature: BitString,
+
+    #[asn1(context_specific = "0", optional = "true")]
+    certs: Option<Vec<Cert>>,
+}
+
+#[derive(Debug, Sequence)]
+pub(crate) struct ResponseData {
+    #[asn1(context_specific = "0", default = "Default::default")]
+    version: u8,
+
+    responder_id: ResponderId,
+    produced_at: GeneralizedTime,
+    pub(crate) responses: Vec<SingleResponse>,
+
+    #[asn1(context_specific = "1", optional = "true")]
+    response_extensions: Option<Extensions>,
+}
+
+#[derive(Debug, Choice)]
+enum ResponderId {
+    #[asn1(context_specific = "1", tag_mode = "EXPLICIT", constructed = "true")]
+    ByName(Name),
+
+    #[asn1(context_specific = "2", tag_mode = "EXPLICIT", constructed = "true")]
+    ByKey(OctetString),
+}
+
+#[derive(Debug, Sequence)]
+pub(crate) struct SingleResponse {
+    cert_id: CertId,
+    cert_status: CertStatus,
+    this_update: GeneralizedTime,
+
+    #[asn1(context_specific = "0", optional = "true")]
+    next_update: Option<GeneralizedTime>,
+
+    #[asn1(context_specific = "1", optional = "true")]
+    pub(crate) single_extensions: Option<Extensions>,
+}
+
+impl SingleResponse {
+    /// Returns serial number of the certificate this response is for.
+    pub(crate) fn serial_number(&self) -> &SerialNumber {
+        &self.cert_id.serial_number
+    }
+}
+
+#[derive(Debug, Sequence)]
+struct CertId {
+    hash_algorithm: AlgorithmIdentifierOwned,
+    issuer_name_hash: OctetString,
+    issuer_key_hash: OctetString,
+    serial_number: SerialNumber,
+}
+
+#[derive(Debug, Choice)]
+enum CertStatus {
+    #[asn1(context_specific = "0", tag_mode = "IMPLICIT")]
+    Good(Null),
+
+    #[asn1(context_specific = "1", tag_mode = "IMPLICIT", constructed = "true")]
+    Revoked(RevokedInfo),
+
+    #[asn1(context_specific = "2", tag_mode = "IMPLICIT")]
+    Unknown(Null),
+}
+
+#[derive(Debug, Sequence)]
+struct RevokedInfo {
+    revocation_time: GeneralizedTime,
+
+    #[asn1(context_specific = "0", optional = "true")]
+    revocation_reason: Option<CrlReason>,
+}
+
+/// OCSP request as defined in [RFC 6960 §4.1.1].
+///
+/// ```text
+/// OCSPRequest ::= SEQUENCE {
+///    tbsRequest                  TBSRequest,
+///    optionalSignature   [0]     EXPLICIT Signature OPTIONAL }
+/// ```
+///
+/// Requests are always sent unsigned.
+///
+/// [RFC 6960 §4.1.1]: https://datatracker.ietf.org/doc/html/rfc6960#section-4.1.1
+#[derive(Debug, Sequence)]
+struct OcspRequest {
+    tbs_request: TbsRequest,
+}
+
+#[derive(Debug, Sequence)]
+struct TbsRequest {
+    #[asn1(context_specific = "0", default = "Default::default")]
+    version: u8,
+
+    request_list: Vec<Request>,
+}
+
+#[derive(Debug, Sequence)]
+struct Request {
+    req_cert: CertId,
+}
+
+/// Builds a DER-encoded OCSP request for `cert`, identified using SHA-1 hashes of its issuer.
+pub(crate) fn build_request(cert: &Certificate, issuer: &Certificate) -> eyre::Result<Vec<u8>> {
+    let issuer_key = issuer
+        .tbs_certificate()
+        .subject_public_key_info()
+        .subject_public_key
+        .as_bytes()
+        .ok_or_else(|| eyre!("issuer public key has unused bits"))?;
+
+    let req = OcspRequest {
+        tbs_request: TbsRequest {
+            version: 0,
+            request_list: vec![Request {
+                req_cert: CertId {
+                    hash_algorithm: AlgorithmIdentifierOwned {
+                        oid: rfc5912::ID_SHA_1,
+                        parameters: Some(Null.into()),
+                    },
+                    issuer_name_hash: OctetString::new(
+                        sha1::Sha1::digest(cert.tbs_certificate().issuer().to_der()?).to_vec(),
+                    )?,
+                    issuer_key_hash: OctetString::new(sha1::Sha1::digest(issuer_key).to_vec())?,
+                    serial_number: cert.tbs_certificate().serial_number().clone(),
+                },
+            }],
+        },
+    };
+
+    Ok(req.to_der()?)
+}
+
+/// Decodes an OCSP response, returning its basic response if it was successful.
+pub(crate) fn decode(der: &[u8]) -> eyre::Result<BasicOcspResponse> {
+    Ok(BasicOcspResponse::from_der(&basic_response_bytes(der)?)?)
+}
+
+/// Returns the encoded basic response of a successful OCSP response.
+fn basic_response_bytes(der: &[u8]) -> eyre::Result<Vec<u8>> {
+    let res = OcspResponse::from_der(der)?;
+
+    if res.response_status != ResponseStatus::Successful {
+        bail!("responder returned status {:?}", res.response_status);
+    }
+
+    let bytes = res
+        .response_bytes
+        .ok_or_else(|| eyre!("successful response contains no response bytes"))?;
+
+    if bytes.response_type != ID_PKIX_OCSP_BASIC {
+        bail!(
+            "unsupported response type {}",
+            util::oid_desc_or_raw(&bytes.response_type)
+        );
+    }
+
+    Ok(bytes.response.into_bytes().into_vec())
+}
+
+/// Decoded OCSP response, checked against the certificate chain.
+#[derive(Debug)]
+pub(crate) struct Response {
+    pub(crate) responder_id: String,
+    pub(crate) produced_at: Time,
+    pub(crate) responses: Vec<CertResponse>,
+    pub(crate) signature: Result<Signer, String>,
+}
+
+/// Status of a single certificate in an OCSP response.
+#[derive(Debug)]
+pub(crate) struct CertResponse {
+    /// Index of matching certificate in the chain.
+    pub(crate) cert_idx: Option<usize>,
+    pub(crate) serial: Vec<u8>,
+    pub(crate) status: Status,
+    pub(crate) this_update: Time,
+    pub(crate) next_update: Option<Time>,
+}
+
+/// Key which produced a valid OCSP response signature.
+#[derive(Debug)]
+pub(crate) enum Signer {
+    /// Signed directly by the certificate's issuer at this index in the chain.
+    Issuer { issuer_idx: usize },
+
+    /// Signed by a delegated responder certificate issued by the certificate's issuer.
+    Delegated {
+        responder: String,
+        issuer_idx: usize,
+    },
+}
+
+impl Signer {
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Self::Issuer { issuer_idx } => {
+                format!("valid (signed by issuer, certificate #{issuer_idx})")
+            }
+            Self::Delegated {
+                responder,
+                issuer_idx,
+            } => format!(
+                "valid (signed by delegated responder {responder}, issued by certificate #{issuer_idx})"
+            ),
+        }
+    }
+}
+
+/// Decodes OCSP response and matches it against the certificate chain.
+pub(crate) fn inspect(der: &[u8], certs: &[Cert]) -> eyre::Result<Response> {
+    let basic_der = basic_response_bytes(der)?;
+    let basic = BasicOcspResponse::from_der(&basic_der)?;
+    let data = &basic.tbs_response_data;
+
+    let responses = data
+        .responses
+        .iter()
+        .map(|res| CertResponse {
+            cert_idx: certs
+                .iter()
+                .position(|cert| cert_id_matches(&res.cert_id, cert, certs)),
+            serial: res.cert_id.serial_number.as_bytes().to_vec(),
+            status: match &res.cert_status {
+                CertStatus::Good(_) => Status::Good,
+                CertStatus::Revoked(info) => Status::Revoked {
+                    time: Time::GeneralTime(info.revocation_time),
+                    reason: info.revocation_reason,
+                },
+                CertStatus::Unknown(_) => Status::Unknown,
+            },
+            this_update: Time::GeneralTime(res.this_update),
+            next_update: res.next_update.map(Time::GeneralTime),
+        })
+        .collect::<Vec<_>>();
+
+    let signature = responses
+        .iter()
+        .find_map(|res| res.cert_idx)
+        .ok_or_else(|| "unchecked (no certificate in chain matches response)".to_owned())
+        .and_then(|cert_idx| {
+            let issuer_idx = find_issuer(cert_idx, certs)
+                .ok_or_else(|| "unchecked (issuer not in chain)".to_owned())?;
+
+            verify_signature(&basic, &basic_der, &certs[issuer_idx], issuer_idx)
+        });
+
+    Ok(Response {
+        responder_id: match &data.responder_id {
+            ResponderId::ByName(name) => name.to_string(),
+            ResponderId::ByKey(hash) => {
+                format!(
+                    "key hash {}",
+                    util::openssl_hex(hash.as_bytes(), usize::MAX).collect::<String>()
+                )
+            }
+        },
+        produced_at: Time::GeneralTime(data.produced_at),
+        responses,
+        signature,
+    })
+}
+
+/// Returns true if the leaf requires a stapled OCSP response (OCSP Must-Staple).
+pub(crate) fn must_staple(leaf: &Certificate) -> bool {
+    leaf.tbs_certificate()
+        .extensions()
+        .into_iter()
+        .flatten()
+        .filter(|ext| ext.extn_id == ID_PE_TLS_FEATURE)
+        .filter_map(|ext| Vec::<u16>::from_der(ext.extn_value.as_bytes()).ok())
+        .any(|features| features.contains(&STATUS_REQUEST))
+}
+
+/// Writes stapled OCSP response details in text form.
+pub(crate) fn write_report(
+    staple: Option<&eyre::Result<Response>>,
+    leaf: &Certificate,
+    mut wrt: impl io::Write,
+) -> io::Result<()> {
+    writeln!(wrt, "OCSP Staple")?;
+    writeln!(wrt, "===========")?;
+    writeln!(
+        wrt,
+        "Must-Staple: {}",
+        if must_staple(leaf) { "yes" } else { "no" }
+    )?;
+
+    let staple = match staple {
+        None => return writeln!(wrt, "Response: <none>"),
+        Some(Err(err)) => return writeln!(wrt, "Response: could not be decoded: {err:#}"),
+        Some(Ok(staple)) => staple,
+    };
+
+    writeln!(wrt, "Responder ID: {}", staple.responder_id)?;
+    writeln!(wrt, "Produced At: {}", staple.produced_at)?;
+
+    for res in &staple.responses {
+        writeln!(wrt, "Response:")?;
+        writeln!(
+            wrt,
+            "  Serial Number: {}{}",
+            util::openssl_hex(&res.serial, usize::MAX).collect::<String>(),
+            match res.cert_idx {
+                Some(idx) => format!(" (certificate #{idx})"),
+                None => " (not in chain)".to_owned(),
+            }
+        )?;
+        writeln!(wrt, "  Status: {}", res.status.describe())?;
+
+        let (this_update, _) = util::duration_since_now_fmt(res.this_update);
+        writeln!(wrt, "  This Update: {} ({this_update})", res.this_update)?;
+
+        match res.next_update {
+            Some(next_update) => {
+                let (when, in_future) = util::duration_since_now_fmt(next_update);
+                writeln!(
+                    wrt,
+                    "  Next Update: {next_update} ({when}{})",
+                    if in_future { "" } else { ", EXPIRED" }
+                )?;
+            }
+            None => writeln!(wrt, "  Next Update: <none>")?,
+        }
+    }
+
+    writeln!(
+        wrt,
+        "Signature: {}",
+        match &staple.signature {
+            Ok(signer) => signer.describe(),
+            Err(reason) => reason.clone(),
+        }
+    )?;
+
+    Ok(())
+}
+
+/// Checks that `cert_id` identifies `cert`, using the hashes of its issuer from the chain.
+fn cert_id_matches(cert_id: &CertId, cert: &Certificate, certs: &[Cert]) -> bool {
+    let tbs = cert.tbs_certificate();
+
+    if cert_id.serial_number.as_bytes() != tbs.serial_number().as_bytes() {
+        return false;
+    }
+
+    let Ok(issuer_name) = tbs.issuer().to_der() else {
+        return false;
+    };
+
+    if hash(&cert_id.hash_algorithm.oid, &issuer_name).as_deref()
+        != Some(cert_id.issuer_name_hash.as_bytes())
+    {
+        return false;
+    }
+
+    // without the issuer's key, the certificate is not identified by the issuer name hash alone
+    certs
+        .iter()
+        .filter(|issuer| is_issuer(issuer, cert))
+        .filter_map(|issuer| {
+            issuer
+                .tbs_certificate()
+                .subject_public_key_info()
+                .subject_public_key
+                .as_bytes()
+        })
+        .any(|key| {
+            hash(&cert_id.hash_algorithm.oid, key).as_deref()
+                == Some(cert_id.issuer_key_hash.as_bytes())
+        })
+}
+
+fn hash(alg: &ObjectIdentifier, data: &[u8]) -> Option<Vec<u8>> {
+    match *alg {
+        rfc5912::ID_SHA_1 => Some(sha1::Sha1::digest(data).to_vec()),
+        rfc5912::ID_SHA_256 => Some(sha2::Sha256::digest(data).to_vec()),
+        rfc5912::ID_SHA_384 => Some(sha2::Sha384::digest(data).to_vec()),
+        rfc5912::ID_SHA_512 => Some(sha2::Sha512::digest(data).to_vec()),
+        _ => None,
+    }
+}
+
+/// Verifies response signature using the issuer's key or that of an included delegated responder.
+fn verify_signature(
+    basic: &BasicOcspResponse,
+    basic_der: &[u8],
+    issuer: &Certificate,
+    issuer_idx: usize,
+) -> Result<Signer, String> {
+    let tbs =
+        signature::tbs_bytes(basic_der).map_err(|err| format!("unchecked ({})", err.reason()))?;
+
+    let signature = basic
+        .signature
+        .as_bytes()
+        .ok_or_else(|| "INVALID (signature has unused bits)".to_owned())?;
+
+    let verify = |cert: &Certificate| {
+        signature::verify(
+            tbs,
+            &basic.signature_algorithm,
+            signature,
+            cert.tbs_certificate().subject_public_key_info(),
+        )
+    };
+
+    let issuer_err = match verify(issuer) {
+        Ok(()) => return Ok(Signer::Issuer { issuer_idx }),
+        Err(err) => err,
+    };
+
+    for responder in basic.certs.iter().flatten() {
+        if verify(responder).is_err() {
+            continue;
+        }
+
+        let responder_name = responder.tbs_certificate().subject().to_string();
+
+        if let Err(err) = signature::verify_cert(responder, issuer) {
+            return Err(format!(
+                "INVALID (delegated responder {responder_name} not issued by certificate #{issuer_idx}: {})",
+                err.reason()
+            ));
+        }
+
+        let is_ocsp_signer = responder
+            .tbs_certificate()
+            .get_extension::<ExtendedKeyUsage>()
+            .ok()
+            .flatten()
+            .is_some_and(|(_, eku)| eku.0.contains(&ID_KP_OCSP_SIGNING));
+
+        if !is_ocsp_signer {
+            return Err(format!(
+                "INVALID (delegated responder {responder_name} lacks {} extended key usage)",
+                util::oid_desc_or_raw(&ID_KP_OCSP_SIGNING)
+            ));
+        }
+
+        return Ok(Signer::Delegated {
+            responder: responder_name,
+            issuer_idx,
+        });
+    }
+
+    Err(match issuer_err {
+        signature::Error::Invalid(reason) => format!("INVALID ({reason})"),
+        signature::Error::Unsupported(reason) => format!("unchecked ({reason})"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cert::fixtures;
+
+    fn inspect_fixture(response: &str, chain: &[&str]) -> Response {
+        let der = fixtures::read(&format!("pki/ocsp-{response}.der"));
+        inspect(&der, &fixtures::chain(chain)).unwrap()
+    }
+
+    #[test]
+    fn good_response() {
+        let res = inspect_fixture("good", &["pki/good.pem", "pki/ca.pem"]);
+
+        assert_eq!(res.responses.len(), 1);
+        assert_eq!(res.responses[0].cert_idx, Some(0));
+        assert!(matches!(res.responses[0].status, Status::Good));
+        assert!(matches!(
+            res.signature,
+            Ok(Signer::Issuer { issuer_idx: 1 })
+        ));
+    }
+
+    #[test]
+    fn revoked_response() {
+        let res = inspect_fixture("revoked", &["pki/revoked.pem", "pki/ca.pem"]);
+
+        assert_eq!(res.responses[0].cert_idx, Some(0));
+        assert!(matches!(
+            res.responses[0].status,
+            Status::Revoked {
+                reason: Some(CrlReason::KeyCompromise),
+                ..
+            }
+        ));
+        assert!(res.signature.is_ok());
+    }
+
+    #[test]
+    fn unknown_response() {
+        let res = inspect_fixture("unknown", &["pki/unknown.pem", "pki/ca.pem"]);
+
+        assert!(matches!(res.responses[0].status, Status::Unknown));
+        assert!(res.signature.is_ok());
+    }
+
+    #[test]
+    fn response_for_other_certificate() {
+        let res = inspect_fixture("good", &["pki/revoked.pem", "pki/ca.pem"]);
+
+        assert_eq!(res.responses[0].cert_idx, None);
+        assert!(res.signature.unwrap_err().starts_with("unchecked"));
+    }
+
+    #[test]
+    fn issuer_key_hash_requires_issuer() {
+        let res = inspect_fixture("good", &["pki/good.pem"]);
+
+        assert_eq!(res.responses[0].cert_idx, None);
+    }
+
+    #[test]
+    fn modified_response_is_invalid() {
+        let mut der = fixtures::read("pki/ocsp-good.der");
+
+        // change the seconds of producedAt, the first GeneralizedTime in the response
+        let pos = der.windows(2).position(|tl| tl == [0x18, 0x0f]).unwrap() + 2 + 13;
+        der[pos] = if der[pos] == b'0' { b'1' } else { b'0' };
+
+        let res = inspect(&der, &fixtures::chain(&["pki/good.pem", "pki/ca.pem"])).unwrap();
+
+        assert!(res.signature.unwrap_err().starts_with("INVALID"));
+    }
+}
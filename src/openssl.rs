@@ -0,0 +1,1040 @@
+//! Rendering of certificates in the layout of `openssl x509 -text -noout`.
+//!
+//! Output follows OpenSSL 3.x, including its object names, so that tooling written against
+//! OpenSSL's output keeps working. Extensions which are not decoded are printed as OpenSSL prints
+//! unsupported extensions.
+
+use std::{
+    fmt::{self, Write as _},
+    net::IpAddr,
+};
+
+use itertools::Itertools as _;
+
+use crate::{
+    ext::{self, GeneralNameInfo, QualifierValue},
+    model::{CertInfo, DistinguishedName, Exponent, KeyParams, Oid, PublicKey},
+};
+
+/// Object identifiers known to OpenSSL, with their short and long names.
+const OBJECTS: &[(&str, &str, &str)] = &[
+    // distinguished name attributes
+    ("2.5.4.3", "CN", "commonName"),
+    ("2.5.4.4", "SN", "surname"),
+    ("2.5.4.5", "serialNumber", "serialNumber"),
+    ("2.5.4.6", "C", "countryName"),
+    ("2.5.4.7", "L", "localityName"),
+    ("2.5.4.8", "ST", "stateOrProvinceName"),
+    ("2.5.4.9", "street", "streetAddress"),
+    ("2.5.4.10", "O", "organizationName"),
+    ("2.5.4.11", "OU", "organizationalUnitName"),
+    ("2.5.4.12", "title", "title"),
+    ("2.5.4.13", "description", "description"),
+    ("2.5.4.15", "businessCategory", "businessCategory"),
+    ("2.5.4.17", "postalCode", "postalCode"),
+    ("2.5.4.41", "name", "name"),
+    ("2.5.4.42", "GN", "givenName"),
+    ("2.5.4.43", "initials", "initials"),
+    ("2.5.4.44", "generationQualifier", "generationQualifier"),
+    ("2.5.4.46", "dnQualifier", "dnQualifier"),
+    ("2.5.4.65", "pseudonym", "pseudonym"),
+    (
+        "2.5.4.97",
+        "organizationIdentifier",
+        "organizationIdentifier",
+    ),
+    ("1.2.840.113549.1.9.1", "emailAddress", "emailAddress"),
+    ("0.9.2342.19200300.100.1.1", "UID", "userId"),
+    ("0.9.2342.19200300.100.1.25", "DC", "domainComponent"),
+    (
+        "1.3.6.1.4.1.311.60.2.1.1",
+        "jurisdictionL",
+        "jurisdictionLocalityName",
+    ),
+    (
+        "1.3.6.1.4.1.311.60.2.1.2",
+        "jurisdictionST",
+        "jurisdictionStateOrProvinceName",
+    ),
+    (
+        "1.3.6.1.4.1.311.60.2.1.3",
+        "jurisdictionC",
+        "jurisdictionCountryName",
+    ),
+    // key and signature algorithms
+    ("1.2.840.113549.1.1.1", "rsaEncryption", "rsaEncryption"),
+    ("1.2.840.113549.1.1.4", "RSA-MD5", "md5WithRSAEncryption"),
+    ("1.2.840.113549.1.1.5", "RSA-SHA1", "sha1WithRSAEncryption"),
+    ("1.2.840.113549.1.1.10", "RSASSA-PSS", "rsassaPss"),
+    (
+        "1.2.840.113549.1.1.11",
+        "RSA-SHA256",
+        "sha256WithRSAEncryption",
+    ),
+    (
+        "1.2.840.113549.1.1.12",
+        "RSA-SHA384",
+        "sha384WithRSAEncryption",
+    ),
+    (
+        "1.2.840.113549.1.1.13",
+        "RSA-SHA512",
+        "sha512WithRSAEncryption",
+    ),
+    (
+        "1.2.840.113549.1.1.14",
+        "RSA-SHA224",
+        "sha224WithRSAEncryption",
+    ),
+    ("1.2.840.10040.4.1", "DSA", "dsaEncryption"),
+    ("1.2.840.10040.4.3", "DSA-SHA1", "dsaWithSHA1"),
+    ("1.2.840.10045.2.1", "id-ecPublicKey", "id-ecPublicKey"),
+    ("1.2.840.10045.4.1", "ecdsa-with-SHA1", "ecdsa-with-SHA1"),
+    (
+        "1.2.840.10045.4.3.1",
+        "ecdsa-with-SHA224",
+        "ecdsa-with-SHA224",
+    ),
+    (
+        "1.2.840.10045.4.3.2",
+        "ecdsa-with-SHA256",
+        "ecdsa-with-SHA256",
+    ),
+    (
+        "1.2.840.10045.4.3.3",
+        "ecdsa-with-SHA384",
+        "ecdsa-with-SHA384",
+    ),
+    (
+        "1.2.840.10045.4.3.4",
+        "ecdsa-with-SHA512",
+        "ecdsa-with-SHA512",
+    ),
+    ("1.3.101.112", "ED25519", "ED25519"),
+    ("1.3.101.113", "ED448", "ED448"),
+    // elliptic curves
+    ("1.2.840.10045.3.1.1", "prime192v1", "prime192v1"),
+    ("1.2.840.10045.3.1.7", "prime256v1", "prime256v1"),
+    ("1.3.132.0.10", "secp256k1", "secp256k1"),
+    ("1.3.132.0.33", "secp224r1", "secp224r1"),
+    ("1.3.132.0.34", "secp384r1", "secp384r1"),
+    ("1.3.132.0.35", "secp521r1", "secp521r1"),
+    ("1.3.36.3.3.2.8.1.1.7", "brainpoolP256r1", "brainpoolP256r1"),
+    (
+        "1.3.36.3.3.2.8.1.1.11",
+        "brainpoolP384r1",
+        "brainpoolP384r1",
+    ),
+    (
+        "1.3.36.3.3.2.8.1.1.13",
+        "brainpoolP512r1",
+        "brainpoolP512r1",
+    ),
+    // extensions
+    (
+        "2.5.29.9",
+        "subjectDirectoryAttributes",
+        "X509v3 Subject Directory Attributes",
+    ),
+    (
+        "2.5.29.14",
+        "subjectKeyIdentifier",
+        "X509v3 Subject Key Identifier",
+    ),
+    ("2.5.29.15", "keyUsage", "X509v3 Key Usage"),
+    (
+        "2.5.29.16",
+        "privateKeyUsagePeriod",
+        "X509v3 Private Key Usage Period",
+    ),
+    (
+        "2.5.29.17",
+        "subjectAltName",
+        "X509v3 Subject Alternative Name",
+    ),
+    (
+        "2.5.29.18",
+        "issuerAltName",
+        "X509v3 Issuer Alternative Name",
+    ),
+    ("2.5.29.19", "basicConstraints", "X509v3 Basic Constraints"),
+    ("2.5.29.30", "nameConstraints", "X509v3 Name Constraints"),
+    (
+        "2.5.29.31",
+        "crlDistributionPoints",
+        "X509v3 CRL Distribution Points",
+    ),
+    (
+        "2.5.29.32",
+        "certificatePolicies",
+        "X509v3 Certificate Policies",
+    ),
+    ("2.5.29.33", "policyMappings", "X509v3 Policy Mappings"),
+    (
+        "2.5.29.35",
+        "authorityKeyIdentifier",
+        "X509v3 Authority Key Identifier",
+    ),
+    (
+        "2.5.29.36",
+        "policyConstraints",
+        "X509v3 Policy Constraints",
+    ),
+    ("2.5.29.37", "extendedKeyUsage", "X509v3 Extended Key Usage"),
+    ("2.5.29.46", "freshestCRL", "X509v3 Freshest CRL"),
+    ("2.5.29.54", "inhibitAnyPolicy", "X509v3 Inhibit Any Policy"),
+    (
+        "1.3.6.1.5.5.7.1.1",
+        "authorityInfoAccess",
+        "Authority Information Access",
+    ),
+    (
+        "1.3.6.1.5.5.7.1.11",
+        "subjectInfoAccess",
+        "Subject Information Access",
+    ),
+    ("1.3.6.1.5.5.7.1.24", "tlsfeature", "TLS Feature"),
+    (
+        "1.3.6.1.4.1.11129.2.4.2",
+        "ct_precert_scts",
+        "CT Precertificate SCTs",
+    ),
+    (
+        "1.3.6.1.4.1.11129.2.4.3",
+        "ct_precert_poison",
+        "CT Precertificate Poison",
+    ),
+    ("2.16.840.1.113730.1.1", "nsCertType", "Netscape Cert Type"),
+    ("2.16.840.1.113730.1.13", "nsComment", "Netscape Comment"),
+    ("2.23.42.7.0", "setCext-hashedRoot", "setCext-hashedRoot"),
+    // extended key usages
+    (
+        "1.3.6.1.5.5.7.3.1",
+        "serverAuth",
+        "TLS Web Server Authentication",
+    ),
+    (
+        "1.3.6.1.5.5.7.3.2",
+        "clientAuth",
+        "TLS Web Client Authentication",
+    ),
+    ("1.3.6.1.5.5.7.3.3", "codeSigning", "Code Signing"),
+    ("1.3.6.1.5.5.7.3.4", "emailProtection", "E-mail Protection"),
+    ("1.3.6.1.5.5.7.3.5", "ipsecEndSystem", "IPSec End System"),
+    ("1.3.6.1.5.5.7.3.6", "ipsecTunnel", "IPSec Tunnel"),
+    ("1.3.6.1.5.5.7.3.7", "ipsecUser", "IPSec User"),
+    ("1.3.6.1.5.5.7.3.8", "timeStamping", "Time Stamping"),
+    ("1.3.6.1.5.5.7.3.9", "OCSPSigning", "OCSP Signing"),
+    (
+        "1.3.6.1.5.5.7.3.17",
+        "ipsecIKE",
+        "ipsec Internet Key Exchange",
+    ),
+    (
+        "2.5.29.37.0",
+        "anyExtendedKeyUsage",
+        "Any Extended Key Usage",
+    ),
+    (
+        "1.3.6.1.4.1.311.2.1.21",
+        "msCodeInd",
+        "Microsoft Individual Code Signing",
+    ),
+    (
+        "1.3.6.1.4.1.311.2.1.22",
+        "msCodeCom",
+        "Microsoft Commercial Code Signing",
+    ),
+    (
+        "1.3.6.1.4.1.311.10.3.1",
+        "msCTLSign",
+        "Microsoft Trust List Signing",
+    ),
+    (
+        "1.3.6.1.4.1.311.10.3.3",
+        "msSGC",
+        "Microsoft Server Gated Crypto",
+    ),
+    (
+        "1.3.6.1.4.1.311.10.3.4",
+        "msEFS",
+        "Microsoft Encrypted File System",
+    ),
+    (
+        "1.3.6.1.4.1.311.20.2.2",
+        "msSmartcardLogin",
+        "Microsoft Smartcard Login",
+    ),
+    (
+        "2.16.840.1.113730.4.1",
+        "nsSGC",
+        "Netscape Server Gated Crypto",
+    ),
+    // access methods, policies and qualifiers
+    ("1.3.6.1.5.5.7.48.1", "OCSP", "OCSP"),
+    ("1.3.6.1.5.5.7.48.2", "caIssuers", "CA Issuers"),
+    ("2.5.29.32.0", "anyPolicy", "X509v3 Any Policy"),
+    ("1.3.6.1.5.5.7.2.1", "id-qt-cps", "Policy Qualifier CPS"),
+    (
+        "1.3.6.1.5.5.7.2.2",
+        "id-qt-unotice",
+        "Policy Qualifier User Notice",
+    ),
+];
+
+/// Named curves with their size in bits and NIST name, if any.
+const CURVES: &[(&str, usize, Option<&str>)] = &[
+    ("1.2.840.10045.3.1.1", 192, Some("P-192")),
+    ("1.2.840.10045.3.1.7", 256, Some("P-256")),
+    ("1.3.132.0.10", 256, None),
+    ("1.3.132.0.33", 224, Some("P-224")),
+    ("1.3.132.0.34", 384, Some("P-384")),
+    ("1.3.132.0.35", 521, Some("P-521")),
+    ("1.3.36.3.3.2.8.1.1.7", 256, None),
+    ("1.3.36.3.3.2.8.1.1.11", 384, None),
+    ("1.3.36.3.3.2.8.1.1.13", 512, None),
+];
+
+/// Renders certificate as `openssl x509 -text -noout` does.
+pub(crate) fn render(info: &CertInfo) -> String {
+    let mut out = String::new();
+    write_cert(info, &mut out).expect("writing to a string cannot fail");
+    out
+}
+
+fn write_cert(info: &CertInfo, out: &mut String) -> fmt::Result {
+    writeln!(out, "Certificate:")?;
+    writeln!(out, "    Data:")?;
+    writeln!(
+        out,
+        "        Version: {} (0x{:x})",
+        info.version,
+        info.version.saturating_sub(1)
+    )?;
+    writeln!(
+        out,
+        "        Serial Number:{}",
+        fmt_serial(&info.serial_number.0)
+    )?;
+    writeln!(
+        out,
+        "        Signature Algorithm: {}",
+        long_name(&info.signature_algorithm)
+    )?;
+    writeln!(out, "        Issuer: {}", fmt_name(&info.issuer))?;
+    writeln!(out, "        Validity")?;
+    writeln!(
+        out,
+        "            Not Before: {}",
+        fmt_time(info.validity.not_before.unix_time)
+    )?;
+    writeln!(
+        out,
+        "            Not After : {}",
+        fmt_time(info.validity.not_after.unix_time)
+    )?;
+    writeln!(out, "        Subject: {}", fmt_name(&info.subject))?;
+
+    writeln!(out, "        Subject Public Key Info:")?;
+    write_public_key(&info.public_key, out)?;
+
+    if let Some(id) = &info.issuer_unique_id {
+        writeln!(out, "        Issuer Unique ID: ")?;
+        writeln!(
+            out,
+            "            {}",
+            hex_lines(&id.0, 18, false).join("\n            ")
+        )?;
+    }
+
+    if !info.extensions.is_empty() {
+        writeln!(out, "        X509v3 extensions:")?;
+
+        for ext in &info.extensions {
+            writeln!(
+                out,
+                "            {}: {}",
+                long_name(&ext.oid),
+                if ext.critical { "critical" } else { "" }
+            )?;
+            writeln!(out, "{}", ext_value_lines(&ext.value).join("\n"))?;
+        }
+    }
+
+    writeln!(
+        out,
+        "    Signature Algorithm: {}",
+        long_name(&info.signature_algorithm)
+    )?;
+    writeln!(out, "    Signature Value:")?;
+    writeln!(
+        out,
+        "        {}",
+        hex_lines(&info.signature.value.0, 18, false).join("\n        ")
+    )
+}
+
+fn write_public_key(key: &PublicKey, out: &mut String) -> fmt::Result {
+    let algorithm = long_name(&key.algorithm);
+    writeln!(out, "            Public Key Algorithm: {algorithm}")?;
+
+    match &key.params {
+        KeyParams::Ec { curve, point } => {
+            let (bits, nist) = CURVES.iter().find(|(oid, _, _)| *oid == curve.oid).map_or(
+                ((point.0.len().saturating_sub(1)) / 2 * 8, None),
+                |&(_, bits, nist)| (bits, nist),
+            );
+
+            writeln!(out, "                Public-Key: ({bits} bit)")?;
+            writeln!(out, "                pub:")?;
+            writeln!(
+                out,
+                "                    {}",
+                hex_lines(&point.0, 15, false).join("\n                    ")
+            )?;
+            writeln!(out, "                ASN1 OID: {}", short_name(curve))?;
+
+            if let Some(nist) = nist {
+                writeln!(out, "                NIST CURVE: {nist}")?;
+            }
+        }
+
+        KeyParams::Rsa {
+            exponent, modulus, ..
+        } => {
+            let modulus = strip_leading_zeros(&modulus.0);
+            let bits = (modulus.len() * 8).saturating_sub(
+                modulus
+                    .first()
+                    .map_or(0, |byte| byte.leading_zeros() as usize),
+            );
+
+            writeln!(out, "                Public-Key: ({bits} bit)")?;
+            writeln!(out, "                Modulus:")?;
+            writeln!(
+                out,
+                "                    {}",
+                hex_lines(&sign_padded(modulus), 15, false).join("\n                    ")
+            )?;
+
+            match exponent {
+                Exponent::Small(exponent) => {
+                    writeln!(out, "                Exponent: {exponent} (0x{exponent:x})")?;
+                }
+                Exponent::Large(exponent) => {
+                    writeln!(out, "                Exponent:")?;
+                    writeln!(
+                        out,
+                        "                    {}",
+                        hex_lines(&sign_padded(strip_leading_zeros(&exponent.0)), 15, false)
+                            .join("\n                    ")
+                    )?;
+                }
+            }
+        }
+
+        KeyParams::Other { key: pub_key } if matches!(algorithm, "ED25519" | "ED448") => {
+            writeln!(out, "                {algorithm} Public-Key:")?;
+            writeln!(out, "                pub:")?;
+            writeln!(
+                out,
+                "                    {}",
+                hex_lines(&pub_key.0, 15, false).join("\n                    ")
+            )?;
+        }
+
+        KeyParams::Other { .. } => {
+            writeln!(out, "            Unable to load Public Key")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns extension value lines, indented as OpenSSL does.
+fn ext_value_lines(value: &ext::Value) -> Vec<String> {
+    const INDENT: &str = "                ";
+
+    match value {
+        ext::Value::SubjectKeyIdentifier { key_id } => {
+            vec![format!("{INDENT}{}", fmt_hex_upper(&key_id.0))]
+        }
+
+        ext::Value::SubjectAltName { names } => {
+            vec![format!(
+                "{INDENT}{}",
+                names.iter().map(fmt_name_value).join(", ")
+            )]
+        }
+
+        ext::Value::CertificatePolicies { policies } => {
+            let mut lines = Vec::new();
+
+            for info in policies {
+                lines.push(format!("{INDENT}Policy: {}", long_name(&info.policy)));
+
+                for qualifier in info.qualifiers.iter().flatten() {
+                    match &qualifier.value {
+                        QualifierValue::Cps { uri } => lines.push(format!("{INDENT}  CPS: {uri}")),
+
+                        QualifierValue::UserNotice {
+                            organization,
+                            notice_numbers,
+                            explicit_text,
+                        } => {
+                            lines.push(format!("{INDENT}  User Notice:"));
+
+                            if let Some(organization) = organization {
+                                let numbers = notice_numbers.as_deref().unwrap_or_default();
+
+                                lines.push(format!("{INDENT}    Organization: {organization}"));
+                                lines.push(format!(
+                                    "{INDENT}    Number{}: {}",
+                                    if numbers.len() > 1 { "s" } else { "" },
+                                    numbers.iter().join(", ")
+                                ));
+                            }
+
+                            if let Some(text) = explicit_text {
+                                lines.push(format!("{INDENT}    Explicit Text: {text}"));
+                            }
+                        }
+
+                        QualifierValue::Unknown { .. } => lines.push(format!(
+                            "{INDENT}  Unknown Qualifier: {}",
+                            long_name(&qualifier.oid)
+                        )),
+                    }
+                }
+            }
+
+            lines
+        }
+
+        ext::Value::BasicConstraints {
+            ca,
+            path_len_constraint,
+        } => {
+            let ca = if *ca { "TRUE" } else { "FALSE" };
+
+            match path_len_constraint {
+                Some(path_len) => vec![format!("{INDENT}CA:{ca}, pathlen:{path_len}")],
+                None => vec![format!("{INDENT}CA:{ca}")],
+            }
+        }
+
+        ext::Value::AuthorityInfoAccess { descriptions } => descriptions
+            .iter()
+            .map(|desc| {
+                format!(
+                    "{INDENT}{} - {}",
+                    long_name(&desc.method),
+                    fmt_name_value(&desc.location)
+                )
+            })
+            .collect(),
+
+        ext::Value::KeyUsage { usages } => {
+            let mut usages = usages.iter().map(|usage| match usage.as_str() {
+                "DigitalSignature" => "Digital Signature",
+                "NonRepudiation" => "Non Repudiation",
+                "KeyEncipherment" => "Key Encipherment",
+                "DataEncipherment" => "Data Encipherment",
+                "KeyAgreement" => "Key Agreement",
+                "KeyCertSign" => "Certificate Sign",
+                "CRLSign" => "CRL Sign",
+                "EncipherOnly" => "Encipher Only",
+                "DecipherOnly" => "Decipher Only",
+                other => other,
+            });
+
+            vec![format!("{INDENT}{}", usages.join(", "))]
+        }
+
+        ext::Value::ExtendedKeyUsage { usages } => {
+            vec![format!(
+                "{INDENT}{}",
+                usages.iter().map(long_name).join(", ")
+            )]
+        }
+
+        ext::Value::AuthorityKeyIdentifier {
+            key_id,
+            issuer,
+            serial,
+        } => {
+            let mut lines = Vec::new();
+
+            if let Some(key_id) = key_id {
+                let label = if issuer.is_some() || serial.is_some() {
+                    "keyid:"
+                } else {
+                    ""
+                };
+
+                lines.push(format!("{INDENT}{label}{}", fmt_hex_upper(&key_id.0)));
+            }
+
+            for name in issuer.iter().flatten() {
+                lines.push(format!("{INDENT}{}", fmt_name_value(name)));
+            }
+
+            if let Some(serial) = serial {
+                lines.push(format!(
+                    "{INDENT}serial:{}",
+                    fmt_hex_upper(strip_leading_zeros(&serial.0))
+                ));
+            }
+
+            lines
+        }
+
+        ext::Value::CrlDistributionPoints { points } => {
+            let mut lines = Vec::new();
+
+            for point in points {
+                if let Some(names) = &point.full_name {
+                    lines.push(format!("{INDENT}Full Name:"));
+                    lines.extend(
+                        names
+                            .iter()
+                            .map(|name| format!("{INDENT}  {}", fmt_name_print(name))),
+                    );
+                }
+
+                if let Some(name) = &point.relative_name {
+                    lines.push(format!("{INDENT}Relative Name:"));
+                    lines.push(format!("{INDENT}  {name}"));
+                }
+
+                if let Some(reasons) = &point.reasons {
+                    lines.push(format!("{INDENT}Reasons:"));
+                    lines.push(format!(
+                        "{INDENT}  {}",
+                        if reasons.is_empty() {
+                            "<EMPTY>".to_owned()
+                        } else {
+                            reasons.iter().map(|reason| fmt_reason(reason)).join(", ")
+                        }
+                    ));
+                }
+
+                if let Some(names) = &point.crl_issuer {
+                    lines.push(format!("{INDENT}CRL Issuer:"));
+                    lines.extend(
+                        names
+                            .iter()
+                            .map(|name| format!("{INDENT}  {}", fmt_name_print(name))),
+                    );
+                }
+
+                // OpenSSL ends each distribution point with a newline of its own
+                lines.push(String::new());
+            }
+
+            lines
+        }
+
+        ext::Value::SctList { scts } => scts.iter().flat_map(sct_lines).collect(),
+
+        ext::Value::NameConstraints {
+            permitted,
+            excluded,
+        } => {
+            let mut lines = Vec::new();
+
+            for (label, subtrees) in [("Permitted", permitted), ("Excluded", excluded)] {
+                let Some(subtrees) = subtrees.as_deref().filter(|trees| !trees.is_empty()) else {
+                    continue;
+                };
+
+                lines.push(format!("{INDENT}{label}:"));
+                lines.extend(
+                    subtrees
+                        .iter()
+                        .map(|subtree| format!("{INDENT}  {}", fmt_subtree_base(&subtree.base))),
+                );
+            }
+
+            lines
+        }
+
+        ext::Value::PolicyConstraints {
+            require_explicit_policy,
+            inhibit_policy_mapping,
+        } => {
+            let values = [
+                ("Require Explicit Policy", require_explicit_policy),
+                ("Inhibit Policy Mapping", inhibit_policy_mapping),
+            ];
+
+            vec![format!(
+                "{INDENT}{}",
+                values
+                    .iter()
+                    .filter_map(|(label, skip_certs)| Some(format!(
+                        "{label}:{}",
+                        skip_certs.as_ref()?
+                    )))
+                    .join(", ")
+            )]
+        }
+
+        ext::Value::PolicyMappings { mappings } => {
+            vec![format!(
+                "{INDENT}{}",
+                mappings
+                    .iter()
+                    .map(|mapping| format!(
+                        "{}:{}",
+                        long_name(&mapping.issuer_domain_policy),
+                        long_name(&mapping.subject_domain_policy)
+                    ))
+                    .join(", ")
+            )]
+        }
+
+        ext::Value::InhibitAnyPolicy { skip_certs } => vec![format!("{INDENT}{skip_certs}")],
+
+        ext::Value::PrivateKeyUsagePeriod {
+            not_before,
+            not_after,
+        } => vec![format!(
+            "{INDENT}{}",
+            [("Not Before", not_before), ("Not After", not_after)]
+                .into_iter()
+                .filter_map(|(label, time)| Some(format!(
+                    "{label}: {}",
+                    fmt_time(time.as_ref()?.unix_time)
+                )))
+                .join(", ")
+        )],
+
+        ext::Value::NetscapeCertType { types } => vec![format!("{INDENT}{}", types.join(", "))],
+
+        ext::Value::Unknown { der } | ext::Value::Malformed { der, .. } => {
+            vec![format!("{INDENT}{}", fmt_raw_string(&der.0))]
+        }
+    }
+}
+
+fn sct_lines(sct: &ext::Sct) -> Vec<String> {
+    const INDENT: &str = "                    ";
+    const CONT: &str = "\n                                ";
+
+    let timestamp = chrono::DateTime::from_timestamp_millis(sct.unix_time_ms as i64)
+        .map(|ts| ts.format("%b %e %H:%M:%S%.3f %Y GMT").to_string())
+        .unwrap_or_default();
+
+    let extensions = if sct.extensions.0.is_empty() {
+        "none".to_owned()
+    } else {
+        hex_lines(&sct.extensions.0, 16, true).join(CONT)
+    };
+
+    vec![
+        "                Signed Certificate Timestamp:".to_owned(),
+        format!(
+            "{INDENT}Version   : {}",
+            if sct.version == "V1" {
+                "v1 (0x0)"
+            } else {
+                "unknown"
+            }
+        ),
+        format!(
+            "{INDENT}Log ID    : {}",
+            hex_lines(&sct.log_id.0, 16, true).join(CONT)
+        ),
+        format!("{INDENT}Timestamp : {timestamp}"),
+        format!("{INDENT}Extensions: {extensions}"),
+        format!(
+            "{INDENT}Signature : {}",
+            fmt_sct_signature_alg(&sct.signature_algorithm)
+        ),
+        format!(
+            "                                {}",
+            hex_lines(&sct.signature.0, 16, true).join(CONT)
+        ),
+    ]
+}
+
+/// Converts an SCT signature algorithm (e.g., "ECDSA-with-SHA256") to OpenSSL's name for it.
+fn fmt_sct_signature_alg(alg: &str) -> String {
+    match alg.split_once("-with-") {
+        Some(("ECDSA", hash)) => format!("ecdsa-with-{hash}"),
+        Some(("RSA", hash)) => format!("{}WithRSAEncryption", hash.to_ascii_lowercase()),
+        _ => alg.to_owned(),
+    }
+}
+
+fn fmt_reason(reason: &str) -> &str {
+    match reason {
+        "KeyCompromise" => "Key Compromise",
+        "CaCompromise" => "CA Compromise",
+        "AffiliationChanged" => "Affiliation Changed",
+        "CessationOfOperation" => "Cessation Of Operation",
+        "CertificateHold" => "Certificate Hold",
+        "PrivilegeWithdrawn" => "Privilege Withdrawn",
+        "AaCompromise" => "AA Compromise",
+        other => other,
+    }
+}
+
+/// Formats serial number, in decimal and hex when it fits in a (signed) 64-bit integer.
+fn fmt_serial(serial: &[u8]) -> String {
+    let negative = serial.first().is_some_and(|byte| byte & 0x80 != 0);
+
+    let magnitude = if negative {
+        // two's complement negation
+        let mut magnitude = serial.iter().map(|byte| !byte).collect::<Vec<_>>();
+
+        for byte in magnitude.iter_mut().rev() {
+            let (sum, overflow) = byte.overflowing_add(1);
+            *byte = sum;
+
+            if !overflow {
+                break;
+            }
+        }
+
+        magnitude
+    } else {
+        serial.to_vec()
+    };
+
+    let magnitude = strip_leading_zeros(&magnitude);
+    let neg = if negative { "-" } else { "" };
+
+    if magnitude.len() <= 8 {
+        let mut val = 0_u64;
+        for byte in magnitude {
+            val = val << 8 | u64::from(*byte);
+        }
+
+        if val <= i64::MAX as u64 {
+            return format!(" {neg}{val} ({neg}0x{val:x})");
+        }
+    }
+
+    format!(
+        "\n            {}{}",
+        if negative { " (Negative)" } else { "" },
+        magnitude.iter().map(|byte| format!("{byte:02x}")).join(":")
+    )
+}
+
+fn fmt_time(unix_time: u64) -> String {
+    chrono::DateTime::from_timestamp(unix_time as i64, 0)
+        .map(|time| time.format("%b %e %H:%M:%S %Y GMT").to_string())
+        .unwrap_or_default()
+}
+
+/// Formats name as OpenSSL's default `-nameopt` does (e.g., "C=US, O=Org, CN=Name").
+fn fmt_name(name: &DistinguishedName) -> String {
+    name.rdns
+        .iter()
+        .map(|rdn| {
+            rdn.iter()
+                .map(|attr| format!("{}={}", short_name(&attr.oid), attr.value))
+                .join(" + ")
+        })
+        .join(", ")
+}
+
+/// Formats name as `X509_NAME_oneline` does (e.g., "/C=US/O=Org/CN=Name").
+fn fmt_name_slashed(name: &DistinguishedName) -> String {
+    let mut out = String::new();
+
+    for rdn in &name.rdns {
+        for (idx, attr) in rdn.iter().enumerate() {
+            out.push(if idx == 0 { '/' } else { '+' });
+            out.push_str(short_name(&attr.oid));
+            out.push('=');
+
+            for byte in attr.value.bytes() {
+                match byte {
+                    b'/' | b'+' => {
+                        out.push('\\');
+                        out.push(byte as char);
+                    }
+                    b' '..=b'~' => out.push(byte as char),
+                    _ => {
+                        let _ = write!(out, "\\x{byte:02X}");
+                    }
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// Formats name as `XN_FLAG_ONELINE` does (e.g., "C = US, O = Org, CN = Name").
+fn fmt_name_oneline(name: &DistinguishedName) -> String {
+    name.rdns
+        .iter()
+        .map(|rdn| {
+            rdn.iter()
+                .map(|attr| {
+                    format!(
+                        "{} = {}",
+                        short_name(&attr.oid),
+                        escape_oneline(&attr.value)
+                    )
+                })
+                .join(" + ")
+        })
+        .join(", ")
+}
+
+/// Escapes attribute value as OpenSSL's RFC 2253 flags with quoting do.
+fn escape_oneline(value: &str) -> String {
+    let needs_quotes = value.starts_with(['#', ' '])
+        || value.ends_with(' ')
+        || value.contains([',', '+', '"', '\\', '<', '>', ';']);
+
+    let mut out = String::with_capacity(value.len() + 2);
+
+    if needs_quotes {
+        out.push('"');
+    }
+
+    for byte in value.bytes() {
+        match byte {
+            b'"' | b'\\' => {
+                out.push('\\');
+                out.push(byte as char);
+            }
+            b' '..=b'~' => out.push(byte as char),
+            _ => {
+                let _ = write!(out, "\\{byte:02X}");
+            }
+        }
+    }
+
+    if needs_quotes {
+        out.push('"');
+    }
+
+    out
+}
+
+/// Formats general name as OpenSSL's extension value lists do.
+fn fmt_name_value(name: &GeneralNameInfo) -> String {
+    match name {
+        GeneralNameInfo::Directory(dir) => format!("DirName:{}", fmt_name_slashed(dir)),
+        _ => fmt_name_print(name),
+    }
+}
+
+/// Formats general name as `GENERAL_NAME_print` does.
+fn fmt_name_print(name: &GeneralNameInfo) -> String {
+    match name {
+        GeneralNameInfo::Other { .. } => "othername:<unsupported>".to_owned(),
+        GeneralNameInfo::Rfc822(email) => format!("email:{email}"),
+        GeneralNameInfo::Dns(dns) => format!("DNS:{dns}"),
+        GeneralNameInfo::Directory(dir) => format!("DirName:{}", fmt_name_oneline(dir)),
+        GeneralNameInfo::EdiParty { .. } => "EdiPartyName:<unsupported>".to_owned(),
+        GeneralNameInfo::Uri(uri) => format!("URI:{uri}"),
+        GeneralNameInfo::Ip(ip) => format!("IP Address:{}", fmt_ip(ip)),
+        GeneralNameInfo::RegisteredId(id) => format!("Registered ID:{}", long_name(id)),
+    }
+}
+
+/// Formats name constraint subtree base as OpenSSL does, printing IP ranges as `IP:<addr>/<mask>`.
+fn fmt_subtree_base(name: &GeneralNameInfo) -> String {
+    match name {
+        GeneralNameInfo::Ip(range) => match range.split_once('/') {
+            Some((addr, mask)) => format!("IP:{}/{}", fmt_ip(addr), fmt_ip(mask)),
+            None => format!("IP:{}", fmt_ip(range)),
+        },
+        _ => fmt_name_print(name),
+    }
+}
+
+/// Formats IP address as OpenSSL does, printing IPv6 addresses in full without zero compression.
+fn fmt_ip(ip: &str) -> String {
+    match ip.parse::<IpAddr>() {
+        Ok(IpAddr::V6(ip)) => ip.segments().iter().map(|seg| format!("{seg:X}")).join(":"),
+        _ => ip.to_owned(),
+    }
+}
+
+/// Formats bytes as `ASN1_STRING_print` does, replacing unprintable bytes with dots.
+fn fmt_raw_string(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| match byte {
+            b'\n' | b'\r' | b' '..=b'~' => byte as char,
+            _ => '.',
+        })
+        .collect()
+}
+
+fn fmt_hex_upper(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02X}")).join(":")
+}
+
+/// Formats bytes as colon-separated hex with `width` bytes per line, as OpenSSL's dumps do.
+fn hex_lines(bytes: &[u8], width: usize, upper: bool) -> Vec<String> {
+    let n_lines = bytes.len().div_ceil(width);
+
+    bytes
+        .chunks(width)
+        .enumerate()
+        .map(|(idx, chunk)| {
+            let mut line = chunk
+                .iter()
+                .map(|byte| {
+                    if upper {
+                        format!("{byte:02X}")
+                    } else {
+                        format!("{byte:02x}")
+                    }
+                })
+                .join(":");
+
+            if idx + 1 < n_lines {
+                line.push(':');
+            }
+
+            line
+        })
+        .collect()
+}
+
+fn strip_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|&byte| byte != 0)
+        .unwrap_or(bytes.len().saturating_sub(1));
+
+    &bytes[start..]
+}
+
+/// Prefixes a zero byte to integers with the top bit set, as in DER, which OpenSSL prints.
+fn sign_padded(bytes: &[u8]) -> Vec<u8> {
+    let mut padded = Vec::with_capacity(bytes.len() + 1);
+    if bytes.first().is_some_and(|byte| byte & 0x80 != 0) {
+        padded.push(0);
+    }
+    padded.extend_from_slice(bytes);
+    padded
+}
+
+/// Returns OpenSSL's long name for OID, falling back to dotted notation.
+fn long_name(oid: &Oid) -> &str {
+    OBJECTS
+        .iter()
+        .find(|(raw, _, _)| *raw == oid.oid)
+        .map_or(&oid.oid, |(_, _, long)| long)
+}
+
+/// Returns OpenSSL's short name for OID, falling back to dotted notation.
+fn short_name(oid: &Oid) -> &str {
+    OBJECTS
+        .iter()
+        .find(|(raw, _, _)| *raw == oid.oid)
+        .map_or(&oid.oid, |(_, short, _)| short)
+}
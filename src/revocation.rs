@@ -0,0 +1,343 @@
+use std::{fmt, io};
+
+use const_oid::db::rfc5912::ID_AD_OCSP;
+use crossterm::style::Stylize as _;
+use x509_cert::{
+    Certificate,
+    ext::pkix::{
+        CrlDistributionPoints,
+        crl::CrlReason,
+        name::{DistributionPointName, GeneralName},
+    },
+    time::Time,
+};
+
+use crate::{
+    aia,
+    cert::{Cert, find_issuer, is_self_issued},
+    crl,
+    http::Fetcher,
+    ocsp, util,
+};
+
+/// Revocation status of a certificate, as reported by an OCSP response or CRL.
+#[derive(Debug)]
+pub(crate) enum Status {
+    Good,
+    Revoked {
+        time: Time,
+        reason: Option<CrlReason>,
+    },
+    Unknown,
+}
+
+impl Status {
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Self::Good => "good".to_owned(),
+            Self::Revoked { time, reason } => match reason {
+                Some(reason) => format!("REVOKED at {time} ({reason:?})"),
+                None => format!("REVOKED at {time}"),
+            },
+            Self::Unknown => "unknown".to_owned(),
+        }
+    }
+}
+
+/// Revocation mechanism used for a live lookup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Method {
+    Ocsp,
+    Crl,
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ocsp => "OCSP",
+            Self::Crl => "CRL",
+        })
+    }
+}
+
+/// Outcome of querying one OCSP responder or CRL distribution point.
+#[derive(Debug)]
+pub(crate) struct Lookup {
+    pub(crate) method: Method,
+    pub(crate) url: String,
+    pub(crate) result: Result<Status, String>,
+}
+
+/// Queries the OCSP responders and CRL distribution points named by each certificate in the chain.
+///
+/// Self-signed certificates are skipped since trust anchors cannot be revoked.
+pub(crate) fn check_live(certs: &[Cert], fetcher: &impl Fetcher) -> Vec<Vec<Lookup>> {
+    (0..certs.len())
+        .map(|idx| {
+            if is_self_issued(&certs[idx]) {
+                return Vec::new();
+            }
+
+            let ocsp = aia::access_urls(&certs[idx], ID_AD_OCSP)
+                .into_iter()
+                .map(|url| Lookup {
+                    method: Method::Ocsp,
+                    result: query_ocsp(idx, certs, &url, fetcher),
+                    url,
+                });
+
+            let crl = crl_urls(&certs[idx]).into_iter().map(|url| Lookup {
+                method: Method::Crl,
+                result: query_crl(idx, certs, &url, fetcher),
+                url,
+            });
+
+            ocsp.chain(crl).collect()
+        })
+        .collect()
+}
+
+/// Writes live revocation results for each certificate in text form.
+pub(crate) fn write_report(
+    certs: &[Cert],
+    results: &[Vec<Lookup>],
+    mut wrt: impl io::Write,
+) -> io::Result<()> {
+    writeln!(wrt, "Revocation (Live)")?;
+    writeln!(wrt, "=================")?;
+
+    for (idx, (cert, lookups)) in certs.iter().zip(results).enumerate() {
+        if idx > 0 {
+            writeln!(wrt)?;
+        }
+
+        let tbs = cert.tbs_certificate();
+        writeln!(wrt, "[{idx}] {}", tbs.subject())?;
+
+        if is_self_issued(cert) {
+            writeln!(wrt, "  <self-signed; not checked>")?;
+            continue;
+        }
+
+        if lookups.is_empty() {
+            writeln!(wrt, "  <no OCSP responder or CRL distribution point>")?;
+            continue;
+        }
+
+        for lookup in lookups {
+            let result = match &lookup.result {
+                Ok(status @ Status::Revoked { .. }) => status.describe().red().bold().to_string(),
+                Ok(status @ Status::Good) => status.describe().green().to_string(),
+                Ok(status @ Status::Unknown) => status.describe().yellow().to_string(),
+                Err(err) => format!("{} {err}", "error:".red()),
+            };
+
+            writeln!(wrt, "  {}: {}", lookup.method, lookup.url)?;
+            writeln!(wrt, "    Status: {result}")?;
+        }
+    }
+
+    Ok(())
+}
+
+fn crl_urls(cert: &Certificate) -> Vec<String> {
+    let Ok(Some((_, dps))) = cert
+        .tbs_certificate()
+        .get_extension::<CrlDistributionPoints>()
+    else {
+        return Vec::new();
+    };
+
+    dps.0
+        .iter()
+        .filter_map(|dp| match &dp.distribution_point {
+            Some(DistributionPointName::FullName(names)) => Some(names),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|name| match name {
+            GeneralName::UniformResourceIdentifier(uri) => Some(uri.as_str().to_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn query_ocsp(
+    idx: usize,
+    certs: &[Cert],
+    url: &str,
+    fetcher: &impl Fetcher,
+) -> Result<Status, String> {
+    let issuer_idx = find_issuer(idx, certs).ok_or_else(|| "issuer not in chain".to_owned())?;
+
+    let req = ocsp::build_request(&certs[idx], &certs[issuer_idx])
+        .map_err(|err| format!("failed to build request: {err:#}"))?;
+
+    let der = fetcher
+        .post(url, "application/ocsp-request", &req)
+        .map_err(|err| format!("{err:#}"))?;
+
+    let res = ocsp::inspect(&der, certs).map_err(|err| format!("invalid response: {err:#}"))?;
+
+    let cert_res = res
+        .responses
+        .into_iter()
+        .find(|res| res.cert_idx == Some(idx))
+        .ok_or_else(|| "response does not cover certificate".to_owned())?;
+
+    match res.signature {
+        Ok(signer) => tracing::debug!(%url, "OCSP response signature {}", signer.describe()),
+        Err(reason) => return Err(format!("response signature {reason}")),
+    }
+
+    if let Some(next_update) = cert_res.next_update
+        && let (ago, false) = util::duration_since_now_fmt(next_update)
+    {
+        return Err(format!("response expired at {next_update} ({ago})"));
+    }
+
+    Ok(cert_res.status)
+}
+
+fn query_crl(
+    idx: usize,
+    certs: &[Cert],
+    url: &str,
+    fetcher: &impl Fetcher,
+) -> Result<Status, String> {
+    let data = fetcher.get(url).map_err(|err| format!("{err:#}"))?;
+    let crls = crl::parse(&data, url).map_err(|err| format!("invalid CRL: {err:#}"))?;
+
+    let check = crl::check_cert(idx, certs, &crls);
+
+    if check.crl.is_none() {
+        return Err("CRL does not cover certificate".to_owned());
+    }
+
+    if let Some(err) = check.checks.iter().find_map(|check| {
+        check
+            .result
+            .as_ref()
+            .err()
+            .map(|reason| format!("{}: {reason}", check.name))
+    }) {
+        return Err(err);
+    }
+
+    Ok(check.status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cert::fixtures, http::StubFetcher};
+
+    const OCSP_URL: &str = "http://ocsp.test/";
+    const CRL_URL: &str = "http://crl.test/ca.crl";
+
+    /// Checks the leaf against the test CA with the given OCSP response and CRL.
+    fn check_leaf(leaf: &str, ocsp_response: &str, crl: &str) -> Vec<Lookup> {
+        let certs = fixtures::chain(&[leaf, "pki/ca.pem"]);
+        let fetcher = StubFetcher::default()
+            .with(OCSP_URL, fixtures::read(ocsp_response))
+            .with(CRL_URL, fixtures::read(crl));
+
+        let mut results = check_live(&certs, &fetcher);
+
+        // the root is self-signed and never looked up
+        assert!(results[1].is_empty());
+        assert_eq!(*fetcher.requests.borrow(), [OCSP_URL, CRL_URL]);
+
+        let lookups = results.remove(0);
+        assert_eq!(lookups[0].method, Method::Ocsp);
+        assert_eq!(lookups[0].url, OCSP_URL);
+        assert_eq!(lookups[1].method, Method::Crl);
+        assert_eq!(lookups[1].url, CRL_URL);
+
+        lookups
+    }
+
+    fn assert_err_contains(result: &Result<Status, String>, expected: &str) {
+        let err = result.as_ref().unwrap_err();
+        assert!(
+            err.contains(expected),
+            "{err:?} does not contain {expected:?}"
+        );
+    }
+
+    #[test]
+    fn good() {
+        let lookups = check_leaf("pki/good.pem", "pki/ocsp-good.der", "pki/ca.crl");
+
+        assert!(matches!(lookups[0].result, Ok(Status::Good)));
+        assert!(matches!(lookups[1].result, Ok(Status::Good)));
+    }
+
+    #[test]
+    fn revoked() {
+        let lookups = check_leaf("pki/revoked.pem", "pki/ocsp-revoked.der", "pki/ca.crl");
+
+        for lookup in &lookups {
+            assert!(matches!(
+                lookup.result,
+                Ok(Status::Revoked {
+                    reason: Some(CrlReason::KeyCompromise),
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn unknown() {
+        let lookups = check_leaf("pki/unknown.pem", "pki/ocsp-unknown.der", "pki/ca.crl");
+
+        assert!(matches!(lookups[0].result, Ok(Status::Unknown)));
+        assert!(matches!(lookups[1].result, Ok(Status::Good)));
+    }
+
+    #[test]
+    fn expired_crl() {
+        let lookups = check_leaf("pki/good.pem", "pki/ocsp-good.der", "pki/expired.crl.pem");
+
+        assert!(matches!(lookups[0].result, Ok(Status::Good)));
+        assert_err_contains(&lookups[1].result, "CRL is current");
+    }
+
+    #[test]
+    fn crl_out_of_scope() {
+        let lookups = check_leaf(
+            "pki/good.pem",
+            "pki/ocsp-good.der",
+            "pki/idp-other-dp.crl.pem",
+        );
+
+        assert_err_contains(&lookups[1].result, "CRL does not cover certificate");
+    }
+
+    #[test]
+    fn ocsp_response_for_other_certificate() {
+        let lookups = check_leaf("pki/revoked.pem", "pki/ocsp-good.der", "pki/ca.crl");
+
+        assert_err_contains(&lookups[0].result, "response does not cover certificate");
+    }
+
+    #[test]
+    fn fetch_failure() {
+        let certs = fixtures::chain(&["pki/good.pem", "pki/ca.pem"]);
+        let results = check_live(&certs, &StubFetcher::default());
+
+        assert_err_contains(&results[0][0].result, "HTTP status 404");
+        assert_err_contains(&results[0][1].result, "HTTP status 404");
+    }
+
+    #[test]
+    fn issuer_missing_from_chain() {
+        let certs = fixtures::chain(&["pki/good.pem"]);
+        let fetcher = StubFetcher::default().with(CRL_URL, fixtures::read("pki/ca.crl"));
+        let results = check_live(&certs, &fetcher);
+
+        assert_err_contains(&results[0][0].result, "issuer not in chain");
+        assert_err_contains(&results[0][1].result, "CRL signed by issuer");
+    }
+}
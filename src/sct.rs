@@ -0,0 +1,211 @@
+use std::{fmt, io};
+
+use const_oid::ObjectIdentifier;
+use der::Decode as _;
+use serde::Serialize;
+use x509_cert::{
+    Certificate,
+    ext::{Extension, pkix::sct::SignedCertificateTimestampList},
+};
+
+use crate::{ext, ocsp};
+
+/// OCSP single response extension carrying SCTs (RFC 6962 §3.3).
+const CT_OCSP_SCTS: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.11129.2.4.5");
+
+/// Mechanism through which an SCT was delivered to the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Source {
+    /// Embedded in the leaf certificate's SCT list extension.
+    #[serde(rename = "certificate_extension")]
+    Certificate,
+
+    /// Included in the stapled OCSP response for the leaf certificate.
+    OcspStaple,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Certificate => "Certificate Extension",
+            Self::OcspStaple => "OCSP Staple",
+        })
+    }
+}
+
+/// Signed certificate timestamp along with how it was delivered.
+#[derive(Debug)]
+pub(crate) struct DeliveredSct {
+    pub(crate) source: Source,
+    pub(crate) sct: x509_cert::ext::pkix::sct::SignedCertificateTimestamp,
+}
+
+/// Collects SCTs for the leaf certificate from its extension and the stapled OCSP response.
+///
+/// SCTs delivered in the `signed_certificate_timestamp` TLS extension are not collected because
+/// rustls can neither request the extension nor expose its contents.
+pub(crate) fn collect(leaf: &Certificate, ocsp_response: Option<&[u8]>) -> Vec<DeliveredSct> {
+    let mut scts = Vec::new();
+
+    let cert_exts = leaf.tbs_certificate().extensions().into_iter().flatten();
+    collect_from_exts(cert_exts, Source::Certificate, &mut scts);
+
+    if let Some(basic) = ocsp_response.and_then(|der| ocsp::decode(der).ok()) {
+        let leaf_serial = leaf.tbs_certificate().serial_number();
+
+        let ocsp_exts = basic
+            .tbs_response_data
+            .responses
+            .iter()
+            .filter(|res| res.serial_number() == leaf_serial)
+            .flat_map(|res| res.single_extensions.iter().flatten());
+
+        collect_from_exts(ocsp_exts, Source::OcspStaple, &mut scts);
+    }
+
+    scts
+}
+
+fn collect_from_exts<'a>(
+    exts: impl Iterator<Item = &'a Extension>,
+    source: Source,
+    scts: &mut Vec<DeliveredSct>,
+) {
+    for ext in exts {
+        let is_sct_list = match source {
+            Source::Certificate => ext.extn_id == const_oid::db::rfc6962::CT_PRECERT_SCTS,
+            Source::OcspStaple => ext.extn_id == CT_OCSP_SCTS,
+        };
+
+        if !is_sct_list {
+            continue;
+        }
+
+        let list = match SignedCertificateTimestampList::from_der(ext.extn_value.as_bytes())
+            .map_err(|err| err.to_string())
+            .and_then(|list| list.parse_timestamps().map_err(|err| format!("{err:?}")))
+        {
+            Ok(list) => list,
+            Err(err) => {
+                tracing::warn!("failed to decode SCT list from {source}: {err}");
+                continue;
+            }
+        };
+
+        for serialized in list {
+            match serialized.parse_timestamp() {
+                Ok(sct) => scts.push(DeliveredSct { source, sct }),
+                Err(err) => tracing::warn!("failed to decode SCT from {source}: {err:?}"),
+            }
+        }
+    }
+}
+
+/// Writes SCTs from all delivery methods in text form.
+pub(crate) fn write_report(scts: &[DeliveredSct], mut wrt: impl io::Write) -> io::Result<()> {
+    writeln!(wrt, "Signed Certificate Timestamps")?;
+    writeln!(wrt, "=============================")?;
+
+    for source in [Source::Certificate, Source::OcspStaple] {
+        let count = scts.iter().filter(|sct| sct.source == source).count();
+        writeln!(wrt, "{source}: {count}")?;
+    }
+
+    // see `collect`
+    writeln!(wrt, "TLS Extension: <not collected; unsupported by rustls>")?;
+
+    for sct in scts {
+        writeln!(wrt)?;
+        writeln!(wrt, "Delivered Via: {}", sct.source)?;
+        writeln!(wrt, "    {}", ext::fmt_sct(&sct.sct))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cert::fixtures;
+
+    fn count(scts: &[DeliveredSct], source: Source) -> usize {
+        scts.iter().filter(|sct| sct.source == source).count()
+    }
+
+    #[test]
+    fn certificate_extension() {
+        let certs = fixtures::certs("ct/leaf.pem");
+        let scts = collect(&certs[0], None);
+
+        assert_eq!(count(&scts, Source::Certificate), 5);
+        assert_eq!(count(&scts, Source::OcspStaple), 0);
+    }
+
+    #[test]
+    fn ocsp_staple() {
+        let certs = fixtures::certs("ct/leaf.pem");
+        let staple = fixtures::read("ct/ocsp-scts.der");
+        let scts = collect(&certs[0], Some(&staple));
+
+        // the response carries SCTs for another serial too, and the leaf's single response has
+        // SCTs under the certificate extension OID, which does not apply to OCSP
+        assert_eq!(count(&scts, Source::Certificate), 5);
+        assert_eq!(count(&scts, Source::OcspStaple), 2);
+
+        // certificate SCTs come first
+        assert!(
+            scts[..5]
+                .iter()
+                .all(|sct| sct.source == Source::Certificate)
+        );
+    }
+
+    #[test]
+    fn ocsp_staple_for_other_certificate() {
+        let certs = fixtures::certs("pki/good.pem");
+        let staple = fixtures::read("ct/ocsp-scts.der");
+        let scts = collect(&certs[0], Some(&staple));
+
+        assert!(scts.is_empty());
+    }
+
+    #[test]
+    fn malformed_ocsp_staple() {
+        let certs = fixtures::certs("ct/leaf.pem");
+        let scts = collect(&certs[0], Some(b"not an OCSP response"));
+
+        assert_eq!(count(&scts, Source::Certificate), 5);
+        assert_eq!(count(&scts, Source::OcspStaple), 0);
+    }
+
+    #[test]
+    fn report() {
+        let certs = fixtures::certs("ct/leaf.pem");
+        let staple = fixtures::read("ct/ocsp-scts.der");
+        let scts = collect(&certs[0], Some(&staple));
+
+        let mut out = Vec::new();
+        write_report(&scts, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(
+            out.starts_with(
+                "\
+Signed Certificate Timestamps
+=============================
+Certificate Extension: 5
+OCSP Staple: 2
+TLS Extension: <not collected; unsupported by rustls>
+"
+            ),
+            "{out}"
+        );
+        assert_eq!(
+            out.matches("Delivered Via: Certificate Extension\n")
+                .count(),
+            5
+        );
+        assert_eq!(out.matches("Delivered Via: OCSP Staple\n").count(), 2);
+    }
+}
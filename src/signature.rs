@@ -0,0 +1,245 @@
+use der::{Decode as _, Encode as _, Header, Reader as _, SliceReader, Tag, asn1::AnyRef};
+use rustls_pki_types::SignatureVerificationAlgorithm;
+use serde::Serialize;
+use x509_cert::{
+    Certificate,
+    spki::{AlgorithmIdentifierOwned, SubjectPublicKeyInfoOwned},
+};
+
+use crate::{
+    cert::{Cert, is_self_issued},
+    util,
+};
+
+/// Result of checking a certificate's signature against the key of its issuer.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub(crate) enum Status {
+    /// Signature verified using the key of the certificate at this index in the chain.
+    Valid {
+        #[serde(rename = "issuer_index")]
+        issuer_idx: usize,
+    },
+
+    /// Signature does not verify using the key of the certificate at this index in the chain.
+    Invalid {
+        #[serde(rename = "issuer_index")]
+        issuer_idx: usize,
+        reason: String,
+    },
+
+    /// Signature or key algorithm is not supported.
+    Unsupported {
+        #[serde(rename = "issuer_index")]
+        issuer_idx: usize,
+        reason: String,
+    },
+
+    /// Issuer is not present in the chain so signature could not be checked.
+    NoIssuer,
+}
+
+impl Status {
+    /// Returns a short, human readable, description of status.
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Self::Valid { issuer_idx } => {
+                format!("valid (signed by key of certificate #{issuer_idx})")
+            }
+            Self::Invalid { issuer_idx, reason } => {
+                format!("INVALID (not signed by key of certificate #{issuer_idx}: {reason})")
+            }
+            Self::Unsupported { issuer_idx, reason } => {
+                format!("unchecked (certificate #{issuer_idx}: {reason})")
+            }
+            Self::NoIssuer => "unchecked (issuer not in chain)".to_owned(),
+        }
+    }
+
+    pub(crate) fn is_valid(&self) -> bool {
+        matches!(self, Self::Valid { .. })
+    }
+
+    pub(crate) fn is_invalid(&self) -> bool {
+        matches!(self, Self::Invalid { .. })
+    }
+}
+
+/// Checks that each certificate in chain was signed by the key of the following certificate.
+///
+/// The last certificate is checked against its own key when it is self-issued.
+pub(crate) fn chain_statuses(certs: &[Cert]) -> Vec<Status> {
+    certs
+        .iter()
+        .enumerate()
+        .map(|(idx, cert)| {
+            let issuer_idx = match certs.get(idx + 1) {
+                Some(_) => idx + 1,
+                None if is_self_issued(cert) => idx,
+                None => return Status::NoIssuer,
+            };
+
+            match verify_cert(cert, &certs[issuer_idx]) {
+                Ok(()) => Status::Valid { issuer_idx },
+                Err(Error::Invalid(reason)) => Status::Invalid { issuer_idx, reason },
+                Err(Error::Unsupported(reason)) => Status::Unsupported { issuer_idx, reason },
+            }
+        })
+        .collect()
+}
+
+/// Signature verification failure.
+#[derive(Debug)]
+pub(crate) enum Error {
+    /// Signature did not verify.
+    Invalid(String),
+
+    /// Algorithm combination is not supported or inputs are unavailable.
+    Unsupported(String),
+}
+
+impl Error {
+    pub(crate) fn reason(&self) -> &str {
+        match self {
+            Self::Invalid(reason) | Self::Unsupported(reason) => reason,
+        }
+    }
+}
+
+/// Verifies that `cert` was signed by the key of `issuer`.
+pub(crate) fn verify_cert(cert: &Cert, issuer: &Certificate) -> Result<(), Error> {
+    let tbs = tbs_bytes(cert.der())?;
+
+    let signature = cert
+        .signature()
+        .as_bytes()
+        .ok_or_else(|| Error::Invalid("signature has unused bits".to_owned()))?;
+
+    verify(
+        tbs,
+        cert.signature_algorithm(),
+        signature,
+        issuer.tbs_certificate().subject_public_key_info(),
+    )
+}
+
+/// Returns the encoded "to be signed" part of a signed structure (e.g., a certificate, CRL or basic
+/// OCSP response), which is always the first field of its outer SEQUENCE.
+///
+/// Signatures are checked over these original bytes rather than a re-encoding of the decoded value,
+/// which may differ.
+pub(crate) fn tbs_bytes(signed: &[u8]) -> Result<&[u8], Error> {
+    let first_field = |signed| -> der::Result<&[u8]> {
+        let mut reader = SliceReader::new(signed)?;
+        let header = reader.decode::<Header>()?;
+        header.tag().assert_eq(Tag::Sequence)?;
+        reader.tlv_bytes()
+    };
+
+    first_field(signed)
+        .map_err(|err| Error::Unsupported(format!("failed to decode signed data: {err}")))
+}
+
+/// Verifies `signature` over `message` using the given signature algorithm and public key.
+///
+/// Supports RSA PKCS#1 v1.5, RSA-PSS, ECDSA (P-256, P-384, P-521) and Ed25519.
+pub(crate) fn verify(
+    message: &[u8],
+    signature_alg: &AlgorithmIdentifierOwned,
+    signature: &[u8],
+    spki: &SubjectPublicKeyInfoOwned,
+) -> Result<(), Error> {
+    let sig_alg_id = alg_id_contents(signature_alg)?;
+    let key_alg_id = alg_id_contents(&spki.algorithm)?;
+
+    let alg = find_algorithm(&sig_alg_id, &key_alg_id).ok_or_else(|| {
+        Error::Unsupported(format!(
+            "unsupported signature algorithm {} with {} key",
+            util::oid_desc_or_raw(&signature_alg.oid),
+            util::oid_desc_or_raw(&spki.algorithm.oid),
+        ))
+    })?;
+
+    let public_key = spki
+        .subject_public_key
+        .as_bytes()
+        .ok_or_else(|| Error::Unsupported("public key has unused bits".to_owned()))?;
+
+    alg.verify_signature(public_key, message, signature)
+        .map_err(|_| Error::Invalid("signature mismatch".to_owned()))
+}
+
+fn find_algorithm(
+    sig_alg_id: &[u8],
+    key_alg_id: &[u8],
+) -> Option<&'static dyn SignatureVerificationAlgorithm> {
+    rustls::crypto::aws_lc_rs::default_provider()
+        .signature_verification_algorithms
+        .all
+        .iter()
+        .copied()
+        .find(|alg| {
+            alg.signature_alg_id().as_ref() == sig_alg_id
+                && alg.public_key_alg_id().as_ref() == key_alg_id
+        })
+}
+
+/// Returns DER encoding of algorithm identifier without its outer SEQUENCE header.
+///
+/// This is the form in which `rustls` algorithms expose their identifiers.
+fn alg_id_contents(alg: &AlgorithmIdentifierOwned) -> Result<Vec<u8>, Error> {
+    let der = alg
+        .to_der()
+        .map_err(|err| Error::Unsupported(format!("failed to encode algorithm: {err}")))?;
+
+    let seq = AnyRef::from_der(&der)
+        .map_err(|err| Error::Unsupported(format!("failed to decode algorithm: {err}")))?;
+
+    Ok(seq.value().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cert::fixtures;
+
+    #[test]
+    fn verifies_over_original_tbs_encoding() {
+        // validity uses GeneralizedTime before 2050, which is re-encoded as UTCTime
+        let certs = fixtures::certs("generalized-time.pem");
+        let cert = &certs[0];
+
+        assert_ne!(
+            tbs_bytes(cert.der()).unwrap(),
+            cert.tbs_certificate().to_der().unwrap(),
+        );
+
+        verify_cert(cert, cert).unwrap();
+        assert!(chain_statuses(&certs)[0].is_valid());
+    }
+
+    #[test]
+    fn detects_modified_tbs() {
+        let certs = fixtures::certs("generalized-time.pem");
+
+        // flip a byte in the subject common name
+        let mut der = certs[0].der().to_vec();
+        let pos = der
+            .windows(4)
+            .rposition(|window| window == b"Test")
+            .unwrap();
+        der[pos] ^= 0x20;
+        let modified = Cert::from_der(&der).unwrap();
+
+        assert!(matches!(
+            verify_cert(&modified, &certs[0]),
+            Err(Error::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn tbs_bytes_rejects_non_sequence() {
+        assert!(tbs_bytes(&[0x04, 0x00]).is_err());
+        assert!(tbs_bytes(&[]).is_err());
+    }
+}
@@ -0,0 +1,674 @@
+use std::io::{self, Read, Write};
+
+use eyre::{WrapErr as _, bail, eyre};
+
+/// Upper bound on the size of a single response read during the upgrade dialog.
+const MAX_RESPONSE_LEN: usize = 64 * 1_024;
+
+/// Plaintext protocols which can be upgraded to TLS before fetching the certificate chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum Protocol {
+    /// SMTP `STARTTLS` command.
+    Smtp,
+
+    /// IMAP `STARTTLS` command.
+    Imap,
+
+    /// POP3 `STLS` command.
+    Pop3,
+
+    /// FTP `AUTH TLS` command.
+    Ftp,
+
+    /// LDAP StartTLS extended operation.
+    Ldap,
+
+    /// XMPP client `<starttls/>` stream negotiation.
+    Xmpp,
+
+    /// PostgreSQL `SSLRequest` message.
+    Postgres,
+}
+
+impl Protocol {
+    /// Returns the standard port on which this protocol offers STARTTLS.
+    pub(crate) fn default_port(self) -> u16 {
+        match self {
+            Self::Smtp => 587,
+            Self::Imap => 143,
+            Self::Pop3 => 110,
+            Self::Ftp => 21,
+            Self::Ldap => 389,
+            Self::Xmpp => 5222,
+            Self::Postgres => 5432,
+        }
+    }
+}
+
+/// Performs the plaintext upgrade dialog so that the stream is ready for a TLS handshake.
+pub(crate) fn upgrade(
+    proto: Protocol,
+    stream: &mut (impl Read + Write),
+    host: &str,
+) -> eyre::Result<()> {
+    tracing::debug!(?proto, "performing STARTTLS upgrade");
+
+    match proto {
+        Protocol::Smtp => upgrade_smtp(stream),
+        Protocol::Imap => upgrade_imap(stream),
+        Protocol::Pop3 => upgrade_pop3(stream),
+        Protocol::Ftp => upgrade_ftp(stream),
+        Protocol::Ldap => upgrade_ldap(stream),
+        Protocol::Xmpp => upgrade_xmpp(stream, host),
+        Protocol::Postgres => upgrade_postgres(stream),
+    }
+    .wrap_err_with(|| format!("STARTTLS upgrade failed ({proto:?})"))
+}
+
+fn upgrade_smtp(stream: &mut (impl Read + Write)) -> eyre::Result<()> {
+    expect_reply(stream, "220", "greeting")?;
+
+    send_line(stream, "EHLO inspect-cert-chain")?;
+    let capabilities = expect_reply(stream, "250", "EHLO")?;
+
+    if !capabilities.iter().any(|line| {
+        line.get(4..)
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("STARTTLS"))
+    }) {
+        tracing::warn!("server did not advertise STARTTLS capability");
+    }
+
+    send_line(stream, "STARTTLS")?;
+    expect_reply(stream, "220", "STARTTLS")?;
+
+    Ok(())
+}
+
+fn upgrade_imap(stream: &mut (impl Read + Write)) -> eyre::Result<()> {
+    let greeting = read_line(stream)?;
+    if !greeting.starts_with("* OK") {
+        bail!("unexpected greeting: {greeting}");
+    }
+
+    send_line(stream, "a001 STARTTLS")?;
+
+    loop {
+        let line = read_line(stream)?;
+
+        // skip untagged responses
+        if line.starts_with("* ") {
+            continue;
+        }
+
+        if line.starts_with("a001 OK") {
+            return Ok(());
+        }
+
+        bail!("unexpected response to STARTTLS: {line}");
+    }
+}
+
+fn upgrade_pop3(stream: &mut (impl Read + Write)) -> eyre::Result<()> {
+    let greeting = read_line(stream)?;
+    if !greeting.starts_with("+OK") {
+        bail!("unexpected greeting: {greeting}");
+    }
+
+    send_line(stream, "STLS")?;
+
+    let line = read_line(stream)?;
+    if !line.starts_with("+OK") {
+        bail!("unexpected response to STLS: {line}");
+    }
+
+    Ok(())
+}
+
+fn upgrade_ftp(stream: &mut (impl Read + Write)) -> eyre::Result<()> {
+    expect_reply(stream, "220", "greeting")?;
+
+    send_line(stream, "AUTH TLS")?;
+    expect_reply(stream, "234", "AUTH TLS")?;
+
+    Ok(())
+}
+
+/// LDAPMessage containing an ExtendedRequest for StartTLS (OID 1.3.6.1.4.1.1466.20037).
+const LDAP_START_TLS_REQUEST: &[u8] = b"\x30\x1d\x02\x01\x01\x77\x18\x80\x16\
+    1.3.6.1.4.1.1466.20037";
+
+fn upgrade_ldap(stream: &mut (impl Read + Write)) -> eyre::Result<()> {
+    stream.write_all(LDAP_START_TLS_REQUEST)?;
+    stream.flush()?;
+
+    let (tag, msg) = read_ber_tlv(stream)?;
+    if tag != 0x30 {
+        bail!("unexpected LDAP message tag: {tag:#04x}");
+    }
+
+    let mut msg = msg.as_slice();
+
+    let (_, _message_id) = split_ber_tlv(&mut msg)?;
+    let (op_tag, mut response) = split_ber_tlv(&mut msg)?;
+
+    // [APPLICATION 24] ExtendedResponse
+    if op_tag != 0x78 {
+        bail!("unexpected LDAP protocol operation tag: {op_tag:#04x}");
+    }
+
+    let (result_tag, result_code) = split_ber_tlv(&mut response)?;
+    if result_tag != 0x0a || result_code.is_empty() {
+        bail!("malformed LDAP result code");
+    }
+
+    let (_, diagnostic) = split_ber_tlv(&mut response)
+        .and_then(|_matched_dn| split_ber_tlv(&mut response))
+        .unwrap_or_default();
+
+    match result_code {
+        [0] => Ok(()),
+        code => bail!(
+            "server refused StartTLS with result code {} ({})",
+            code.iter()
+                .fold(0_u32, |acc, &byte| acc << 8 | u32::from(byte)),
+            String::from_utf8_lossy(diagnostic),
+        ),
+    }
+}
+
+fn upgrade_xmpp(stream: &mut (impl Read + Write), host: &str) -> eyre::Result<()> {
+    let open = format!(
+        "<?xml version='1.0'?><stream:stream to='{}' xmlns='jabber:client' \
+        xmlns:stream='http://etherx.jabber.org/streams' version='1.0'>",
+        xml_escape(host),
+    );
+
+    stream.write_all(open.as_bytes())?;
+    stream.flush()?;
+
+    let features = read_until(stream, "</stream:features>")?;
+    if !features.contains("urn:ietf:params:xml:ns:xmpp-tls") {
+        tracing::warn!("server did not advertise STARTTLS stream feature");
+    }
+
+    stream.write_all(b"<starttls xmlns='urn:ietf:params:xml:ns:xmpp-tls'/>")?;
+    stream.flush()?;
+
+    let reply = read_until(stream, ">")?;
+    if !reply.contains("<proceed") {
+        bail!("server did not proceed with STARTTLS: {reply}");
+    }
+
+    Ok(())
+}
+
+/// Escapes text for use in an XML attribute value.
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\'' => escaped.push_str("&apos;"),
+            '"' => escaped.push_str("&quot;"),
+            ch => escaped.push(ch),
+        }
+    }
+
+    escaped
+}
+
+/// PostgreSQL SSLRequest: message length (8) followed by request code 80877103.
+const POSTGRES_SSL_REQUEST: &[u8] = &[0x00, 0x00, 0x00, 0x08, 0x04, 0xd2, 0x16, 0x2f];
+
+fn upgrade_postgres(stream: &mut (impl Read + Write)) -> eyre::Result<()> {
+    stream.write_all(POSTGRES_SSL_REQUEST)?;
+    stream.flush()?;
+
+    let mut reply = [0; 1];
+    stream.read_exact(&mut reply)?;
+
+    match &reply {
+        b"S" => Ok(()),
+        b"N" => bail!("server does not support SSL"),
+        [byte] => bail!("unexpected response to SSLRequest: {byte:#04x}"),
+    }
+}
+
+/// Reads a (possibly multi-line) reply in the style of SMTP and FTP, checking its status code.
+fn expect_reply(stream: &mut impl Read, code: &str, stage: &str) -> eyre::Result<Vec<String>> {
+    let mut lines = Vec::new();
+
+    loop {
+        let line = read_line(stream)?;
+
+        if line.len() < 3 || !line.starts_with(code) {
+            bail!("unexpected {stage} reply: {line}");
+        }
+
+        let last = line.as_bytes().get(3) != Some(&b'-');
+        lines.push(line);
+
+        if last {
+            return Ok(lines);
+        }
+    }
+}
+
+fn send_line(stream: &mut impl Write, line: &str) -> io::Result<()> {
+    tracing::trace!("STARTTLS: > {line}");
+    stream.write_all(line.as_bytes())?;
+    stream.write_all(b"\r\n")?;
+    stream.flush()
+}
+
+/// Reads a single CRLF or LF terminated line.
+///
+/// Reads one byte at a time so no data after the line is consumed.
+fn read_line(stream: &mut impl Read) -> eyre::Result<String> {
+    let mut line = read_until(stream, "\n")?;
+
+    line.truncate(line.trim_end_matches(['\r', '\n']).len());
+    tracing::trace!("STARTTLS: < {line}");
+
+    Ok(line)
+}
+
+/// Reads until `terminator` is seen, returning everything read.
+fn read_until(stream: &mut impl Read, terminator: &str) -> eyre::Result<String> {
+    let mut buf = Vec::new();
+    let mut byte = [0; 1];
+
+    while !buf.ends_with(terminator.as_bytes()) {
+        if buf.len() > MAX_RESPONSE_LEN {
+            bail!("response too long");
+        }
+
+        match stream.read(&mut byte)? {
+            0 => bail!("connection closed by server"),
+            _ => buf.push(byte[0]),
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// Reads a complete BER tag-length-value with a single byte tag and a definite length.
+fn read_ber_tlv(stream: &mut impl Read) -> eyre::Result<(u8, Vec<u8>)> {
+    let mut header = [0; 2];
+    stream.read_exact(&mut header)?;
+
+    let [tag, len] = header;
+
+    let len = if len & 0x80 == 0 {
+        usize::from(len)
+    } else {
+        let n_bytes = usize::from(len & 0x7f);
+        if n_bytes == 0 || n_bytes > 4 {
+            bail!("unsupported BER length encoding");
+        }
+
+        let mut len_bytes = [0; 4];
+        stream.read_exact(&mut len_bytes[4 - n_bytes..])?;
+        u32::from_be_bytes(len_bytes) as usize
+    };
+
+    // the length is read before any of the value so must be checked before allocating
+    if len > MAX_RESPONSE_LEN {
+        bail!("response too long ({len} bytes)");
+    }
+
+    let mut value = vec![0; len];
+    stream.read_exact(&mut value)?;
+
+    Ok((tag, value))
+}
+
+/// Splits a BER tag-length-value off the front of `buf`.
+fn split_ber_tlv<'a>(buf: &mut &'a [u8]) -> eyre::Result<(u8, &'a [u8])> {
+    let mut rdr = *buf;
+    let mut header = [0; 2];
+    rdr.read_exact(&mut header)
+        .map_err(|_| eyre!("truncated BER value"))?;
+
+    let [tag, len] = header;
+
+    let len = if len & 0x80 == 0 {
+        usize::from(len)
+    } else {
+        let n_bytes = usize::from(len & 0x7f);
+        if n_bytes == 0 || n_bytes > 4 || rdr.len() < n_bytes {
+            bail!("unsupported BER length encoding");
+        }
+
+        let (len_bytes, rest) = rdr.split_at(n_bytes);
+        rdr = rest;
+        len_bytes
+            .iter()
+            .fold(0_usize, |acc, &byte| acc << 8 | usize::from(byte))
+    };
+
+    if rdr.len() < len {
+        bail!("truncated BER value");
+    }
+
+    let (value, rest) = rdr.split_at(len);
+    *buf = rest;
+
+    Ok((tag, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::BufRead as _,
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    use super::*;
+
+    /// Server end of a test connection.
+    struct Peer {
+        rdr: io::BufReader<TcpStream>,
+        wrt: TcpStream,
+    }
+
+    impl Peer {
+        fn expect_line(&mut self, expected: &str) {
+            let mut line = String::new();
+            self.rdr.read_line(&mut line).unwrap();
+            assert_eq!(line, format!("{expected}\r\n"));
+        }
+
+        fn expect_bytes(&mut self, expected: &[u8]) {
+            let mut buf = vec![0; expected.len()];
+            self.rdr.read_exact(&mut buf).unwrap();
+            assert_eq!(buf, expected);
+        }
+
+        fn read_until(&mut self, terminator: u8) -> String {
+            let mut buf = Vec::new();
+            self.rdr.read_until(terminator, &mut buf).unwrap();
+            String::from_utf8(buf).unwrap()
+        }
+
+        fn send(&mut self, data: impl AsRef<[u8]>) {
+            self.wrt.write_all(data.as_ref()).unwrap();
+        }
+    }
+
+    /// Performs an upgrade against a local server running `server`.
+    fn upgrade_with(
+        proto: Protocol,
+        host: &str,
+        server: impl FnOnce(&mut Peer) + Send + 'static,
+    ) -> eyre::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+
+            server(&mut Peer {
+                rdr: io::BufReader::new(stream.try_clone().unwrap()),
+                wrt: stream,
+            });
+        });
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let res = upgrade(proto, &mut stream, host);
+
+        // unblock a server still waiting for the client before checking its assertions
+        drop(stream);
+        server.join().unwrap();
+
+        res
+    }
+
+    fn assert_err_contains(res: eyre::Result<()>, expected: &str) {
+        let err = format!("{:#}", res.unwrap_err());
+        assert!(
+            err.contains(expected),
+            "{err:?} does not contain {expected:?}"
+        );
+    }
+
+    #[test]
+    fn smtp() {
+        upgrade_with(Protocol::Smtp, "mail.test", |peer| {
+            peer.send("220-mail.test ESMTP\r\n220 ready\r\n");
+            peer.expect_line("EHLO inspect-cert-chain");
+            peer.send("250-mail.test\r\n250-PIPELINING\r\n250 STARTTLS\r\n");
+            peer.expect_line("STARTTLS");
+            peer.send("220 go ahead\r\n");
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn smtp_refused() {
+        let res = upgrade_with(Protocol::Smtp, "mail.test", |peer| {
+            peer.send("220 mail.test ESMTP\r\n");
+            peer.expect_line("EHLO inspect-cert-chain");
+            peer.send("250 mail.test\r\n");
+            peer.expect_line("STARTTLS");
+            peer.send("454 TLS not available\r\n");
+        });
+
+        assert_err_contains(res, "unexpected STARTTLS reply: 454 TLS not available");
+    }
+
+    #[test]
+    fn smtp_unavailable() {
+        let res = upgrade_with(Protocol::Smtp, "mail.test", |peer| {
+            peer.send("554 no service\r\n");
+        });
+
+        assert_err_contains(res, "unexpected greeting reply: 554 no service");
+    }
+
+    #[test]
+    fn imap() {
+        upgrade_with(Protocol::Imap, "imap.test", |peer| {
+            peer.send("* OK IMAP4rev1 ready\r\n");
+            peer.expect_line("a001 STARTTLS");
+            peer.send("* CAPABILITY IMAP4rev1 STARTTLS\r\na001 OK begin TLS\r\n");
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn imap_refused() {
+        let res = upgrade_with(Protocol::Imap, "imap.test", |peer| {
+            peer.send("* OK IMAP4rev1 ready\r\n");
+            peer.expect_line("a001 STARTTLS");
+            peer.send("a001 BAD STARTTLS not supported\r\n");
+        });
+
+        assert_err_contains(res, "unexpected response to STARTTLS: a001 BAD");
+    }
+
+    #[test]
+    fn pop3() {
+        upgrade_with(Protocol::Pop3, "pop.test", |peer| {
+            peer.send("+OK POP3 ready\r\n");
+            peer.expect_line("STLS");
+            peer.send("+OK begin TLS\r\n");
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn pop3_refused() {
+        let res = upgrade_with(Protocol::Pop3, "pop.test", |peer| {
+            peer.send("+OK POP3 ready\r\n");
+            peer.expect_line("STLS");
+            peer.send("-ERR command not supported\r\n");
+        });
+
+        assert_err_contains(res, "unexpected response to STLS: -ERR");
+    }
+
+    #[test]
+    fn ftp() {
+        upgrade_with(Protocol::Ftp, "ftp.test", |peer| {
+            peer.send("220 FTP ready\r\n");
+            peer.expect_line("AUTH TLS");
+            peer.send("234 AUTH TLS successful\r\n");
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn ftp_refused() {
+        let res = upgrade_with(Protocol::Ftp, "ftp.test", |peer| {
+            peer.send("220 FTP ready\r\n");
+            peer.expect_line("AUTH TLS");
+            peer.send("534 TLS not available\r\n");
+        });
+
+        assert_err_contains(res, "unexpected AUTH TLS reply: 534");
+    }
+
+    /// LDAPMessage with message ID 1 and an ExtendedResponse with the given result and diagnostic.
+    fn ldap_response(result_code: u8, diagnostic: &str) -> Vec<u8> {
+        let mut response = vec![0x0a, 0x01, result_code, 0x04, 0x00, 0x04];
+        response.push(diagnostic.len() as u8);
+        response.extend_from_slice(diagnostic.as_bytes());
+
+        let mut msg = vec![0x02, 0x01, 0x01, 0x78, response.len() as u8];
+        msg.extend(response);
+
+        let mut tlv = vec![0x30, msg.len() as u8];
+        tlv.extend(msg);
+        tlv
+    }
+
+    #[test]
+    fn ldap() {
+        upgrade_with(Protocol::Ldap, "ldap.test", |peer| {
+            peer.expect_bytes(LDAP_START_TLS_REQUEST);
+            peer.send(ldap_response(0, ""));
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn ldap_refused() {
+        let res = upgrade_with(Protocol::Ldap, "ldap.test", |peer| {
+            peer.expect_bytes(LDAP_START_TLS_REQUEST);
+            peer.send(ldap_response(2, "unsupported operation"));
+        });
+
+        assert_err_contains(
+            res,
+            "server refused StartTLS with result code 2 (unsupported operation)",
+        );
+    }
+
+    #[test]
+    fn ldap_oversized_response() {
+        let res = upgrade_with(Protocol::Ldap, "ldap.test", |peer| {
+            peer.expect_bytes(LDAP_START_TLS_REQUEST);
+            peer.send([0x30, 0x84, 0x7f, 0xff, 0xff, 0xff]);
+        });
+
+        assert_err_contains(res, "response too long (2147483647 bytes)");
+    }
+
+    #[test]
+    fn xmpp() {
+        upgrade_with(Protocol::Xmpp, "chat.test", |peer| {
+            peer.read_until(b'>'); // XML declaration
+            let open = peer.read_until(b'>');
+            assert!(open.contains("to='chat.test'"), "{open}");
+
+            peer.send(
+                "<?xml version='1.0'?><stream:stream from='chat.test' id='1' version='1.0' \
+                xmlns='jabber:client' xmlns:stream='http://etherx.jabber.org/streams'>\
+                <stream:features><starttls xmlns='urn:ietf:params:xml:ns:xmpp-tls'><required/>\
+                </starttls></stream:features>",
+            );
+
+            assert_eq!(
+                peer.read_until(b'>'),
+                "<starttls xmlns='urn:ietf:params:xml:ns:xmpp-tls'/>"
+            );
+            peer.send("<proceed xmlns='urn:ietf:params:xml:ns:xmpp-tls'/>");
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn xmpp_refused() {
+        let res = upgrade_with(Protocol::Xmpp, "chat.test", |peer| {
+            peer.read_until(b'>');
+            peer.read_until(b'>');
+            peer.send("<stream:stream><stream:features></stream:features>");
+            peer.read_until(b'>');
+            peer.send("<failure xmlns='urn:ietf:params:xml:ns:xmpp-tls'/>");
+        });
+
+        assert_err_contains(res, "server did not proceed with STARTTLS: <failure");
+    }
+
+    #[test]
+    fn xmpp_escapes_host() {
+        let res = upgrade_with(Protocol::Xmpp, "a'b&<c>", |peer| {
+            peer.read_until(b'>');
+            let open = peer.read_until(b'>');
+            assert!(open.contains("to='a&apos;b&amp;&lt;c&gt;'"), "{open}");
+        });
+
+        assert_err_contains(res, "connection closed by server");
+    }
+
+    #[test]
+    fn postgres() {
+        upgrade_with(Protocol::Postgres, "db.test", |peer| {
+            peer.expect_bytes(POSTGRES_SSL_REQUEST);
+            peer.send("S");
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn postgres_refused() {
+        let res = upgrade_with(Protocol::Postgres, "db.test", |peer| {
+            peer.expect_bytes(POSTGRES_SSL_REQUEST);
+            peer.send("N");
+        });
+
+        assert_err_contains(res, "server does not support SSL");
+    }
+
+    #[test]
+    fn postgres_error() {
+        let res = upgrade_with(Protocol::Postgres, "db.test", |peer| {
+            peer.expect_bytes(POSTGRES_SSL_REQUEST);
+            peer.send("E");
+        });
+
+        assert_err_contains(res, "unexpected response to SSLRequest: 0x45");
+    }
+
+    #[test]
+    fn connection_closed() {
+        let res = upgrade_with(Protocol::Imap, "imap.test", |_| {});
+
+        assert_err_contains(res, "connection closed by server");
+    }
+
+    #[test]
+    fn overlong_line() {
+        let res = upgrade_with(Protocol::Pop3, "pop.test", |peer| {
+            // client may close the connection before everything is written
+            let _ = peer.wrt.write_all(&vec![b'+'; MAX_RESPONSE_LEN + 2]);
+        });
+
+        assert_err_contains(res, "response too long");
+    }
+}
@@ -16,9 +16,8 @@ use ratatui::{
         ScrollbarState,
     },
 };
-use x509_cert::Certificate;
 
-use crate::{LINE_ENDING, info::write_cert_info};
+use crate::{LINE_ENDING, cert::Cert, info::write_cert_info, signature};
 
 pub type Tui = Terminal<CrosstermBackend<io::Stdout>>;
 
@@ -38,7 +37,7 @@ pub(crate) fn restore() -> io::Result<()> {
 
 #[derive(Debug)]
 pub(crate) struct App {
-    certs: Vec<(Certificate, String, usize)>,
+    certs: Vec<(Cert, signature::Status, String, usize)>,
     list_state: ListState,
     details_scroll: usize,
     exit: bool,
@@ -46,7 +45,7 @@ pub(crate) struct App {
 
 impl App {
     /// Constructs new TUI app widget.
-    pub(crate) fn new(certs: &[Certificate]) -> Self {
+    pub(crate) fn new(certs: &[Cert]) -> Self {
         Self {
             exit: false,
             list_state: ListState::default().with_selected(Some(0)),
@@ -54,10 +53,11 @@ impl App {
             certs: certs
                 .iter()
                 .cloned()
-                .map(|cert| {
+                .zip(signature::chain_statuses(certs))
+                .map(|(cert, signature)| {
                     let mut details = Vec::with_capacity(4_096); // roughly ~4Kb of output
 
-                    write_cert_info(&cert, &mut details, true)
+                    write_cert_info(&cert, &signature, &mut details, true)
                         .expect("io::Write-ing to a Vec always succeeds");
 
                     let details = String::from_utf8(details)
@@ -65,7 +65,7 @@ impl App {
 
                     let lines = details.lines().count();
 
-                    (cert, details, lines)
+                    (cert, signature, details, lines)
                 })
                 .collect(),
         }
@@ -134,7 +134,18 @@ impl App {
         let list = self
             .certs
             .iter()
-            .map(|(cert, _, _)| cert.tbs_certificate().subject().to_string())
+            .map(|(cert, signature, _, _)| {
+                let subject = cert.tbs_certificate().subject().to_string();
+
+                // mark each certificate with the outcome of its signature check
+                if signature.is_valid() {
+                    Line::from(vec!["✓ ".green(), subject.into()])
+                } else if signature.is_invalid() {
+                    Line::from(vec!["✗ ".red().bold(), subject.into()])
+                } else {
+                    Line::from(vec!["? ".yellow(), subject.into()])
+                }
+            })
             .collect::<List<'static>>();
 
         let instructions = Line::from(vec![
@@ -159,7 +170,7 @@ impl App {
     fn create_details(&self) -> (Paragraph<'static>, (Scrollbar<'static>, ScrollbarState)) {
         let selected = self.list_state.selected().unwrap();
 
-        let details = self.certs[selected].1.to_owned();
+        let details = self.certs[selected].2.to_owned();
 
         let scrollbar_state =
             ScrollbarState::new(details.lines().count()).position(self.details_scroll);
@@ -194,7 +205,7 @@ impl App {
     fn handle_key_event(&mut self, ev: event::KeyEvent) {
         let max = self.certs.len() - 1;
         let selected = self.list_state.selected().unwrap();
-        let selected_cert_lines = self.certs[selected].2.saturating_sub(1);
+        let selected_cert_lines = self.certs[selected].3.saturating_sub(1);
 
         match ev.code {
             event::KeyCode::Char('q') => {
@@ -3,12 +3,6 @@ use const_oid::{
     db::{DB, rfc5280, rfc5912, rfc6962},
 };
 use itertools::Itertools as _;
-use x509_cert::spki::AlgorithmIdentifierOwned;
-
-#[track_caller]
-pub(crate) fn assert_null_params(alg: &AlgorithmIdentifierOwned) {
-    assert!(alg.parameters.is_none() || alg.parameters.as_ref().unwrap().is_null());
-}
 
 pub(crate) fn oid_desc_or_raw(oid: &ObjectIdentifier) -> String {
     get_oid_desc(oid)
@@ -38,11 +32,11 @@ pub(crate) fn duration_since_now_fmt(time: x509_cert::time::Time) -> (String, bo
 }
 
 pub(crate) fn openssl_hex(bytes: &[u8], width: usize) -> impl Iterator<Item = String> + '_ {
-    let n_chunks = bytes.len() / width;
+    let last_chunk = bytes.len().saturating_sub(1) / width;
 
     bytes.chunks(width).enumerate().map(move |(i, chunk)| {
         let mut chunk = chunk.iter().map(|byte| format!("{byte:0>2x}:")).join("");
-        if i == n_chunks {
+        if i == last_chunk {
             let _ = chunk.pop();
         }
         chunk
@@ -68,6 +62,10 @@ const OID_DESCS: &[(&ObjectIdentifier, &str)] = &[
         "Authority Key Identifier",
     ),
     (&rfc5280::ID_CE_KEY_USAGE, "Key Usage"),
+    (
+        &rfc5280::ID_CE_PRIVATE_KEY_USAGE_PERIOD,
+        "Private Key Usage Period",
+    ),
     (&rfc5280::ID_CE_EXT_KEY_USAGE, "Extended Key Usage"),
     (&rfc5280::ID_CE_SUBJECT_ALT_NAME, "Subject Alternate Name"),
     (&rfc5912::ID_KP_CLIENT_AUTH, "Client Authentication"),
@@ -82,6 +80,14 @@ const OID_DESCS: &[(&ObjectIdentifier, &str)] = &[
         "CRL Distribution Points",
     ),
     (&rfc5912::ID_CE_CERTIFICATE_POLICIES, "Certificate Policies"),
+    (&rfc5280::ID_CE_NAME_CONSTRAINTS, "Name Constraints"),
+    (&rfc5280::ID_CE_POLICY_CONSTRAINTS, "Policy Constraints"),
+    (&rfc5280::ID_CE_POLICY_MAPPINGS, "Policy Mappings"),
+    (&rfc5280::ID_CE_INHIBIT_ANY_POLICY, "Inhibit anyPolicy"),
+    (
+        &ObjectIdentifier::new_unwrap("2.16.840.1.113730.1.1"),
+        "Netscape Cert Type",
+    ),
     (&rfc5912::ID_AD_OCSP, "OCSP"),
     (&rfc5912::ID_AD_CA_ISSUERS, "CA Issuers"),
     (&rfc6962::CT_PRECERT_SCTS, "CT Precertificate SCTs"),
@@ -110,3 +116,24 @@ const OID_DESCS: &[(&ObjectIdentifier, &str)] = &[
         "ACME Identifier",
     ),
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn openssl_hex_lines() {
+        assert_eq!(openssl_hex(&[], 3).count(), 0);
+        assert_eq!(openssl_hex(&[0x0a], 3).collect::<Vec<_>>(), ["0a"]);
+        assert_eq!(
+            openssl_hex(&[1, 2, 3, 4], 3).collect::<Vec<_>>(),
+            ["01:02:03:", "04"]
+        );
+
+        // no trailing colon when the last line is full
+        assert_eq!(
+            openssl_hex(&[1, 2, 3, 4, 5, 6], 3).collect::<Vec<_>>(),
+            ["01:02:03:", "04:05:06"]
+        );
+    }
+}
@@ -0,0 +1,576 @@
+use std::{fs, io, sync::Arc};
+
+use crossterm::style::Stylize as _;
+use eyre::{WrapErr as _, eyre};
+use rustls::client::danger::ServerCertVerifier as _;
+use rustls_pki_types::{CertificateDer, ServerName, UnixTime};
+use x509_cert::{
+    Certificate,
+    ext::pkix::{BasicConstraints, KeyUsage, SubjectAltName, name::GeneralName},
+};
+
+use crate::{
+    cert::{Cert, is_issuer, is_self_issued},
+    signature,
+};
+
+/// Source of trust anchors used when verifying a chain.
+#[derive(Debug)]
+pub(crate) enum TrustStore {
+    /// Platform certificate store, via `rustls-platform-verifier`.
+    Platform,
+
+    /// Trust anchors loaded from `--ca-file` and/or `--ca-dir`.
+    Custom { anchors: Vec<Cert>, source: String },
+}
+
+impl TrustStore {
+    /// Loads trust anchors from the given PEM bundle and/or directory of PEM files.
+    ///
+    /// Returns the platform store when neither is provided.
+    pub(crate) fn load(
+        ca_file: Option<&camino::Utf8Path>,
+        ca_dir: Option<&camino::Utf8Path>,
+    ) -> eyre::Result<Self> {
+        if ca_file.is_none() && ca_dir.is_none() {
+            return Ok(Self::Platform);
+        }
+
+        let mut anchors = Vec::new();
+        let mut sources = Vec::new();
+
+        if let Some(path) = ca_file {
+            anchors.extend(read_pem_certs(path)?);
+            sources.push(path.to_string());
+        }
+
+        if let Some(dir) = ca_dir {
+            anchors.extend(read_pem_dir(dir)?);
+            sources.push(format!("{dir}/"));
+        }
+
+        if anchors.is_empty() {
+            return Err(eyre!("no trust anchors found in {}", sources.join(", ")));
+        }
+
+        tracing::info!("loaded {} trust anchors", anchors.len());
+
+        Ok(Self::Custom {
+            anchors,
+            source: sources.join(", "),
+        })
+    }
+
+    fn anchors(&self) -> &[Cert] {
+        match self {
+            Self::Platform => &[],
+            Self::Custom { anchors, .. } => anchors,
+        }
+    }
+}
+
+/// Where the issuer of a certificate was found.
+#[derive(Debug)]
+pub(crate) enum Issuer {
+    /// Certificate at this index in the supplied chain.
+    Chain(usize),
+
+    /// Trust anchor at this index in the custom trust store.
+    Anchor(usize),
+
+    /// Certificate is self-issued.
+    SelfIssued,
+
+    /// Issuer was not found in the chain or trust store.
+    Missing,
+}
+
+/// Outcome of one check performed on a chain link.
+#[derive(Debug)]
+pub(crate) struct Check {
+    pub(crate) name: &'static str,
+    pub(crate) result: Result<(), String>,
+}
+
+impl Check {
+    pub(crate) fn new(name: &'static str, result: Result<(), String>) -> Self {
+        Self { name, result }
+    }
+}
+
+/// Verification results for a single certificate and its issuer.
+#[derive(Debug)]
+pub(crate) struct Link {
+    pub(crate) issuer: Issuer,
+    pub(crate) checks: Vec<Check>,
+}
+
+impl Link {
+    pub(crate) fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.result.is_ok())
+    }
+}
+
+/// Verification results for a whole chain.
+#[derive(Debug)]
+pub(crate) struct ChainReport {
+    pub(crate) links: Vec<Link>,
+
+    /// Overall path validation verdict from `webpki` or the platform verifier.
+    pub(crate) verdict: Result<(), String>,
+}
+
+/// Verifies chain, leaf first, against the given trust store.
+pub(crate) fn verify_chain(
+    certs: &[Cert],
+    store: &TrustStore,
+    server_name: Option<&str>,
+) -> eyre::Result<ChainReport> {
+    let now = UnixTime::now();
+
+    let links = certs
+        .iter()
+        .enumerate()
+        .map(|(idx, cert)| verify_link(certs, idx, cert, store.anchors(), now))
+        .collect();
+
+    let ders = certs
+        .iter()
+        .map(|cert| CertificateDer::from(cert.der()))
+        .collect::<Vec<_>>();
+
+    let (end_entity, intermediates) = ders
+        .split_first()
+        .ok_or_else(|| eyre!("chain contained 0 certificates"))?;
+
+    let verdict = match store {
+        TrustStore::Platform => {
+            verify_platform(certs, end_entity, intermediates, server_name, now)?
+        }
+        TrustStore::Custom { anchors, .. } => {
+            verify_webpki(anchors, end_entity, intermediates, now)?
+        }
+    };
+
+    Ok(ChainReport { links, verdict })
+}
+
+fn verify_link(certs: &[Cert], idx: usize, cert: &Cert, anchors: &[Cert], now: UnixTime) -> Link {
+    let tbs = cert.tbs_certificate();
+
+    let mut checks = vec![Check::new(
+        "within validity period",
+        check_validity(cert, now),
+    )];
+
+    let issuer = find_issuer(certs, idx, cert, anchors);
+
+    let issuer_cert = match issuer {
+        Issuer::Chain(idx) => Some(&certs[idx]),
+        Issuer::Anchor(idx) => Some(&anchors[idx]),
+        Issuer::SelfIssued => Some(cert),
+        Issuer::Missing => None,
+    };
+
+    match issuer_cert {
+        Some(issuer_cert) => {
+            if matches!(issuer, Issuer::Chain(issuer_idx) if issuer_idx != idx + 1) {
+                checks.push(Check::new(
+                    "issuer follows certificate",
+                    Err("issuer is present but out of order".to_owned()),
+                ));
+            }
+
+            checks.push(Check::new(
+                "issuer is a CA",
+                check_issuer_is_ca(issuer_cert),
+            ));
+
+            checks.push(Check::new(
+                "signed by issuer",
+                signature::verify_cert(cert, issuer_cert).map_err(|err| err.reason().to_owned()),
+            ));
+        }
+
+        None => checks.push(Check::new(
+            "issuer found",
+            Err(format!(
+                "no certificate with subject \"{}\" in chain or trust store",
+                tbs.issuer()
+            )),
+        )),
+    }
+
+    Link { issuer, checks }
+}
+
+fn find_issuer(certs: &[Cert], idx: usize, cert: &Certificate, anchors: &[Cert]) -> Issuer {
+    let anchor_idx = anchors.iter().position(|anchor| is_issuer(anchor, cert));
+
+    if is_self_issued(cert) {
+        return anchor_idx.map_or(Issuer::SelfIssued, Issuer::Anchor);
+    }
+
+    // prefer the next certificate in the chain, then trust anchors, then any other certificate
+    if certs.get(idx + 1).is_some_and(|next| is_issuer(next, cert)) {
+        return Issuer::Chain(idx + 1);
+    }
+
+    if let Some(anchor_idx) = anchor_idx {
+        return Issuer::Anchor(anchor_idx);
+    }
+
+    certs
+        .iter()
+        .enumerate()
+        .position(|(other_idx, other)| other_idx != idx && is_issuer(other, cert))
+        .map_or(Issuer::Missing, Issuer::Chain)
+}
+
+fn check_validity(cert: &Certificate, now: UnixTime) -> Result<(), String> {
+    let validity = cert.tbs_certificate().validity();
+    let now = now.as_secs();
+
+    if validity.not_before.to_unix_duration().as_secs() > now {
+        return Err(format!("not valid before {}", validity.not_before));
+    }
+
+    if validity.not_after.to_unix_duration().as_secs() < now {
+        return Err(format!("expired at {}", validity.not_after));
+    }
+
+    Ok(())
+}
+
+fn check_issuer_is_ca(issuer: &Certificate) -> Result<(), String> {
+    let tbs = issuer.tbs_certificate();
+
+    match tbs.get_extension::<BasicConstraints>() {
+        Ok(Some((_, constraints))) if constraints.ca => {}
+        Ok(Some(_)) => return Err("issuer basic constraints do not allow CA use".to_owned()),
+        Ok(None) => return Err("issuer has no basic constraints extension".to_owned()),
+        Err(err) => return Err(format!("issuer basic constraints are malformed: {err}")),
+    }
+
+    match tbs.get_extension::<KeyUsage>() {
+        Ok(Some((_, usage))) if !usage.key_cert_sign() => {
+            Err("issuer key usage does not allow certificate signing".to_owned())
+        }
+        Ok(_) => Ok(()),
+        Err(err) => Err(format!("issuer key usage is malformed: {err}")),
+    }
+}
+
+fn verify_webpki(
+    anchors: &[Cert],
+    end_entity: &CertificateDer<'_>,
+    intermediates: &[CertificateDer<'_>],
+    now: UnixTime,
+) -> eyre::Result<Result<(), String>> {
+    let anchor_ders = anchors
+        .iter()
+        .map(|cert| CertificateDer::from(cert.der()))
+        .collect::<Vec<_>>();
+
+    let anchors = anchor_ders
+        .iter()
+        .filter_map(|der| match webpki::anchor_from_trusted_cert(der) {
+            Ok(anchor) => Some(anchor),
+            Err(err) => {
+                tracing::warn!("skipping unusable trust anchor: {err}");
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let end_entity = match webpki::EndEntityCert::try_from(end_entity) {
+        Ok(end_entity) => end_entity,
+        Err(err) => return Ok(Err(format!("leaf certificate is unusable: {err}"))),
+    };
+
+    let provider = rustls::crypto::aws_lc_rs::default_provider();
+
+    Ok(end_entity
+        .verify_for_usage(
+            provider.signature_verification_algorithms.all,
+            &anchors,
+            intermediates,
+            now,
+            webpki::KeyUsage::server_auth(),
+            None,
+            None,
+        )
+        .map(|_| ())
+        .map_err(|err| err.to_string()))
+}
+
+fn verify_platform(
+    certs: &[Cert],
+    end_entity: &CertificateDer<'_>,
+    intermediates: &[CertificateDer<'_>],
+    server_name: Option<&str>,
+    now: UnixTime,
+) -> eyre::Result<Result<(), String>> {
+    // the platform verifier always checks the server name so, when none is given, use one
+    // which the leaf certificate claims to be valid for
+    let server_name = match server_name {
+        Some(name) => name.to_owned(),
+        None => leaf_dns_name(&certs[0])
+            .ok_or_else(|| eyre!("leaf certificate has no DNS name to verify against"))?,
+    };
+
+    let server_name = ServerName::try_from(server_name.as_str())
+        .with_context(|| format!("failed to convert \"{server_name}\" to server name"))?;
+
+    let provider = rustls::crypto::CryptoProvider::get_default()
+        .cloned()
+        .unwrap_or_else(|| Arc::new(rustls::crypto::aws_lc_rs::default_provider()));
+
+    let verifier = rustls_platform_verifier::Verifier::new(provider)?;
+
+    Ok(verifier
+        .verify_server_cert(end_entity, intermediates, &server_name, &[], now)
+        .map(|_| ())
+        .map_err(|err| err.to_string()))
+}
+
+fn leaf_dns_name(leaf: &Certificate) -> Option<String> {
+    let (_, san) = leaf
+        .tbs_certificate()
+        .get_extension::<SubjectAltName>()
+        .ok()??;
+
+    san.0.into_iter().find_map(|name| match name {
+        GeneralName::DnsName(dns) => Some(dns.as_str().trim_start_matches("*.").to_owned()),
+        _ => None,
+    })
+}
+
+/// Writes verification report in text form.
+pub(crate) fn write_report(
+    certs: &[Cert],
+    store: &TrustStore,
+    report: &ChainReport,
+    mut wrt: impl io::Write,
+) -> io::Result<()> {
+    writeln!(wrt, "Verification")?;
+    writeln!(wrt, "============")?;
+
+    match store {
+        TrustStore::Platform => writeln!(wrt, "Trust Store: platform")?,
+        TrustStore::Custom { anchors, source } => writeln!(
+            wrt,
+            "Trust Store: {} certificates from {source}",
+            anchors.len()
+        )?,
+    }
+
+    for (idx, (cert, link)) in certs.iter().zip(&report.links).enumerate() {
+        writeln!(wrt)?;
+        writeln!(
+            wrt,
+            "[{idx}] {} {}",
+            cert.tbs_certificate().subject(),
+            if link.passed() {
+                "pass".green().bold()
+            } else {
+                "FAIL".red().bold()
+            }
+        )?;
+
+        let issuer = match link.issuer {
+            Issuer::Chain(idx) => format!("[{idx}] {}", certs[idx].tbs_certificate().subject()),
+            Issuer::Anchor(idx) => format!(
+                "trust anchor {}",
+                store.anchors()[idx].tbs_certificate().subject()
+            ),
+            Issuer::SelfIssued => "self".to_owned(),
+            Issuer::Missing => "<not found>".to_owned(),
+        };
+        writeln!(wrt, "  Issued By: {issuer}")?;
+
+        for check in &link.checks {
+            match &check.result {
+                Ok(()) => writeln!(wrt, "  {} {}", "✓".green(), check.name)?,
+                Err(reason) => writeln!(wrt, "  {} {}: {reason}", "✗".red(), check.name)?,
+            }
+        }
+    }
+
+    writeln!(wrt)?;
+
+    match &report.verdict {
+        Ok(()) => writeln!(wrt, "Result: {}", "chain is trusted".green().bold())?,
+        Err(reason) => writeln!(
+            wrt,
+            "Result: {} ({reason})",
+            "chain is NOT trusted".red().bold()
+        )?,
+    }
+
+    Ok(())
+}
+
+/// Reads certificates from all PEM files (`.pem`, `.crt` or `.cer`) in a directory.
+pub(crate) fn read_pem_dir(dir: &camino::Utf8Path) -> eyre::Result<Vec<Cert>> {
+    let entries = fs::read_dir(dir).wrap_err_with(|| format!("could not read directory: {dir}"))?;
+
+    let mut certs = Vec::new();
+
+    for entry in entries {
+        let path = camino::Utf8PathBuf::try_from(entry?.path())?;
+
+        if !matches!(path.extension(), Some("pem" | "crt" | "cer")) {
+            tracing::debug!(%path, "skipping non-certificate file");
+            continue;
+        }
+
+        certs.extend(read_pem_certs(&path)?);
+    }
+
+    Ok(certs)
+}
+
+fn read_pem_certs(path: &camino::Utf8Path) -> eyre::Result<Vec<Cert>> {
+    use der::Decode as _;
+
+    let file = fs::File::open(path).wrap_err_with(|| format!("could not open file: {path}"))?;
+    let mut rdr = io::BufReader::new(file);
+
+    rustls_pemfile::certs(&mut rdr)
+        .map(|der| Ok(Cert::from_der(&der?)?))
+        .collect::<eyre::Result<Vec<_>>>()
+        .wrap_err_with(|| format!("could not read certificates from: {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cert::fixtures;
+
+    fn custom_store(ca_file: &str) -> TrustStore {
+        TrustStore::load(Some(&fixtures::path(ca_file)), None).unwrap()
+    }
+
+    fn failures(link: &Link) -> Vec<(&'static str, &str)> {
+        link.checks
+            .iter()
+            .filter_map(|check| Some((check.name, check.result.as_ref().err()?.as_str())))
+            .collect()
+    }
+
+    #[test]
+    fn trusted_chain() {
+        let certs = fixtures::chain(&["chain/leaf.pem", "chain/intermediate.pem"]);
+        let store = custom_store("chain/root.pem");
+
+        let report = verify_chain(&certs, &store, None).unwrap();
+
+        assert_eq!(report.verdict, Ok(()));
+        assert!(matches!(report.links[0].issuer, Issuer::Chain(1)));
+        assert!(matches!(report.links[1].issuer, Issuer::Anchor(0)));
+        assert!(report.links.iter().all(Link::passed));
+    }
+
+    #[test]
+    fn trusted_chain_including_root() {
+        let certs =
+            fixtures::chain(&["chain/leaf.pem", "chain/intermediate.pem", "chain/root.pem"]);
+        let store = custom_store("chain/root.pem");
+
+        let report = verify_chain(&certs, &store, None).unwrap();
+
+        assert_eq!(report.verdict, Ok(()));
+        assert!(matches!(report.links[1].issuer, Issuer::Chain(2)));
+
+        // the root is found in the trust store rather than treated as merely self-issued
+        assert!(matches!(report.links[2].issuer, Issuer::Anchor(0)));
+        assert!(report.links.iter().all(Link::passed));
+    }
+
+    #[test]
+    fn unknown_anchor() {
+        let certs = fixtures::chain(&["chain/leaf.pem", "chain/intermediate.pem"]);
+        let store = custom_store("pki/ca.pem");
+
+        let report = verify_chain(&certs, &store, None).unwrap();
+
+        assert!(report.verdict.is_err());
+        assert!(report.links[0].passed());
+        assert!(matches!(report.links[1].issuer, Issuer::Missing));
+        assert_eq!(
+            failures(&report.links[1]),
+            [(
+                "issuer found",
+                "no certificate with subject \"CN=Chain Root\" in chain or trust store"
+            )]
+        );
+    }
+
+    #[test]
+    fn non_ca_issuer() {
+        let certs = fixtures::chain(&["verify/issued-by-leaf.pem", "tls/client.pem"]);
+        let store = custom_store("tls/ca.pem");
+
+        let report = verify_chain(&certs, &store, None).unwrap();
+
+        assert!(report.verdict.is_err());
+        assert_eq!(
+            failures(&report.links[0]),
+            [(
+                "issuer is a CA",
+                "issuer basic constraints do not allow CA use"
+            )]
+        );
+        assert!(report.links[1].passed());
+    }
+
+    #[test]
+    fn expired_certificate() {
+        let certs = fixtures::certs("verify/expired.pem");
+        let store = custom_store("tls/ca.pem");
+
+        let report = verify_chain(&certs, &store, None).unwrap();
+
+        assert!(report.verdict.is_err());
+        assert!(matches!(report.links[0].issuer, Issuer::Anchor(0)));
+        assert_eq!(
+            failures(&report.links[0]),
+            [("within validity period", "expired at 2021-01-01T00:00:00Z")]
+        );
+    }
+
+    #[test]
+    fn out_of_order_issuer() {
+        let certs =
+            fixtures::chain(&["chain/intermediate.pem", "chain/root.pem", "chain/leaf.pem"]);
+        let store = custom_store("chain/root.pem");
+
+        let report = verify_chain(&certs, &store, None).unwrap();
+
+        assert!(matches!(report.links[2].issuer, Issuer::Chain(0)));
+        assert_eq!(
+            failures(&report.links[2]),
+            [(
+                "issuer follows certificate",
+                "issuer is present but out of order"
+            )]
+        );
+    }
+
+    #[test]
+    fn pem_dir() {
+        // keys and PKCS#12 archives are skipped
+        let certs = read_pem_dir(&fixtures::path("tls")).unwrap();
+        assert_eq!(certs.len(), 5);
+
+        let store = TrustStore::load(None, Some(&fixtures::path("chain"))).unwrap();
+        assert_eq!(store.anchors().len(), 4);
+
+        let err = read_pem_dir(&fixtures::path("missing")).unwrap_err();
+        assert!(
+            err.to_string().starts_with("could not read directory"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! Golden-file tests for `--format openssl`.
+//!
+//! Golden files hold the output of `openssl x509 -text -noout` (OpenSSL 3.x) for each certificate
+//! in the corresponding `roots/` or `tests/fixtures/` file, concatenated in order.
+
+use std::{fs, path::Path, process::Command};
+
+fn assert_matches_golden(name: &str) {
+    assert_file_matches_golden("roots", name);
+}
+
+fn assert_file_matches_golden(dir: &str, name: &str) {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_inspect-cert-chain"))
+        .arg("--file")
+        .arg(root.join(dir).join(format!("{name}.pem")))
+        .args(["--format", "openssl"])
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let golden = fs::read_to_string(
+        root.join("tests/golden")
+            .join(format!("{name}.openssl.txt")),
+    )
+    .unwrap();
+
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), golden);
+}
+
+#[test]
+fn autoridad() {
+    assert_matches_golden("Autoridad.crt");
+}
+
+#[test]
+fn digicert_tls_ecc_p384_root_g5() {
+    assert_matches_golden("DigiCertTLSECCP384RootG5.crt");
+}
+
+#[test]
+fn affirmtrust_commercial() {
+    assert_matches_golden("affirmtrust_commercial.crt");
+}
+
+#[test]
+fn certigna_root_ca() {
+    // several CRL distribution points
+    assert_matches_golden("CertignaRootCA.crt");
+}
+
+#[test]
+fn certigna() {
+    // Netscape Cert Type
+    assert_matches_golden("Certigna.crt");
+}
+
+#[test]
+fn entrust_root_certification_authority() {
+    // Private Key Usage Period
+    assert_matches_golden("EntrustRootCertificationAuthority.crt");
+}
+
+#[test]
+fn ietf_org_chain() {
+    assert_matches_golden("ietf-org-chain");
+}
+
+#[test]
+fn name_constraints_and_policy_controls() {
+    assert_file_matches_golden("tests/fixtures", "name-constraints");
+}
+
+#[test]
+fn large_rsa_exponent() {
+    assert_file_matches_golden("tests/fixtures", "large-exponent");
+}
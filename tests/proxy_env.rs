@@ -0,0 +1,113 @@
+//! Proxy selection from the environment for OCSP, CRL and CA Issuers requests.
+
+use std::{
+    io::{BufRead as _, BufReader, Write as _},
+    net::TcpListener,
+    path::Path,
+    process::Command,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+/// Runs the binary with `args` and `HTTPS_PROXY` pointing at a local proxy which rejects every
+/// request, returning the request lines it received.
+fn proxied_requests(args: &[&str], no_proxy: &str) -> Vec<String> {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    thread::spawn({
+        let requests = Arc::clone(&requests);
+
+        move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+
+                let mut line = String::new();
+                BufReader::new(&stream).read_line(&mut line).unwrap();
+                requests.lock().unwrap().push(line.trim_end().to_owned());
+
+                let _ = stream.write_all(b"HTTP/1.1 403 Forbidden\r\n\r\n");
+            }
+        }
+    });
+
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_inspect-cert-chain"))
+        .current_dir(fixtures)
+        .args(args)
+        .args(["--connect-timeout", "1", "--timeout", "1"])
+        .env("HTTPS_PROXY", format!("http://{addr}"))
+        .env("NO_PROXY", no_proxy)
+        .env_remove("https_proxy")
+        .env_remove("no_proxy")
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let mut requests = requests.lock().unwrap().clone();
+    requests.sort();
+    requests
+}
+
+#[test]
+fn revocation_checks_use_env_proxy() {
+    let requests = proxied_requests(
+        &[
+            "--file",
+            "pki/good.pem",
+            "--fetch-issuers",
+            "--cache-dir",
+            "aia-cache",
+            "--check-revocation",
+        ],
+        "",
+    );
+
+    assert_eq!(
+        requests,
+        [
+            "CONNECT crl.test:80 HTTP/1.1",
+            "CONNECT ocsp.test:80 HTTP/1.1"
+        ]
+    );
+}
+
+#[test]
+fn revocation_checks_honor_no_proxy() {
+    let requests = proxied_requests(
+        &[
+            "--file",
+            "pki/good.pem",
+            "--fetch-issuers",
+            "--cache-dir",
+            "aia-cache",
+            "--check-revocation",
+        ],
+        "crl.test",
+    );
+
+    assert_eq!(requests, ["CONNECT ocsp.test:80 HTTP/1.1"]);
+}
+
+#[test]
+fn issuer_downloads_use_env_proxy() {
+    let requests = proxied_requests(&["--file", "pki/good.pem", "--fetch-issuers"], "");
+
+    assert_eq!(requests, ["CONNECT ca.test:80 HTTP/1.1"]);
+}
+
+#[test]
+fn issuer_downloads_honor_no_proxy() {
+    // the direct connection fails as the host does not resolve
+    let requests = proxied_requests(&["--file", "pki/good.pem", "--fetch-issuers"], ".test");
+
+    assert_eq!(requests, Vec::<String>::new());
+}
@@ -0,0 +1,37 @@
+//! Exit status of `--verify`.
+
+use std::{path::Path, process::Command};
+
+fn verify(ca_file: &str) -> std::process::Output {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+
+    Command::new(env!("CARGO_BIN_EXE_inspect-cert-chain"))
+        .arg("--file")
+        .arg(fixtures.join("chain/leaf.pem"))
+        .arg("--verify")
+        .arg("--ca-file")
+        .arg(fixtures.join(ca_file))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn untrusted_chain_fails() {
+    // the intermediate is missing so the chain cannot reach either root
+    let output = verify("chain/root.pem");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("chain is NOT trusted"));
+}
+
+#[test]
+fn trusted_chain_succeeds() {
+    let output = verify("chain/intermediate.pem");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("chain is trusted"));
+}